@@ -9,23 +9,31 @@
 //!     -h, --help       Print help information
 //!     -r               Return the pest.rs rule/token stream
 //!     -j               Return the contract as json
+//!     -i <INIT>        Input to be used with the contract
+//!     --params-file    Read the input to be used with the contract from a JSON or TOML file
 //!     -V, --version    Print version information
 //!
 //! SUBCOMMANDS:
 //!     from-file              Read contract from .marlowe file
 //!     from-standard-input    Read raw marlowe contract from standard input
 //!     help                   Print this message or the help of the given subcommand(s)
+//!     params                 List the parameters that must be provided with -i for a .marlowe file
+//!     decode-datum           Decode a MarloweData datum (CBOR hex) and print the continuation contract
+//!     fmt                    Format .marlowe files in the layout of the Marlowe playground
 //! ```
 
-use std::collections::{HashSet, HashMap};
+use std::collections::HashSet;
 
 use marlowe_lang::parsing::{
-    deserialization::{deserialize, deserialize_with_input},
+    deserialization::{deserialize, deserialize_with_comments, deserialize_with_params_file},
     serialization::marlowe::serialize,
+    pretty::{format_with_comments, FormatOptions},
     Rule, MarloweParser, self
 };
 
 
+use marlowe_lang::types::{marlowe::Contract, template::TemplateInputs};
+
 use clap::{
     ArgEnum,
     Subcommand,
@@ -44,7 +52,24 @@ enum MyCommands {
     /// Read contract from .marlowe file
     FromFile {path: String} ,
     /// Read raw marlowe contract from standard input
-    FromStandardInput { contract: String }
+    FromStandardInput { contract: String },
+    /// List the parameters that must be provided with -i for a .marlowe file
+    Params {path: String},
+    /// Decode a MarloweData datum (CBOR hex) and print the continuation contract
+    DecodeDatum {hex: String},
+    /// Format .marlowe files in the layout of the Marlowe playground
+    Fmt {
+        paths: Vec<String>,
+        /// Do not write the files, only list the ones that are not formatted and exit with status 1 if there are any
+        #[clap(long)]
+        check: bool,
+        /// Indentation of arguments, relative to the opening parenthesis of their constructor
+        #[clap(long, default_value_t = 2)]
+        indent: usize,
+        /// The line width that lists of several items have to fit in to be kept on one line
+        #[clap(long, default_value_t = 80)]
+        width: usize
+    }
 }
 
 #[derive(ClapParser)]
@@ -61,10 +86,17 @@ struct Args {
     #[clap(short = 'r')]
     raw: bool,
     /// Input to be used with the contract.
-    /// Example 1: -d "my_constant_parameter=123, my_other_constant_parameter_name=321, timeout_number_one=2022-03-04@15:41:31"
-    /// Example 2: -d "timeout_number_one=4128381238132"
+    /// Times can be given as POSIX milliseconds, RFC-3339, 2022-03-04@15:41:31 (UTC) or relative to now (+3d, +12h, +30m).
+    /// Values can be given as integers, optionally followed by ADA or lovelace.
+    /// Example 1: -i "my_constant_parameter=123, my_other_constant_parameter_name=5 ADA, timeout_number_one=2022-03-04@15:41:31"
+    /// Example 2: -i "timeout_number_one=4128381238132"
     #[clap(short = 'i')]
-    init: Option<String>
+    init: Option<String>,
+    /// Read the input to be used with the contract from a JSON or TOML file (.toml) 
+    /// with the sections "time_params" and "value_params", mapping parameter names to values
+    /// in the same formats as for -i. All parameters of the contract must be given.
+    #[clap(long = "params-file", conflicts_with = "init")]
+    params_file: Option<String>
 }
 
 fn main() {
@@ -78,14 +110,29 @@ fn main() {
             }, 
             MyCommands::FromStandardInput { contract} => {
                 contract
+            },
+            MyCommands::Params { path } => {
+                print_parameters(read_from_file(path));
+                return
+            },
+            MyCommands::DecodeDatum { hex } => {
+                match marlowe_lang::plutus_data::MarloweData::from_cbor_hex(&hex) {
+                    Ok(datum) => println!("{}",serialize(datum.contract)),
+                    Err(e) => fail(e)
+                }
+                return
+            },
+            MyCommands::Fmt { paths, check, indent, width } => {
+                let ok = format_files(&paths,check,&FormatOptions { indent, width });
+                std::process::exit(if ok { 0 } else { 1 })
             }
         };
 
     match args.raw {
         true => {
 
-            if args.init.is_some() {
-                panic!("Can not use initial input data when tokenizing. Initialize the contract prior to calling this command.")
+            if args.init.is_some() || args.params_file.is_some() {
+                fail("Can not use initial input data when tokenizing. Initialize the contract prior to calling this command.")
             }
 
             let tokens = 
@@ -99,38 +146,29 @@ fn main() {
                     let json = serde_json::to_string_pretty(&v).unwrap();
                     println!("{}",json);
                 },
-                Err(e) => {
-                    println!("{:?}",e);
-                },
+                Err(e) => fail(e),
             }
             
             
         },
         _ => {  
             
-            let contract_initial_input = match args.init {
-                Some(v) => {
-                    let mut h = HashMap::new();
-                    for x in v.split(",") {
-                        let (name,value) = x.split_once("=").unwrap();
-                        let value_num = value.trim().parse::<i64>().unwrap();                        
-                        h.insert(name.trim().to_string(),value_num);
-                    }
-                    h
-                },
-                None => HashMap::new(),
+            let deserialized_instance = match &args.params_file {
+                Some(path) => 
+                    deserialize_with_params_file(&serialized_input,std::path::Path::new(path)).map_err(|e|format!("{e:#}")),
+                None => 
+                    deserialize_with_text_input(&serialized_input,args.init.as_deref())
             };
 
-            let deserialized_instance = 
-                deserialize_with_input(&serialized_input,contract_initial_input);
-
             match deserialized_instance {
                 Ok(c) => {
                     match args.json {
                         true => {
                             
-                            let json = parsing::serialization::json::serialize(c).unwrap();
-                            println!("{}",json);
+                            match parsing::serialization::json::serialize(c) {
+                                Ok(json) => println!("{}",json),
+                                Err(e) => fail(e)
+                            }
                         },
                         false => {
                             let serialized = serialize(c);
@@ -138,7 +176,7 @@ fn main() {
                         },
                     }
                 },
-                Err(e) => println!("{:#}",e),
+                Err(e) => fail(e),
             }
            
         }
@@ -147,11 +185,85 @@ fn main() {
 }
 
 
-fn read_from_file(path:String) -> String {
-    let path_exists = std::path::Path::new(&path).exists();
-    if path_exists {
-        std::fs::read_to_string(&path).expect("failed to read from file.").to_owned()
-    } else {
-        panic!("no such file exists.");
+fn deserialize_with_text_input(serialized_input:&str,init:Option<&str>) -> Result<Contract,String> {
+    let contract = deserialize(serialized_input).map_err(|e|format!("{e:#}"))?;
+    let init = match init {
+        Some(v) => v,
+        None => return Ok(contract)
+    };
+    let mut values = vec![];
+    for x in init.split(',') {
+        match x.split_once('=') {
+            Some((name,value)) => values.push((name.trim(),value.trim())),
+            None => return Err(format!("Expected name=value but found '{}'.",x.trim()))
+        }
+    }
+    TemplateInputs::from_text(&contract,values)
+        .and_then(|inputs|contract.instantiate(&inputs))
+        .map_err(|e|format!("{e}"))
+}
+
+fn print_parameters(serialized_input:String) {
+    let contract = match deserialize(&serialized_input) {
+        Ok(c) => c,
+        Err(e) => fail(format!("{e:#}"))
+    };
+    let params = marlowe_lang::analysis::parameters(&contract);
+    let print = |title:&str,params:&Vec<marlowe_lang::analysis::Parameter>| {
+        println!("{title}:");
+        if params.is_empty() {
+            println!("  (none)");
+        }
+        for p in params {
+            let paths : Vec<String> = p.paths.iter().map(|x|format!("{x:?}")).collect();
+            println!("  \"{}\" - {} occurrence(s) at {}",p.name,p.occurrences(),paths.join(", "));
+        }
+    };
+    print("Time parameters (POSIX milliseconds)",&params.time_params);
+    print("Value parameters (integers)",&params.value_params);
+    let example : Vec<String> = 
+        params.time_params.iter().chain(params.value_params.iter())
+            .map(|p|format!("{}=...",p.name)).collect();
+    if !example.is_empty() {
+        println!("Usage: -i \"{}\"",example.join(", "));
     }
+}
+
+/// Formats the files in place, or with check only reports the ones that would change.
+/// Returns false if a file could not be formatted, or with check if any file is not formatted.
+fn format_files(paths:&[String],check:bool,options:&FormatOptions) -> bool {
+    let mut ok = true;
+    for path in paths {
+        let source = read_from_file(path.clone());
+        let (contract,comments) = match deserialize_with_comments(&source) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{path}: {e:#}");
+                ok = false;
+                continue
+            }
+        };
+        let formatted = format_with_comments(&contract,&comments,options);
+        if formatted == source {
+            continue
+        }
+        if check {
+            println!("{path} is not formatted");
+            ok = false;
+        } else if let Err(e) = std::fs::write(path,formatted) {
+            eprintln!("{path}: {e}");
+            ok = false;
+        }
+    }
+    ok
+}
+
+fn read_from_file(path:String) -> String {
+    std::fs::read_to_string(&path).unwrap_or_else(|e|fail(format!("{path}: {e}")))
+}
+
+/// Reports the error on stderr and exits with status 1
+fn fail(error:impl std::fmt::Display) -> ! {
+    eprintln!("{error}");
+    std::process::exit(1)
 }
\ No newline at end of file
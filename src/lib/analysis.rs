@@ -0,0 +1,646 @@
+// Static checks for the situations that the Marlowe semantics would warn about at runtime.
+// See the ReduceWarning and ApplyWarning types in the semantics module.
+
+// note: only values that can be computed without running the contract are considered,
+//       anything depending on choices, time or account balances is skipped.
+
+use std::collections::{BTreeMap, HashSet};
+
+use num_traits::{Signed, Zero};
+
+use crate::parsing::spans::NodePath;
+use crate::types::address::Network;
+use crate::types::marlowe::*;
+
+/// Something in a contract that will (or may) cause a warning when the contract is executed
+#[derive(Debug,Clone,PartialEq)]
+pub struct Warning {
+    /// Location of the offending node, see [`NodePath`]
+    pub path: NodePath,
+    pub kind: WarningKind
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum WarningKind {
+    /// A deposit of a constant amount that is zero or negative
+    NonPositiveDeposit { amount: BigInt },
+    /// A payment of a constant amount that is zero or negative
+    NonPositivePay { amount: BigInt },
+    /// A Let that replaces a value which is already bound on the same path
+    Shadowing { value_id: String },
+    /// An Assert of an observation that is always false
+    AssertionAlwaysFails,
+    /// A DivValue whose divisor is always zero (the result will always be zero)
+    DivisionByZero,
+    /// A payment of more than could have been deposited into the account on this path
+    PartialPay { account: Party, token: Token, available: BigInt, expected: BigInt }
+}
+
+impl std::fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WarningKind::NonPositiveDeposit { amount } =>
+                write!(f, "The deposit amount is not positive ({amount})"),
+            WarningKind::NonPositivePay { amount } =>
+                write!(f, "The payment amount is not positive ({amount})"),
+            WarningKind::Shadowing { value_id } =>
+                write!(f, "The value \"{value_id}\" is already bound and will be shadowed"),
+            WarningKind::AssertionAlwaysFails =>
+                write!(f, "The assertion will always fail"),
+            WarningKind::DivisionByZero =>
+                write!(f, "Division by zero (the result will always be zero)"),
+            WarningKind::PartialPay { account, token, available, expected } =>
+                write!(f, "The payment of {expected} {token} from the account of {account} will only be partial, at most {available} can have been deposited"),
+        }
+    }
+}
+
+/// Evaluates a value if it does not depend on anything but constants
+pub fn constant_value(value:&Value) -> Option<BigInt> {
+    let eval = |v:&Slot<Box<Value>>| v.as_deref().and_then(constant_value);
+    match value {
+        Value::ConstantValue(n) => Some(n.clone()),
+        Value::NegValue(a) => Some(-eval(a)?),
+        Value::AddValue(a,b) => Some(eval(a)? + eval(b)?),
+        Value::SubValue(a,b) => Some(eval(a)? - eval(b)?),
+        Value::MulValue(a,b) => Some(eval(a)? * eval(b)?),
+        Value::DivValue(a,b) => {
+            let (n,d) = (eval(a)?,eval(b)?);
+            if d.is_zero() { Some(BigInt::zero()) } else { Some(n / d) }
+        },
+        Value::Cond(obs,a,b) => {
+            if constant_observation(obs.as_ref()?)? { eval(a) } else { eval(b) }
+        },
+        _ => None
+    }
+}
+
+/// Evaluates an observation if it does not depend on anything but constants
+pub fn constant_observation(observation:&Observation) -> Option<bool> {
+    let obs = |o:&Slot<Box<Observation>>| o.as_deref().and_then(constant_observation);
+    let val = |v:&Slot<Box<Value>>| v.as_deref().and_then(constant_value);
+    match observation {
+        Observation::True => Some(true),
+        Observation::False => Some(false),
+        Observation::NotObs { not } => Some(!obs(not)?),
+        Observation::AndObs { both, and } => match (obs(both),obs(and)) {
+            (Some(false),_) | (_,Some(false)) => Some(false),
+            (Some(true),Some(true)) => Some(true),
+            _ => None
+        },
+        Observation::OrObs { either, or } => match (obs(either),obs(or)) {
+            (Some(true),_) | (_,Some(true)) => Some(true),
+            (Some(false),Some(false)) => Some(false),
+            _ => None
+        },
+        Observation::ValueGE { value, ge_than } => Some(val(value)? >= val(ge_than)?),
+        Observation::ValueGT { value, gt_than } => Some(val(value)? > val(gt_than)?),
+        Observation::ValueLT { value, lt_than } => Some(val(value)? < val(lt_than)?),
+        Observation::ValueLE { value, le_than } => Some(val(value)? <= val(le_than)?),
+        Observation::ValueEQ { value, equal_to } => Some(val(value)? == val(equal_to)?),
+        Observation::ChoseSomething(_) => None
+    }
+}
+
+fn child(path:&[usize],index:usize) -> NodePath {
+    let mut p = path.to_vec();
+    p.push(index);
+    p
+}
+
+/// What is known about the contract at a specific point along a path
+#[derive(Clone,Default)]
+struct PathContext {
+    bound_values: HashSet<String>,
+    /// Upper bound of what can be in each account, None if it can not be known statically
+    balances: BTreeMap<(Party,Token),Option<BigInt>>
+}
+
+impl PathContext {
+    fn balance(&self,account:&Party,token:&Token) -> Option<BigInt> {
+        // accounts start out empty
+        match self.balances.get(&(account.clone(),token.clone())) {
+            Some(b) => b.clone(),
+            None => Some(BigInt::zero())
+        }
+    }
+    fn deposit(&mut self,account:&Party,token:&Token,amount:Option<&BigInt>) {
+        let new_balance = match (self.balance(account,token),amount) {
+            (Some(b),Some(a)) => Some(b + a.max(&BigInt::zero())),
+            _ => None
+        };
+        self.balances.insert((account.clone(),token.clone()),new_balance);
+    }
+}
+
+fn check_value(value:&Value,path:&[usize],out:&mut Vec<Warning>) {
+    let mut check = |v:&Slot<Box<Value>>,index| if let Slot::Filled(v) = v { check_value(v,&child(path,index),out) };
+    match value {
+        Value::DivValue(a,b) => {
+            check(a,0);
+            check(b,1);
+            if b.as_deref().and_then(constant_value).is_some_and(|x|x.is_zero()) {
+                out.push(Warning { path: path.to_vec(), kind: WarningKind::DivisionByZero })
+            }
+        },
+        Value::AddValue(a,b) | Value::SubValue(a,b) | Value::MulValue(a,b) => {
+            check(a,0);
+            check(b,1);
+        },
+        Value::NegValue(a) => check(a,0),
+        Value::Cond(obs,a,b) => {
+            if let Slot::Filled(o) = obs { check_observation(o,&child(path,0),out) }
+            let mut check = |v:&Slot<Box<Value>>,index| if let Slot::Filled(v) = v { check_value(v,&child(path,index),out) };
+            check(a,1);
+            check(b,2);
+        },
+        _ => {}
+    }
+}
+
+fn check_observation(observation:&Observation,path:&[usize],out:&mut Vec<Warning>) {
+    let mut check = |o:&Slot<Box<Observation>>,index| if let Slot::Filled(o) = o { check_observation(o,&child(path,index),out) };
+    match observation {
+        Observation::AndObs { both: a, and: b } | Observation::OrObs { either: a, or: b } => {
+            check(a,0);
+            check(b,1);
+        },
+        Observation::NotObs { not } => check(not,0),
+        Observation::ValueGE { value: a, ge_than: b } |
+        Observation::ValueGT { value: a, gt_than: b } |
+        Observation::ValueLT { value: a, lt_than: b } |
+        Observation::ValueLE { value: a, le_than: b } |
+        Observation::ValueEQ { value: a, equal_to: b } => {
+            if let Slot::Filled(v) = a { check_value(v,&child(path,0),out) }
+            if let Slot::Filled(v) = b { check_value(v,&child(path,1),out) }
+        },
+        _ => {}
+    }
+}
+
+fn check_action(action:&Action,path:&[usize],ctx:&mut PathContext,out:&mut Vec<Warning>) {
+    match action {
+        Action::Deposit { into_account, of_token, deposits, .. } => {
+            let amount = deposits.as_ref().and_then(constant_value);
+            if let Slot::Filled(v) = deposits { check_value(v,&child(path,3),out) }
+            if let Some(n) = &amount {
+                if !n.is_positive() {
+                    out.push(Warning { path: path.to_vec(), kind: WarningKind::NonPositiveDeposit { amount: n.clone() } })
+                }
+            }
+            if let (Slot::Filled(account),Slot::Filled(token)) = (into_account,of_token) {
+                ctx.deposit(account,token,amount.as_ref())
+            }
+        },
+        Action::Notify { notify_if: Slot::Filled(o) } => check_observation(o,&child(path,0),out),
+        _ => {}
+    }
+}
+
+fn check_contract(contract:&Contract,path:&[usize],ctx:&PathContext,out:&mut Vec<Warning>) {
+    match contract {
+        Contract::Close => {},
+        Contract::When { when, timeout_continuation, .. } => {
+            for (i,case) in when.iter().enumerate() {
+                let Slot::Filled(case) = case else { continue };
+                let case_path = child(&child(path,0),i);
+                let mut case_ctx = ctx.clone();
+                if let Some(action) = case.action() {
+                    check_action(action,&child(&case_path,0),&mut case_ctx,out)
+                }
+                if let Some(c) = case.continuation() {
+                    check_contract(c,&child(&case_path,1),&case_ctx,out)
+                }
+            }
+            if let Slot::Filled(c) = timeout_continuation {
+                check_contract(c,&child(path,2),ctx,out)
+            }
+        },
+        Contract::If { r#if, then, r#else } => {
+            if let Slot::Filled(o) = r#if { check_observation(o,&child(path,0),out) }
+            if let Slot::Filled(c) = then { check_contract(c,&child(path,1),ctx,out) }
+            if let Slot::Filled(c) = r#else { check_contract(c,&child(path,2),ctx,out) }
+        },
+        Contract::Assert { assert, then } => {
+            if let Slot::Filled(o) = assert {
+                check_observation(o,&child(path,0),out);
+                if constant_observation(o) == Some(false) {
+                    out.push(Warning { path: path.to_vec(), kind: WarningKind::AssertionAlwaysFails })
+                }
+            }
+            if let Slot::Filled(c) = then { check_contract(c,&child(path,1),ctx,out) }
+        },
+        Contract::Let { r#let, be, then } => {
+            if let Slot::Filled(v) = be { check_value(v,&child(path,1),out) }
+            let mut ctx = ctx.clone();
+            if !ctx.bound_values.insert(r#let.clone()) {
+                out.push(Warning { path: path.to_vec(), kind: WarningKind::Shadowing { value_id: r#let.clone() } })
+            }
+            if let Slot::Filled(c) = then { check_contract(c,&child(path,2),&ctx,out) }
+        },
+        Contract::Pay { from_account, to, token, pay, then } => {
+            if let Slot::Filled(v) = pay { check_value(v,&child(path,3),out) }
+            let mut ctx = ctx.clone();
+            let amount = pay.as_ref().and_then(constant_value);
+            if let Some(n) = &amount {
+                if !n.is_positive() {
+                    out.push(Warning { path: path.to_vec(), kind: WarningKind::NonPositivePay { amount: n.clone() } })
+                }
+            }
+            if let (Slot::Filled(account),Slot::Filled(token)) = (from_account,token) {
+                let available = ctx.balance(account,token);
+                if let (Some(available),Some(expected)) = (&available,&amount) {
+                    if expected > available {
+                        out.push(Warning {
+                            path: path.to_vec(),
+                            kind: WarningKind::PartialPay { account: account.clone(), token: token.clone(), available: available.clone(), expected: expected.clone() }
+                        })
+                    }
+                }
+                let paid = match (&available,&amount) {
+                    (Some(b),Some(a)) => Some(a.max(&BigInt::zero()).min(b).clone()),
+                    _ => None
+                };
+                let remaining = match (available,&paid) {
+                    (Some(b),Some(p)) => Some(b - p),
+                    (Some(b),None) => Some(b),
+                    _ => None
+                };
+                ctx.balances.insert((account.clone(),token.clone()),remaining);
+                if let Slot::Filled(Payee::Account(Slot::Filled(target))) = to {
+                    ctx.deposit(target,token,paid.as_ref())
+                }
+            }
+            if let Slot::Filled(c) = then { check_contract(c,&child(path,4),&ctx,out) }
+        }
+    }
+}
+
+/// Statically finds the situations in which the Marlowe semantics would emit warnings
+/// when executing the contract, such as non-positive deposits and payments,
+/// shadowed Let bindings, failing assertions and partial payments.
+pub fn warnings(contract:&Contract) -> Vec<Warning> {
+    let mut out = vec![];
+    check_contract(contract,&[],&PathContext::default(),&mut out);
+    out
+}
+
+/// A nested When whose timeout is not later than the timeout of an enclosing When,
+/// making (some of) its cases unreachable
+#[derive(Debug,Clone,PartialEq)]
+pub struct TimeoutViolation {
+    /// Location of the nested When, see [`NodePath`]
+    pub path: NodePath,
+    pub outer: BigInt,
+    pub inner: BigInt
+}
+
+/// An ordering between two timeouts that must hold for all cases to be reachable
+#[derive(Debug,Clone,PartialEq)]
+pub struct TimeoutConstraint {
+    pub earlier: Timeout,
+    pub later: Timeout,
+    /// Location of the first nested When that requires this ordering, see [`NodePath`]
+    pub path: NodePath
+}
+
+impl std::fmt::Display for TimeoutConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} < {}",self.earlier,self.later)
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct TimeoutReport {
+    /// Constant timeouts that are not increasing along a path
+    pub violations: Vec<TimeoutViolation>,
+    /// Orderings involving time parameters which can only be checked once the parameters are known
+    pub constraints: Vec<TimeoutConstraint>
+}
+
+/// `enclosing` is the timeout of the nearest enclosing When, which the constraints between parameters
+/// are taken against, and `latest` is the latest constant timeout of all enclosing Whens
+fn check_timeouts_in(contract:&Contract,path:&[usize],enclosing:Option<&Timeout>,latest:Option<&BigInt>,report:&mut TimeoutReport) {
+    let mut latest = latest;
+    if let Contract::When { timeout: Slot::Filled(timeout), .. } = contract {
+        if let Timeout::TimeConstant(inner) = timeout {
+            match latest {
+                Some(outer) if inner <= outer =>
+                    report.violations.push(TimeoutViolation { path: path.to_vec(), outer: outer.clone(), inner: inner.clone() }),
+                _ => latest = Some(inner)
+            }
+        }
+        match (enclosing,timeout) {
+            (Some(Timeout::TimeConstant(_)),Timeout::TimeConstant(_)) => {},
+            (Some(outer),inner) if !report.constraints.iter().any(|x|&x.earlier == outer && &x.later == inner) =>
+                report.constraints.push(TimeoutConstraint { earlier: outer.clone(), later: inner.clone(), path: path.to_vec() }),
+            _ => {}
+        }
+    }
+    let mut check = |c:&Slot<Box<Contract>>,path:NodePath,enclosing| if let Slot::Filled(c) = c { check_timeouts_in(c,&path,enclosing,latest,report) };
+    match contract {
+        Contract::Close => {},
+        Contract::When { when, timeout, timeout_continuation } => {
+            // a hole does not tell us anything, so keep comparing against the enclosing timeout
+            let enclosing = timeout.as_ref().or(enclosing);
+            for (i,case) in when.iter().enumerate() {
+                if let Slot::Filled(Case::Case { then, .. }) = case {
+                    check(then,child(&child(&child(path,0),i),1),enclosing);
+                }
+            }
+            check(timeout_continuation,child(path,2),enclosing);
+        },
+        Contract::If { then, r#else, .. } => {
+            check(then,child(path,1),enclosing);
+            check(r#else,child(path,2),enclosing);
+        },
+        Contract::Assert { then, .. } => check(then,child(path,1),enclosing),
+        Contract::Let { then, .. } => check(then,child(path,2),enclosing),
+        Contract::Pay { then, .. } => check(then,child(path,4),enclosing)
+    }
+}
+
+/// Checks that the timeouts of nested When contracts are increasing along every path
+/// (through both cases and timeout continuations). Constant timeouts are compared with the
+/// latest constant timeout that encloses them, also across time parameters, while the orderings that must hold between time parameters are returned as constraints.
+pub fn check_timeouts(contract:&Contract) -> TimeoutReport {
+    let mut report = TimeoutReport::default();
+    check_timeouts_in(contract,&[],None,None,&mut report);
+    report
+}
+
+/// A template parameter and everywhere it is used
+#[derive(Debug,Clone,PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    /// Location of each occurrence, in the order they appear in the contract, see [`NodePath`]
+    pub paths: Vec<NodePath>
+}
+
+impl Parameter {
+    pub fn occurrences(&self) -> usize {
+        self.paths.len()
+    }
+}
+
+/// The parameters that must be provided in order to instantiate a contract
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct Parameters {
+    /// Timeout::TimeParam (POSIX milliseconds)
+    pub time_params: Vec<Parameter>,
+    /// Value::ConstantParam (integers)
+    pub value_params: Vec<Parameter>
+}
+
+fn add_parameter(params:&mut Vec<Parameter>,name:&str,path:&[usize]) {
+    match params.iter_mut().find(|x|x.name == name) {
+        Some(p) => p.paths.push(path.to_vec()),
+        None => params.push(Parameter { name: name.to_string(), paths: vec![path.to_vec()] })
+    }
+}
+
+fn params_in_value(value:&Value,path:&[usize],out:&mut Parameters) {
+    let mut collect = |v:&Slot<Box<Value>>,index| if let Slot::Filled(v) = v { params_in_value(v,&child(path,index),out) };
+    match value {
+        Value::ConstantParam(name) => add_parameter(&mut out.value_params,name,path),
+        Value::AddValue(a,b) | Value::SubValue(a,b) | Value::MulValue(a,b) | Value::DivValue(a,b) => {
+            collect(a,0);
+            collect(b,1);
+        },
+        Value::NegValue(a) => collect(a,0),
+        Value::Cond(obs,a,b) => {
+            if let Slot::Filled(o) = obs { params_in_observation(o,&child(path,0),out) }
+            let mut collect = |v:&Slot<Box<Value>>,index| if let Slot::Filled(v) = v { params_in_value(v,&child(path,index),out) };
+            collect(a,1);
+            collect(b,2);
+        },
+        _ => {}
+    }
+}
+
+fn params_in_observation(observation:&Observation,path:&[usize],out:&mut Parameters) {
+    let mut collect = |o:&Slot<Box<Observation>>,index| if let Slot::Filled(o) = o { params_in_observation(o,&child(path,index),out) };
+    match observation {
+        Observation::AndObs { both: a, and: b } | Observation::OrObs { either: a, or: b } => {
+            collect(a,0);
+            collect(b,1);
+        },
+        Observation::NotObs { not } => collect(not,0),
+        Observation::ValueGE { value: a, ge_than: b } |
+        Observation::ValueGT { value: a, gt_than: b } |
+        Observation::ValueLT { value: a, lt_than: b } |
+        Observation::ValueLE { value: a, le_than: b } |
+        Observation::ValueEQ { value: a, equal_to: b } => {
+            if let Slot::Filled(v) = a { params_in_value(v,&child(path,0),out) }
+            if let Slot::Filled(v) = b { params_in_value(v,&child(path,1),out) }
+        },
+        _ => {}
+    }
+}
+
+fn params_in_contract(contract:&Contract,path:&[usize],out:&mut Parameters) {
+    match contract {
+        Contract::Close => {},
+        Contract::When { when, timeout, timeout_continuation } => {
+            for (i,case) in when.iter().enumerate() {
+                let Slot::Filled(case) = case else { continue };
+                let case_path = child(&child(path,0),i);
+                match case.action() {
+                    Some(Action::Deposit { deposits: Slot::Filled(v), .. }) => params_in_value(v,&child(&child(&case_path,0),3),out),
+                    Some(Action::Notify { notify_if: Slot::Filled(o) }) => params_in_observation(o,&child(&child(&case_path,0),0),out),
+                    _ => {}
+                }
+                if let Some(c) = case.continuation() {
+                    params_in_contract(c,&child(&case_path,1),out)
+                }
+            }
+            if let Slot::Filled(Timeout::TimeParam(name)) = timeout {
+                add_parameter(&mut out.time_params,name,&child(path,1))
+            }
+            if let Slot::Filled(c) = timeout_continuation {
+                params_in_contract(c,&child(path,2),out)
+            }
+        },
+        Contract::If { r#if, then, r#else } => {
+            if let Slot::Filled(o) = r#if { params_in_observation(o,&child(path,0),out) }
+            if let Slot::Filled(c) = then { params_in_contract(c,&child(path,1),out) }
+            if let Slot::Filled(c) = r#else { params_in_contract(c,&child(path,2),out) }
+        },
+        Contract::Assert { assert, then } => {
+            if let Slot::Filled(o) = assert { params_in_observation(o,&child(path,0),out) }
+            if let Slot::Filled(c) = then { params_in_contract(c,&child(path,1),out) }
+        },
+        Contract::Let { be, then, .. } => {
+            if let Slot::Filled(v) = be { params_in_value(v,&child(path,1),out) }
+            if let Slot::Filled(c) = then { params_in_contract(c,&child(path,2),out) }
+        },
+        Contract::Pay { pay, then, .. } => {
+            if let Slot::Filled(v) = pay { params_in_value(v,&child(path,3),out) }
+            if let Slot::Filled(c) = then { params_in_contract(c,&child(path,4),out) }
+        }
+    }
+}
+
+/// Finds all time and value parameters of a contract template,
+/// in the order they first appear in the contract.
+pub fn parameters(contract:&Contract) -> Parameters {
+    let mut out = Parameters::default();
+    params_in_contract(contract,&[],&mut out);
+    out
+}
+
+/// Where Address parties are used in a contract, grouped by the network of the address
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct NetworkReport {
+    pub mainnet: Vec<NodePath>,
+    pub testnet: Vec<NodePath>
+}
+
+impl NetworkReport {
+    /// True if the contract uses addresses from both mainnet and a testnet,
+    /// which means that it can not be executed on either of them
+    pub fn is_mixed(&self) -> bool {
+        !self.mainnet.is_empty() && !self.testnet.is_empty()
+    }
+    /// The network that the addresses of the contract belong to,
+    /// None if it has no addresses or if it mixes networks
+    pub fn network(&self) -> Option<Network> {
+        match (self.mainnet.is_empty(),self.testnet.is_empty()) {
+            (false,true) => Some(Network::Mainnet),
+            (true,false) => Some(Network::Testnet),
+            _ => None
+        }
+    }
+}
+
+/// A party or token somewhere in a contract
+pub(crate) enum Leaf<'a> {
+    Party(&'a Party),
+    Token(&'a Token)
+}
+
+type Visitor<'a,'b> = &'b mut dyn FnMut(Leaf<'a>,&[usize]);
+
+fn party_leaf<'a>(party:&'a Slot<Party>,path:&[usize],f:Visitor<'a,'_>) {
+    if let Slot::Filled(p) = party { f(Leaf::Party(p),path) }
+}
+
+fn token_leaf<'a>(token:&'a Slot<Token>,path:&[usize],f:Visitor<'a,'_>) {
+    if let Slot::Filled(t) = token { f(Leaf::Token(t),path) }
+}
+
+fn choice_leaves<'a>(choice:&'a Slot<ChoiceId>,path:&[usize],f:Visitor<'a,'_>) {
+    if let Slot::Filled(c) = choice { party_leaf(&c.choice_owner,&child(path,1),f) }
+}
+
+fn leaves_in_value<'a>(value:&'a Value,path:&[usize],f:Visitor<'a,'_>) {
+    match value {
+        Value::AvailableMoney(party,token) => {
+            party_leaf(party,&child(path,0),f);
+            token_leaf(token,&child(path,1),f)
+        },
+        Value::ChoiceValue(choice) => choice_leaves(choice,&child(path,0),f),
+        Value::AddValue(a,b) | Value::SubValue(a,b) | Value::MulValue(a,b) | Value::DivValue(a,b) => {
+            if let Slot::Filled(v) = a { leaves_in_value(v,&child(path,0),f) }
+            if let Slot::Filled(v) = b { leaves_in_value(v,&child(path,1),f) }
+        },
+        Value::NegValue(Slot::Filled(v)) => leaves_in_value(v,&child(path,0),f),
+        Value::Cond(obs,a,b) => {
+            if let Slot::Filled(o) = obs { leaves_in_observation(o,&child(path,0),f) }
+            if let Slot::Filled(v) = a { leaves_in_value(v,&child(path,1),f) }
+            if let Slot::Filled(v) = b { leaves_in_value(v,&child(path,2),f) }
+        },
+        _ => {}
+    }
+}
+
+fn leaves_in_observation<'a>(observation:&'a Observation,path:&[usize],f:Visitor<'a,'_>) {
+    match observation {
+        Observation::AndObs { both: a, and: b } | Observation::OrObs { either: a, or: b } => {
+            if let Slot::Filled(o) = a { leaves_in_observation(o,&child(path,0),f) }
+            if let Slot::Filled(o) = b { leaves_in_observation(o,&child(path,1),f) }
+        },
+        Observation::NotObs { not: Slot::Filled(o) } => leaves_in_observation(o,&child(path,0),f),
+        Observation::ChoseSomething(choice) => choice_leaves(choice,&child(path,0),f),
+        Observation::ValueGE { value: a, ge_than: b } |
+        Observation::ValueGT { value: a, gt_than: b } |
+        Observation::ValueLT { value: a, lt_than: b } |
+        Observation::ValueLE { value: a, le_than: b } |
+        Observation::ValueEQ { value: a, equal_to: b } => {
+            if let Slot::Filled(v) = a { leaves_in_value(v,&child(path,0),f) }
+            if let Slot::Filled(v) = b { leaves_in_value(v,&child(path,1),f) }
+        },
+        Observation::NotObs { not: Slot::Hole(_) } | Observation::True | Observation::False => {}
+    }
+}
+
+fn leaves_in_contract<'a>(contract:&'a Contract,path:&[usize],f:Visitor<'a,'_>) {
+    match contract {
+        Contract::Close => {},
+        Contract::When { when, timeout_continuation, .. } => {
+            for (i,case) in when.iter().enumerate() {
+                let Slot::Filled(case) = case else { continue };
+                let action_path = child(&child(&child(path,0),i),0);
+                match case.action() {
+                    Some(Action::Deposit { into_account, party, of_token, deposits }) => {
+                        party_leaf(into_account,&child(&action_path,0),f);
+                        party_leaf(party,&child(&action_path,1),f);
+                        token_leaf(of_token,&child(&action_path,2),f);
+                        if let Slot::Filled(v) = deposits { leaves_in_value(v,&child(&action_path,3),f) }
+                    },
+                    Some(Action::Choice { for_choice, .. }) => choice_leaves(for_choice,&child(&action_path,0),f),
+                    Some(Action::Notify { notify_if: Slot::Filled(o) }) => leaves_in_observation(o,&child(&action_path,0),f),
+                    _ => {}
+                }
+                if let Some(c) = case.continuation() {
+                    leaves_in_contract(c,&child(&child(&child(path,0),i),1),f)
+                }
+            }
+            if let Slot::Filled(c) = timeout_continuation {
+                leaves_in_contract(c,&child(path,2),f)
+            }
+        },
+        Contract::If { r#if, then, r#else } => {
+            if let Slot::Filled(o) = r#if { leaves_in_observation(o,&child(path,0),f) }
+            if let Slot::Filled(c) = then { leaves_in_contract(c,&child(path,1),f) }
+            if let Slot::Filled(c) = r#else { leaves_in_contract(c,&child(path,2),f) }
+        },
+        Contract::Assert { assert, then } => {
+            if let Slot::Filled(o) = assert { leaves_in_observation(o,&child(path,0),f) }
+            if let Slot::Filled(c) = then { leaves_in_contract(c,&child(path,1),f) }
+        },
+        Contract::Let { be, then, .. } => {
+            if let Slot::Filled(v) = be { leaves_in_value(v,&child(path,1),f) }
+            if let Slot::Filled(c) = then { leaves_in_contract(c,&child(path,2),f) }
+        },
+        Contract::Pay { from_account, to, token, pay, then } => {
+            party_leaf(from_account,&child(path,0),f);
+            if let Slot::Filled(Payee::Party(p) | Payee::Account(p)) = to { party_leaf(p,&child(&child(path,1),0),f) }
+            token_leaf(token,&child(path,2),f);
+            if let Slot::Filled(v) = pay { leaves_in_value(v,&child(path,3),f) }
+            if let Slot::Filled(c) = then { leaves_in_contract(c,&child(path,4),f) }
+        }
+    }
+}
+
+/// Calls f with every party and token in the contract, along with its path
+pub(crate) fn visit_leaves<'a>(contract:&'a Contract,f:Visitor<'a,'_>) {
+    leaves_in_contract(contract,&[],f)
+}
+
+/// Finds the networks of all Address parties in a contract,
+/// see [`NetworkReport::is_mixed`] for detecting mainnet and testnet addresses in the same contract.
+pub fn check_networks(contract:&Contract) -> NetworkReport {
+    let mut out = NetworkReport::default();
+    visit_leaves(contract,&mut |leaf,path| {
+        if let Leaf::Party(Party::Address { address }) = leaf {
+            // invalid addresses are reported by the parser, so they are not repeated here
+            match crate::types::address::network(address) {
+                Ok(Network::Mainnet) => out.mainnet.push(path.to_vec()),
+                Ok(Network::Testnet) => out.testnet.push(path.to_vec()),
+                Err(_) => {}
+            }
+        }
+    });
+    out
+}
@@ -24,9 +24,17 @@
 //! 
 //! - De-serialize from Marlowe.
 //! - Serialize to Marlowe.
-//! - Serialize to Marlowe 'core' JSON (experimental).
+//! - Serialize to Marlowe 'core' and 'extended' JSON (experimental).
+//! - De-serialize from Marlowe 'core' and 'extended' JSON (experimental).
 //! - Initialize Marlowe DSL based contracts with parameter input values (experimental).
 //! - Tokenize Marlowe DSL contracts to allow for deeper contract inspection and validation.
+//! - Execute contracts using the Marlowe small-step semantics (experimental).
+//! - Statically detect situations that would cause warnings at runtime (experimental).
+//! - Encode and decode contracts as Plutus Data CBOR (experimental).
+//! - Hash contracts and merkleize case continuations (experimental).
+//! - Validate key hashes, currency symbols and token names (on request, and always when encoding Plutus Data).
+//! - Edit contracts in place through a lossless syntax tree, keeping their layout and comments (experimental).
+//! - Keep the names of holes such as `?deposit` when parsing and serializing contracts.
 //!  
 //! ## Main entry-points:
 //! 
@@ -57,14 +65,14 @@
 //! 
 //! let my_contract = Contract::When {
 //!     when: vec![
-//!         Some(Case { 
-//!             case: Some(Action::Notify { 
-//!                 notify_if: Some(Observation::True)
+//!         Slot::Filled(Case::Case { 
+//!             case: Slot::Filled(Action::Notify { 
+//!                 notify_if: Slot::Filled(Observation::True)
 //!             }), 
-//!             then: Some(Contract::Close.boxed()) })
+//!             then: Slot::Filled(Contract::Close.boxed()) })
 //!     ],
-//!     timeout: Some(Timeout::TimeParam("test".into())),
-//!     timeout_continuation: Some(Contract::Close.boxed()),
+//!     timeout: Slot::Filled(Timeout::TimeParam("test".into())),
+//!     timeout_continuation: Slot::Filled(Contract::Close.boxed()),
 //! };
 //! 
 //! let serialized = serialize(my_contract);
@@ -74,10 +82,15 @@
 //! 
 //! #### Where 'println!("{serialized}")' would output this:
 //! ```text
-//! When [ Case (Notify (True)) Close ] (TimeParam "test") Close
+//! When [ (Case (Notify TrueObs) Close) ] (TimeParam "test") Close
 //! ``` 
 
 
+/// Runs the examples in the readme as doctests
+#[cfg(doctest)]
+#[doc = include_str!("../../readme.md")]
+pub struct ReadmeDoctests;
+
 #[macro_use] extern crate pest;
 #[macro_use] extern crate pest_derive;
 mod macros;
@@ -88,6 +101,21 @@ pub mod types;
 /// Where the parsing happens
 pub mod parsing;
 
+/// Reference semantics for executing contracts
+pub mod semantics;
+
+/// Static analysis of contracts
+pub mod analysis;
+
+/// Plutus Data (CBOR) encoding of contracts for on-chain datums
+pub mod plutus_data;
+
+/// Contract hashing and merkleization of case continuations
+pub mod merkleization;
+
+/// Validation of key hashes, currency symbols and token names
+pub mod validation;
+
 // Some testing yeh
 mod tests;
 
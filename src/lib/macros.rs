@@ -4,44 +4,40 @@ mod macros {
     macro_rules! Impl_From_For {
 
         (@$x:ident,$y:ident) => {
-            impl From<AstNode> for Result<$x,String> {
+            impl From<AstNode> for Result<$x,$crate::parsing::error::ParseError> {
                 fn from(x: AstNode) -> Self { 
-                    let expected = stringify!($x); 
-                    match x {
-                        AstNode::$y(xx) => Ok(xx),
-                        hmm => Err(format!("Expected {}, received: {:?}....",expected,hmm).to_string())
-                    }
+                    <$x>::try_from(x)
                 }
             }
             impl TryFrom<AstNode> for $x {
-                type Error = String;
+                type Error = $crate::parsing::error::ParseError;
                 fn try_from(x: AstNode) -> std::result::Result<Self,Self::Error> {
                     let expected = stringify!($x);
                     match x {
                         AstNode::$y(xx) => Ok(xx),
-                        hmm => Err(format!("Expected {}, received: {:?}...",expected,hmm).to_string())
+                        hmm => Err($crate::parsing::error::ParseError::TypeMismatch { span: None, expected: expected.to_string(), found: format!("{hmm:?}") })
                     }
                 }
             }
-            impl TryFrom<AstNode> for Option<$x> {
-                type Error = String;
+            impl TryFrom<AstNode> for Slot<$x> {
+                type Error = $crate::parsing::error::ParseError;
                 fn try_from(a: AstNode) -> std::result::Result<Self,Self::Error> {
                     let expected = stringify!($x);
                     match a {
-                        AstNode::$y(xx) => Ok(Some(xx)),
-                        AstNode::Null => Ok(None),
-                        hmm => Err(format!("Expected {}, received: {:?}..",expected,hmm).to_string())
+                        AstNode::$y(xx) => Ok(Slot::Filled(xx)),
+                        AstNode::Hole(name) => Ok(Slot::Hole(name)),
+                        hmm => Err($crate::parsing::error::ParseError::TypeMismatch { span: None, expected: expected.to_string(), found: format!("{hmm:?}") })
                     }
                 }
             }
-            impl TryFrom<AstNode> for Option<Box<$x>> {
-                type Error = String;
+            impl TryFrom<AstNode> for Slot<Box<$x>> {
+                type Error = $crate::parsing::error::ParseError;
                 fn try_from(a: AstNode) -> std::result::Result<Self,Self::Error> {
                     let expected = stringify!($x);
                     match a {
-                        AstNode::$y(b) => Ok(Some(Box::new(b))),
-                        AstNode::Null => Ok(None),
-                        hmm => Err(format!("Expected {}, received: {:?}.",expected,hmm).to_string())
+                        AstNode::$y(b) => Ok(Slot::Filled(Box::new(b))),
+                        AstNode::Hole(name) => Ok(Slot::Hole(name)),
+                        hmm => Err($crate::parsing::error::ParseError::TypeMismatch { span: None, expected: expected.to_string(), found: format!("{hmm:?}") })
                     }
                 }
             }
@@ -52,24 +48,24 @@ mod macros {
     #[doc(hidden)]
     macro_rules! Impl_From_For_Vec {
         (@$vectype:ident,@$innerwrappertype:ident,@$innertype:ident) => {
-            impl TryFrom<AstNode> for Vec<Option<$innertype>> {
-                type Error = String;
-                fn try_from(x: AstNode) -> Result<Vec<Option<$innertype>>,Self::Error> { match x {
+            impl TryFrom<AstNode> for Vec<Slot<$innertype>> {
+                type Error = $crate::parsing::error::ParseError;
+                fn try_from(x: AstNode) -> Result<Vec<Slot<$innertype>>,Self::Error> { match x {
                     AstNode::$vectype(items) => {
                         let mut result = vec![];
                         for item in items {
                             match item {
-                                AstNode::$innerwrappertype(x) => result.push(Some(x)),
-                                AstNode::Null => result.push(None),
+                                AstNode::$innerwrappertype(x) => result.push(Slot::Filled(x)),
+                                AstNode::Hole(name) => result.push(Slot::Hole(name)),
                                 xxx => {
                                     let expected = stringify!($innerwrappertype);
-                                    return Err(format!("Expected AstNode::{expected}, found: {xxx:?}"))
+                                    return Err($crate::parsing::error::ParseError::TypeMismatch { span: None, expected: expected.to_string(), found: format!("{xxx:?}") })
                                 }
                             }
                         };
                         Ok(result)
                     },
-                    xx => Err(format!("Expected array, found: {xx:?}"))
+                    xx => Err($crate::parsing::error::ParseError::TypeMismatch { span: None, expected: String::from("array"), found: format!("{xx:?}") })
                 }}
             }
         }
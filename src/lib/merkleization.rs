@@ -0,0 +1,120 @@
+// Merkleization replaces the continuation of a Case with the hash of that
+// continuation, so that large contracts fit in a datum. The continuation then
+// has to be supplied together with the input that selects the case.
+// Hashes are blake2b-256 over the Plutus Data (CBOR) encoding of the contract,
+// which is what the Marlowe validator uses.
+
+use std::collections::BTreeMap;
+
+use blake2::{Blake2b, Digest};
+use blake2::digest::consts::U32;
+
+use crate::plutus_data::{to_cbor, PlutusDataError};
+use crate::types::marlowe::*;
+
+/// Continuations of merkleized cases, keyed by their hash (hex)
+pub type ContinuationStore = BTreeMap<String,Contract>;
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum MerkleizationError {
+    /// The contract could not be encoded, for example because it contains holes
+    Encoding(PlutusDataError),
+    /// A merkleized case refers to a continuation that is not in the store
+    MissingContinuation(String)
+}
+
+impl std::fmt::Display for MerkleizationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MerkleizationError::Encoding(e) => write!(f, "Failed to encode the contract: {e}"),
+            MerkleizationError::MissingContinuation(hash) => write!(f, "No continuation with the hash {hash} was found."),
+        }
+    }
+}
+
+impl std::error::Error for MerkleizationError {}
+
+impl From<PlutusDataError> for MerkleizationError {
+    fn from(e: PlutusDataError) -> Self {
+        MerkleizationError::Encoding(e)
+    }
+}
+
+/// Hex encoded blake2b-256 hash of the Plutus Data encoding of a contract
+pub fn contract_hash(contract:&Contract) -> Result<String,PlutusDataError> {
+    Ok(hash_bytes(&to_cbor(contract)?))
+}
+
+fn hash_bytes(bytes:&[u8]) -> String {
+    hex::encode(Blake2b::<U32>::digest(bytes))
+}
+
+/// Replaces the continuations of cases with their hashes, starting with the innermost ones.
+/// With a threshold, only continuations whose (already merkleized) encoding is at least
+/// that many bytes are replaced; without one, all of them are.
+/// Returns the merkleized contract along with the continuations that were taken out of it.
+pub fn merkleize(contract:&Contract,threshold:Option<usize>) -> Result<(Contract,ContinuationStore),MerkleizationError> {
+    let mut store = ContinuationStore::new();
+    let result = merkleize_contract(contract,threshold,&mut store)?;
+    Ok((result,store))
+}
+
+/// Puts the continuations of merkleized cases back into the contract, the inverse of [`merkleize`]
+pub fn rehydrate(contract:&Contract,store:&ContinuationStore) -> Result<Contract,MerkleizationError> {
+    map_contract(contract,&mut |case| match case {
+        Case::MerkleizedCase { case, then } => match store.get(then) {
+            Some(c) => Ok(Case::Case { case: case.clone(), then: Slot::Filled(Box::new(rehydrate(c,store)?)) }),
+            None => Err(MerkleizationError::MissingContinuation(then.clone()))
+        },
+        Case::Case { case, then } => Ok(Case::Case {
+            case: case.clone(),
+            then: then.map_ref(|c|rehydrate(c,store).map(Box::new)).transpose()?
+        })
+    })
+}
+
+fn merkleize_contract(contract:&Contract,threshold:Option<usize>,store:&mut ContinuationStore) -> Result<Contract,MerkleizationError> {
+    map_contract(contract,&mut |case| match case {
+        Case::Case { case, then: Slot::Filled(then) } => {
+            let then = merkleize_contract(then,threshold,store)?;
+            let bytes = to_cbor(&then)?;
+            if threshold.is_none_or(|t| bytes.len() >= t) {
+                let hash = hash_bytes(&bytes);
+                store.insert(hash.clone(),then);
+                Ok(Case::MerkleizedCase { case: case.clone(), then: hash })
+            } else {
+                Ok(Case::Case { case: case.clone(), then: Slot::Filled(Box::new(then)) })
+            }
+        },
+        other => Ok(other.clone())
+    })
+}
+
+/// Rebuilds a contract, using f for each case. Nested cases are left to f.
+fn map_contract<F>(contract:&Contract,f:&mut F) -> Result<Contract,MerkleizationError>
+where F: FnMut(&Case) -> Result<Case,MerkleizationError> {
+    let sub = |x:&Slot<Box<Contract>>,f:&mut F|
+        x.map_ref(|c|map_contract(c,f).map(Box::new)).transpose();
+    Ok(match contract {
+        Contract::Close => Contract::Close,
+        Contract::When { when, timeout, timeout_continuation } => Contract::When {
+            when: when.iter().map(|case|case.map_ref(&mut *f).transpose()).collect::<Result<_,_>>()?,
+            timeout: timeout.clone(),
+            timeout_continuation: sub(timeout_continuation,f)?
+        },
+        Contract::If { r#if, then, r#else } => Contract::If {
+            r#if: r#if.clone(),
+            then: sub(then,f)?,
+            r#else: sub(r#else,f)?
+        },
+        Contract::Assert { assert, then } => Contract::Assert { assert: assert.clone(), then: sub(then,f)? },
+        Contract::Let { r#let, be, then } => Contract::Let { r#let: r#let.clone(), be: be.clone(), then: sub(then,f)? },
+        Contract::Pay { from_account, to, token, pay, then } => Contract::Pay {
+            from_account: from_account.clone(),
+            to: to.clone(),
+            token: token.clone(),
+            pay: pay.clone(),
+            then: sub(then,f)?
+        }
+    })
+}
@@ -0,0 +1,158 @@
+// Comments are skipped by the grammar like whitespace, so they are collected
+// from the source after parsing and attached to the nodes they belong to:
+//
+//   - a comment that follows a node on the same line is a trailing comment of
+//     that node: `(Constant 5) -- five lovelace`
+//   - any other comment is a leading comment of the node that follows it,
+//     or a trailing comment of the whole contract if nothing follows it.
+
+use std::collections::BTreeMap;
+
+use crate::parsing::error::Span;
+use crate::parsing::spans::{NodePath, SpanTable};
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Placement {
+    /// The comment is written before the node
+    Before,
+    /// The comment is written after the node, on the same line
+    After
+}
+
+/// A comment in the Marlowe DSL: either `-- ...` up to the end of the line,
+/// or `{- ... -}` which may span several lines and be nested.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Comment {
+    /// The comment as written, including the delimiters
+    pub text: String,
+    pub placement: Placement
+}
+
+impl Comment {
+    /// Line comments have to be followed by a line break
+    pub fn is_line_comment(&self) -> bool {
+        self.text.starts_with("--")
+    }
+}
+
+/// Side table with the comments attached to the nodes of a contract, see [`NodePath`]
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct CommentTable {
+    comments: BTreeMap<NodePath,Vec<Comment>>
+}
+
+impl CommentTable {
+
+    /// Attaches a comment to the node at the given path, after any comments it already has
+    pub fn insert(&mut self,path:NodePath,comment:Comment) {
+        self.comments.entry(path).or_default().push(comment)
+    }
+
+    /// The comments attached to the node at the given path, in the order they were written
+    pub fn get(&self,path:&[usize]) -> &[Comment] {
+        self.comments.get(path).map(|x|x.as_slice()).unwrap_or_default()
+    }
+
+    /// All nodes that have comments, ordered by path
+    pub fn iter(&self) -> impl Iterator<Item=(&NodePath,&Vec<Comment>)> {
+        self.comments.iter()
+    }
+
+    /// The comments of the node at the given path and of all nodes below it
+    pub fn below(&self,path:&[usize]) -> impl Iterator<Item=&Comment> {
+        let path = path.to_vec();
+        self.comments.range(path.clone()..)
+            .take_while(move |(p,_)| p.starts_with(&path))
+            .flat_map(|(_,x)|x.iter())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+}
+
+/// Finds the comments in a source that has been parsed successfully.
+/// Quoted strings and holes are skipped, so "--" inside of them is not taken for a comment.
+fn find_comments(source:&str) -> Vec<Span> {
+    let bytes = source.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' { i += 1 }
+                    i += 1;
+                }
+                i += 1;
+            },
+            b'?' => {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'-' || bytes[i] == b'_') {
+                    i += 1;
+                }
+            },
+            b'-' | b'{' => match comment_end(source,i) {
+                Some(end) => {
+                    out.push(Span { start: i, end });
+                    i = end;
+                },
+                None => i += 1
+            },
+            _ => i += 1
+        }
+    }
+    out
+}
+
+/// The end of the comment that starts at the given offset, if there is one there.
+/// Block comments that are not closed run to the end of the source.
+pub(crate) fn comment_end(source:&str,start:usize) -> Option<usize> {
+    let rest = &source.as_bytes()[start..];
+    if rest.starts_with(b"--") {
+        return Some(start + rest.iter().position(|x|*x == b'\n' || *x == b'\r').unwrap_or(rest.len()))
+    }
+    if !rest.starts_with(b"{-") {
+        return None
+    }
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with(b"{-") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with(b"-}") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 { break }
+        } else {
+            i += 1;
+        }
+    }
+    Some(start + i.min(rest.len()))
+}
+
+/// Attaches the comments of a parsed source to the nodes in its span table
+pub(crate) fn collect(source:&str,spans:&SpanTable) -> CommentTable {
+    let mut table = CommentTable::default();
+    for comment in find_comments(source) {
+        let text = source[comment.start..comment.end].to_string();
+        let line_start = source[..comment.start].rfind('\n').map(|x|x + 1).unwrap_or(0);
+        // the outermost of the nodes that end last before the comment on the same line
+        let preceding = spans.iter()
+            .filter(|(_,s)| s.end > line_start && s.end <= comment.start)
+            .max_by(|(a,x),(b,y)| x.end.cmp(&y.end).then(b.len().cmp(&a.len())));
+        // the outermost of the nodes that start first after the comment
+        let following = spans.iter()
+            .filter(|(_,s)| s.start >= comment.end)
+            .min_by(|(a,x),(b,y)| x.start.cmp(&y.start).then(a.len().cmp(&b.len())));
+        let (path,placement) = match (preceding,following) {
+            (Some((path,_)),_) => (path.clone(),Placement::After),
+            (None,Some((path,_))) => (path.clone(),Placement::Before),
+            (None,None) => (vec![],Placement::After)
+        };
+        table.insert(path,Comment { text, placement })
+    }
+    table
+}
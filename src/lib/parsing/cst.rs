@@ -0,0 +1,451 @@
+// Lossless concrete syntax tree of the Marlowe DSL.
+//
+// The tree is built from the pest pairs of a contract. Whatever the pairs do not
+// cover (keywords, parentheses, whitespace and comments) is kept as tokens between
+// the nodes, so the tree always holds exactly the source it was parsed from.
+//
+// Edits are typed: a node can only be replaced by a node of the same kind, such as
+// a contract by a contract or a party hole by a party. They are made on the source
+// text and the tree is parsed again afterwards, so the layout, the comments and the
+// names of holes are kept everywhere except for the nodes that were replaced.
+
+use pest::iterators::Pair;
+
+use crate::parsing::{MarloweParser, Rule};
+use crate::parsing::comments::comment_end;
+use crate::parsing::error::{ParseError, Span};
+use crate::parsing::pretty::{format_node, FormatOptions};
+use crate::parsing::spans::NodePath;
+use crate::types::marlowe::*;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum TokenKind {
+    Whitespace,
+    /// A `-- ...` or `{- ... -}` comment
+    Comment,
+    /// The name of a constructor, such as `When`
+    Keyword,
+    /// Parentheses, brackets, commas and the quotes around strings
+    Punctuation
+}
+
+impl TokenKind {
+    /// Whitespace and comments
+    pub fn is_trivia(&self) -> bool {
+        matches!(self,TokenKind::Whitespace|TokenKind::Comment)
+    }
+}
+
+/// A piece of source text between the nodes of the tree
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub span: Span
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken)
+}
+
+impl SyntaxElement {
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(x) => x.span,
+            SyntaxElement::Token(x) => x.span
+        }
+    }
+}
+
+/// A node of the grammar, such as a contract, a party, a string or a hole
+#[derive(Debug,Clone,PartialEq)]
+pub struct SyntaxNode {
+    pub rule: Rule,
+    pub span: Span,
+    /// Location of the node, the same as in a [`crate::parsing::spans::SpanTable`]
+    pub path: NodePath,
+    /// The sub-nodes and the tokens around them. Nodes that are a single piece
+    /// of text (Close, numbers, strings, holes, ...) have no children.
+    pub children: Vec<SyntaxElement>
+}
+
+impl SyntaxNode {
+
+    /// The sub-nodes, without the tokens around them
+    pub fn nodes(&self) -> impl Iterator<Item=&SyntaxNode> {
+        self.children.iter().filter_map(|x| match x {
+            SyntaxElement::Node(n) => Some(n),
+            SyntaxElement::Token(_) => None
+        })
+    }
+
+    pub fn is_hole(&self) -> bool {
+        matches!(self.rule,
+            Rule::PartyHole | Rule::FromPartyHole | Rule::ContractHole | Rule::PayeeHole |
+            Rule::ValueHole | Rule::ObservationHole | Rule::TimeoutHole | Rule::TokenHole |
+            Rule::BoundHole | Rule::CaseHole | Rule::ActionHole)
+    }
+
+    fn collect_holes<'a>(&'a self,out:&mut Vec<&'a SyntaxNode>) {
+        if self.is_hole() {
+            out.push(self)
+        }
+        for x in self.nodes() {
+            x.collect_holes(out)
+        }
+    }
+}
+
+/// The kinds of nodes that can be replaced, see [`Replacement`]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum NodeKind {
+    Contract,
+    Case,
+    Action,
+    Observation,
+    Value,
+    Party,
+    Payee,
+    Token,
+    Timeout,
+    Bound,
+    ChoiceId
+}
+
+/// The kind of the node with the given rule, written inside a node with the parent rule
+fn kind_of(rule:Rule,parent:Option<Rule>) -> Option<NodeKind> {
+    let kind = match rule {
+        Rule::Close | Rule::When | Rule::If | Rule::Let | Rule::Assert | Rule::Pay |
+        Rule::ContractHole => NodeKind::Contract,
+        Rule::Case | Rule::MerkleizedCase | Rule::CaseHole => NodeKind::Case,
+        Rule::Notify | Rule::Choice | Rule::Deposit | Rule::ActionHole => NodeKind::Action,
+        Rule::TrueObs | Rule::FalseObs | Rule::ValueEQ | Rule::ValueLE | Rule::ValueLT |
+        Rule::ValueGT | Rule::ValueGE | Rule::OrObs | Rule::NotObs | Rule::AndObs |
+        Rule::ChoseSomething | Rule::ObservationHole => NodeKind::Observation,
+        Rule::Constant | Rule::ConstantParam | Rule::AvailableMoney | Rule::Cond |
+        Rule::ChoiceValue | Rule::MulValue | Rule::DivValue | Rule::SubValue | Rule::AddValue |
+        Rule::NegValue | Rule::UseValue | Rule::TimeIntervalStart | Rule::TimeIntervalEnd |
+        Rule::ValueHole => NodeKind::Value,
+        Rule::Role | Rule::PK | Rule::Address | Rule::PartyHole | Rule::FromPartyHole => NodeKind::Party,
+        Rule::PayeeAccount | Rule::PayeeParty | Rule::PayeeHole => NodeKind::Payee,
+        Rule::ADA | Rule::Currency | Rule::TokenHole => NodeKind::Token,
+        Rule::TimeConstant | Rule::TimeParam | Rule::TimeoutHole => NodeKind::Timeout,
+        // negative timeouts
+        Rule::Number if parent == Some(Rule::When) => NodeKind::Timeout,
+        Rule::Bound | Rule::BoundHole => NodeKind::Bound,
+        Rule::ChoiceId => NodeKind::ChoiceId,
+        _ => return None
+    };
+    Some(kind)
+}
+
+/// A typed node to write into a contract
+#[derive(Debug,Clone,PartialEq)]
+pub enum Replacement {
+    Contract(Contract),
+    Case(Case),
+    Action(Action),
+    Observation(Observation),
+    Value(Value),
+    Party(Party),
+    Payee(Payee),
+    Token(Token),
+    Timeout(Timeout),
+    Bound(Bound),
+    ChoiceId(ChoiceId)
+}
+
+impl Replacement {
+
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            Replacement::Contract(_) => NodeKind::Contract,
+            Replacement::Case(_) => NodeKind::Case,
+            Replacement::Action(_) => NodeKind::Action,
+            Replacement::Observation(_) => NodeKind::Observation,
+            Replacement::Value(_) => NodeKind::Value,
+            Replacement::Party(_) => NodeKind::Party,
+            Replacement::Payee(_) => NodeKind::Payee,
+            Replacement::Token(_) => NodeKind::Token,
+            Replacement::Timeout(_) => NodeKind::Timeout,
+            Replacement::Bound(_) => NodeKind::Bound,
+            Replacement::ChoiceId(_) => NodeKind::ChoiceId
+        }
+    }
+
+    /// The replacement in the layout of the playground, starting at the given column
+    fn format(&self,is_root:bool,column:usize) -> String {
+        let options = FormatOptions::default();
+        match self {
+            Replacement::Contract(x) => format_node(x,is_root,column,&options),
+            Replacement::Case(x) => format_node(x,false,column,&options),
+            Replacement::Action(x) => format_node(x,false,column,&options),
+            Replacement::Observation(x) => format_node(x,false,column,&options),
+            Replacement::Value(x) => format_node(x,false,column,&options),
+            Replacement::Party(x) => format_node(x,false,column,&options),
+            Replacement::Payee(x) => format_node(x,false,column,&options),
+            Replacement::Token(x) => format_node(x,false,column,&options),
+            Replacement::Timeout(x) => format_node(x,false,column,&options),
+            Replacement::Bound(x) => format_node(x,false,column,&options),
+            Replacement::ChoiceId(x) => format_node(x,false,column,&options)
+        }
+    }
+}
+
+/// A typed change to a contract, see [`SyntaxTree::apply`]
+#[derive(Debug,Clone,PartialEq)]
+pub enum Edit {
+    /// Replaces the node at the given path, which may be a hole
+    Replace { path: NodePath, with: Replacement },
+    /// Fills every hole with the given name, such as `seller` for `?seller`
+    FillHole { name: String, with: Replacement }
+}
+
+/// A change to the source text: the text within the span of the
+/// source (as it was before the change) is replaced by the new text
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum EditError {
+    /// There is no node at the given path
+    NoSuchNode(NodePath),
+    /// The contract has no hole with the given name
+    NoSuchHole(String),
+    /// The node at the given path is part of another node and can not be replaced on its own
+    NotReplaceable { path: NodePath, rule: Rule },
+    /// The node at the given path is of another kind than the replacement
+    TypeMismatch { path: NodePath, expected: NodeKind, found: NodeKind },
+    /// The edited source could not be parsed. This is most likely a bug in the marlowe_lang library.
+    Parse(ParseError)
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::NoSuchNode(path) => write!(f, "There is no node at {path:?}"),
+            EditError::NoSuchHole(name) => write!(f, "The contract has no hole named '?{name}'"),
+            EditError::NotReplaceable { path, rule } => write!(f, "The {rule:?} at {path:?} can not be replaced"),
+            EditError::TypeMismatch { path, expected, found } =>
+                write!(f, "Expected a {expected:?} to replace the node at {path:?}, received a {found:?}"),
+            EditError::Parse(e) => write!(f, "The edited contract could not be parsed: {e}")
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Lossless syntax tree of a contract in the Marlowe DSL
+#[derive(Debug,Clone,PartialEq)]
+pub struct SyntaxTree {
+    source: String,
+    leading_trivia: Vec<SyntaxToken>,
+    root: SyntaxNode,
+    trailing_trivia: Vec<SyntaxToken>
+}
+
+impl SyntaxTree {
+
+    pub fn parse(source:&str) -> Result<SyntaxTree,ParseError> {
+        let mut pairs = <MarloweParser as pest::Parser::<Rule>>::parse(Rule::MainContract,source)?;
+        let pair = pairs.next().ok_or_else(||ParseError::internal("The root node went missing"))?;
+        let root = build(source,pair,vec![]);
+        let mut leading_trivia = vec![];
+        let mut trailing_trivia = vec![];
+        lex(source,0,root.span.start,&mut leading_trivia);
+        lex(source,root.span.end,source.len(),&mut trailing_trivia);
+        Ok(SyntaxTree {
+            source: source.to_string(),
+            leading_trivia: tokens(leading_trivia),
+            root,
+            trailing_trivia: tokens(trailing_trivia)
+        })
+    }
+
+    /// The source text of the whole contract
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The source text of a node or token
+    pub fn text(&self,span:Span) -> &str {
+        &self.source[span.start..span.end]
+    }
+
+    /// The root contract
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// Whitespace and comments before the root contract
+    pub fn leading_trivia(&self) -> &[SyntaxToken] {
+        &self.leading_trivia
+    }
+
+    /// Whitespace and comments after the root contract
+    pub fn trailing_trivia(&self) -> &[SyntaxToken] {
+        &self.trailing_trivia
+    }
+
+    /// The node at the given path, if any
+    pub fn node(&self,path:&[usize]) -> Option<&SyntaxNode> {
+        self.node_and_parent(path).map(|(x,_)|x)
+    }
+
+    fn node_and_parent(&self,path:&[usize]) -> Option<(&SyntaxNode,Option<&SyntaxNode>)> {
+        let mut node = &self.root;
+        let mut parent = None;
+        for i in path {
+            parent = Some(node);
+            node = node.nodes().nth(*i)?;
+        }
+        Some((node,parent))
+    }
+
+    /// All holes in the contract, in the order they are written
+    pub fn holes(&self) -> Vec<&SyntaxNode> {
+        let mut out = vec![];
+        self.root.collect_holes(&mut out);
+        out
+    }
+
+    /// The name of a hole, without the question mark
+    pub fn hole_name(&self,node:&SyntaxNode) -> Option<&str> {
+        if node.is_hole() { Some(&self.text(node.span)[1..]) } else { None }
+    }
+
+    /// Deserializes the contract, see [`crate::parsing::deserialization::deserialize`]
+    pub fn contract(&self) -> Result<Contract,ParseError> {
+        crate::parsing::deserialization::deserialize(&self.source)
+    }
+
+    /// Applies an edit to the source and parses it again.
+    /// Returns the changes made to the source text, in the order they appear in the source.
+    /// They are kept as small as possible: text that is the same before and after is left out.
+    pub fn apply(&mut self,edit:&Edit) -> Result<Vec<TextEdit>,EditError> {
+        let (paths,with) = match edit {
+            Edit::Replace { path, with } => (vec![path.clone()],with),
+            Edit::FillHole { name, with } => {
+                let paths : Vec<NodePath> = self.holes().into_iter()
+                    .filter(|x| self.hole_name(x) == Some(name.as_str()))
+                    .map(|x|x.path.clone())
+                    .collect();
+                if paths.is_empty() {
+                    return Err(EditError::NoSuchHole(name.clone()))
+                }
+                (paths,with)
+            }
+        };
+        let mut edits = vec![];
+        for path in paths {
+            let edit = self.replace(&path,with)?;
+            if !edit.text.is_empty() || edit.span.start != edit.span.end {
+                edits.push(edit)
+            }
+        }
+        let mut source = self.source.clone();
+        for x in edits.iter().rev() {
+            source.replace_range(x.span.start..x.span.end,&x.text)
+        }
+        *self = SyntaxTree::parse(&source).map_err(EditError::Parse)?;
+        Ok(edits)
+    }
+
+    fn replace(&self,path:&[usize],with:&Replacement) -> Result<TextEdit,EditError> {
+        let (node,parent) = self.node_and_parent(path).ok_or_else(||EditError::NoSuchNode(path.to_vec()))?;
+        let expected = kind_of(node.rule,parent.map(|x|x.rule))
+            .ok_or(EditError::NotReplaceable { path: path.to_vec(), rule: node.rule })?;
+        if with.kind() != expected {
+            return Err(EditError::TypeMismatch { path: path.to_vec(), expected, found: with.kind() })
+        }
+        // nested contracts and bounds may be wrapped in parentheses that are not part of their node
+        let span = parent.and_then(|x|self.parentheses_around(x,node)).unwrap_or(node.span);
+        let (_,column) = span.line_col(&self.source);
+        let text = with.format(path.is_empty(),column - 1);
+        Ok(minimal(self.text(span),span,text))
+    }
+
+    fn parentheses_around(&self,parent:&SyntaxNode,node:&SyntaxNode) -> Option<Span> {
+        let index = parent.children.iter().position(|x| matches!(x,SyntaxElement::Node(n) if n.path == node.path))?;
+        let is_paren = |x:Option<&SyntaxElement>,paren:&str| match x {
+            Some(SyntaxElement::Token(t)) if self.text(t.span) == paren => Some(t.span),
+            _ => None
+        };
+        let significant = |x:&&SyntaxElement| !matches!(x,SyntaxElement::Token(t) if t.kind.is_trivia());
+        let open = is_paren(parent.children[..index].iter().rev().find(significant),"(")?;
+        let close = is_paren(parent.children[index + 1..].iter().find(significant),")")?;
+        Some(Span { start: open.start, end: close.end })
+    }
+}
+
+impl std::fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}",self.source)
+    }
+}
+
+fn build(source:&str,pair:Pair<Rule>,path:NodePath) -> SyntaxNode {
+    let rule = pair.as_rule();
+    let span : Span = pair.as_span().into();
+    let inner : Vec<Pair<Rule>> = pair.into_inner().collect();
+    // the top level contract rule only wraps the actual contract, so they share a path
+    if rule == Rule::Contract && inner.len() == 1 {
+        return build(source,inner[0].clone(),path)
+    }
+    let mut children = vec![];
+    if !inner.is_empty() {
+        let mut position = span.start;
+        for (i,x) in inner.into_iter().enumerate() {
+            let mut child_path = path.clone();
+            child_path.push(i);
+            let node = build(source,x,child_path);
+            lex(source,position,node.span.start,&mut children);
+            position = node.span.end;
+            children.push(SyntaxElement::Node(node));
+        }
+        lex(source,position,span.end,&mut children);
+    }
+    SyntaxNode { rule, span, path, children }
+}
+
+/// Splits the text between two nodes into tokens
+fn lex(source:&str,start:usize,end:usize,out:&mut Vec<SyntaxElement>) {
+    let text = &source[..end];
+    let mut i = start;
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        let (kind,length) = if c.is_whitespace() {
+            (TokenKind::Whitespace,rest.find(|x:char|!x.is_whitespace()).unwrap_or(rest.len()))
+        } else if let Some(comment_end) = comment_end(text,i) {
+            (TokenKind::Comment,comment_end - i)
+        } else if c.is_ascii_alphabetic() {
+            (TokenKind::Keyword,rest.find(|x:char|!x.is_ascii_alphanumeric()).unwrap_or(rest.len()))
+        } else {
+            (TokenKind::Punctuation,c.len_utf8())
+        };
+        out.push(SyntaxElement::Token(SyntaxToken { kind, span: Span { start: i, end: i + length } }));
+        i += length;
+    }
+}
+
+fn tokens(elements:Vec<SyntaxElement>) -> Vec<SyntaxToken> {
+    elements.into_iter().filter_map(|x| match x {
+        SyntaxElement::Token(t) => Some(t),
+        SyntaxElement::Node(_) => None
+    }).collect()
+}
+
+/// Leaves out the text that the old and the new text start and end with
+fn minimal(old:&str,span:Span,new:String) -> TextEdit {
+    let common = |a:&mut dyn Iterator<Item=(char,char)>| a.take_while(|(x,y)|x == y).map(|(x,_)|x.len_utf8()).sum::<usize>();
+    let prefix = common(&mut old.chars().zip(new.chars()));
+    let suffix = common(&mut old[prefix..].chars().rev().zip(new[prefix..].chars().rev()));
+    TextEdit {
+        span: Span { start: span.start + prefix, end: span.end - suffix },
+        text: new[prefix..new.len() - suffix].to_string()
+    }
+}
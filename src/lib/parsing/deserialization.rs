@@ -1,12 +1,19 @@
 use std::collections::{HashSet, HashMap};
 
 use pest::iterators::Pair;
-use serde::de::value;
 use crate::parsing::Rule;
+use crate::parsing::error::{ParseError, Span};
+use crate::parsing::spans::{NodePath, SpanTable};
+use crate::parsing::comments::{collect as collect_comments, CommentTable};
 use crate::types::marlowe::*;
+use crate::types::template::TemplateInputs;
+use crate::validation::validate_party;
 
 struct Operation<'a> {
     pair_rule_type : Rule,
+    span : Span,
+    path : NodePath,
+    child_count : usize,
     thing_to_extract_ast_from : pest::iterators::Pairs<'a,Rule>,
     extracted_child_ast_nodes : Vec<AstNode>,
     string_representation : Option<String>
@@ -23,33 +30,37 @@ fn add_to_parent(call_stack:&mut Vec<Operation>,result_stack:&mut Vec<AstNode>,n
     }
 }
 
-fn option_to_result<T>(maybe_ast_node:Option<T>,msg:&str) -> Result<T,&str> {
+fn option_to_result<T>(maybe_ast_node:Option<T>,msg:&str) -> Result<T,ParseError> {
     match maybe_ast_node {
         Some(x) => Ok(x),
-        None => return Err(msg),
+        None => Err(ParseError::internal(msg)),
     }
 }
 
-fn parse_with_input<T>(pair:Pair<Rule>,input:HashMap<String,i64>) -> std::result::Result<T,String>
-    where Result<T, String>: From<AstNode> { 
-        parse_raw(pair,input)?.into() }
+fn parse_with_input<T>(pair:Pair<Rule>,input:HashMap<String,BigInt>,reject_unknown_keys:bool) -> std::result::Result<(T,SpanTable),ParseError>
+    where Result<T, ParseError>: From<AstNode> { 
+        let (node,spans) = parse_raw(pair,input,reject_unknown_keys)?;
+        let result : Result<T, ParseError> = node.into();
+        Ok((result?,spans))
+    }
 
-fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String> {
+fn parse_raw(pair:Pair<Rule>,input:HashMap<String,BigInt>,reject_unknown_keys:bool) -> Result<(AstNode,SpanTable),ParseError> {
     
-    let mut keys : Vec<String> = input.keys().map(|x|x.to_owned()).collect();
-    keys.dedup();
-
-    if input.keys().len() != keys.len() {
-        return Err(String::from("Input data cannot contain duplicate keys."))
-    }
+    let mut used_keys : HashSet<String> = HashSet::new();
 
+    let mut spans = SpanTable::default();
     let rule = pair.as_rule();
+    let span = pair.as_span().into();
+    spans.insert(vec![],span);
     let outer_inner = pair.clone().into_inner();
     let child_count = outer_inner.clone().count();
     let str_rep = if child_count == 0 { Some(pair.as_str().to_string()) } else { None };
     let mut call_stack : Vec<Operation> = vec![
         Operation{ 
             pair_rule_type: rule, 
+            span,
+            path: vec![],
+            child_count: 0,
             thing_to_extract_ast_from: outer_inner, 
             extracted_child_ast_nodes: vec![],
             string_representation: str_rep
@@ -61,11 +72,25 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
 
         if let Some(child_pair) = current_operation.thing_to_extract_ast_from.next() {
             let rule = child_pair.as_rule();
+            let span = child_pair.as_span().into();
+            // the top level contract rule only wraps the actual contract, so they share a path
+            let path = if current_operation.pair_rule_type == Rule::Contract {
+                current_operation.path.clone()
+            } else {
+                let mut p = current_operation.path.clone();
+                p.push(current_operation.child_count);
+                p
+            };
+            current_operation.child_count += 1;
+            spans.insert(path.clone(),span);
             let inner = child_pair.clone().into_inner();
             let child_count = inner.clone().count();
             let str_rep = if child_count == 0 { Some(child_pair.as_str().to_string()) } else { None };
             let op = Operation {
                 pair_rule_type: rule,
+                span,
+                path,
+                child_count: 0,
                 thing_to_extract_ast_from: inner,
                 extracted_child_ast_nodes: vec![],
                 string_representation: str_rep
@@ -75,10 +100,14 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
             continue;
         } 
 
+        let span = current_operation.span;
         let get_next_node = |x:&mut Operation| {
             match x.extracted_child_ast_nodes.pop() {
                 Some(v) => Ok(v),
-                None => Err(format!("Failed to get next node from a {:?}. This is likely a bug in the marlowe_lang library",x.pair_rule_type)),
+                None => Err(ParseError::Internal { 
+                    span: Some(x.span), 
+                    message: format!("Failed to get next node from a {:?}",x.pair_rule_type) 
+                }),
             }
         };
 
@@ -90,17 +119,18 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
 
         macro_rules! try_get_next { () => { (get_next_node(&mut current_operation))};}
         macro_rules! get_next { () => { try_get_next!()? } }
-        macro_rules! get_next_into { () => { get_next!().try_into()? };}
+        macro_rules! get_next_into { () => { get_next!().try_into().map_err(|e:ParseError|e.at(span))? };}
         match current_operation.pair_rule_type {  
             Rule::Contract => fold_back!(get_next!()),
             Rule::Close => fold_back!(AstNode::MarloweContract(Contract::Close)),
             Rule::UseValue => fold_back!(AstNode::MarloweValue(Value::UseValue(get_next_into!()))),            
             Rule::ConstantParam => {
                 let parameter_name : String = get_next_into!();
-                let input_parameter_value : Option<&i64> = input.get(&parameter_name);
+                let input_parameter_value : Option<&BigInt> = input.get(&parameter_name);
+                used_keys.insert(parameter_name.clone());
                 match input_parameter_value {
                     Some(value_from_input) => {
-                        fold_back!(AstNode::MarloweValue(Value::ConstantValue(*value_from_input)))
+                        fold_back!(AstNode::MarloweValue(Value::ConstantValue(value_from_input.clone())))
                     },
                     None => {
                         fold_back!(AstNode::MarloweValue(Value::ConstantParam(parameter_name)))
@@ -109,31 +139,45 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
             },
             Rule::ArrayOfCases => fold_back!(AstNode::MarloweCaseList(current_operation.extracted_child_ast_nodes)),
             Rule::ArrayOfBounds => fold_back!(AstNode::MarloweBoundList(current_operation.extracted_child_ast_nodes)),
+            // key hashes and currency symbols are only checked on request, see deserialize_validated
             Rule::PK => fold_back!(AstNode::MarloweParty(Party::PK { pk_hash : get_next_into!() })),
+            Rule::Address => {
+                let party = Party::Address { address : get_next_into!() };
+                validate_party(&party).map_err(|error|ParseError::Validation { span: Some(span), error })?;
+                fold_back!(AstNode::MarloweParty(party))
+            },
             Rule::TimeParam => {
                 let parameter_name : String = get_next_into!();
-                let input_parameter_value : Option<&i64> = input.get(&parameter_name);
+                let input_parameter_value : Option<&BigInt> = input.get(&parameter_name);
+                used_keys.insert(parameter_name.clone());
                 match input_parameter_value {
                     Some(value_from_input) => {
-                        fold_back!(AstNode::MarloweTimeout(Timeout::TimeConstant(*value_from_input)))
+                        fold_back!(AstNode::MarloweTimeout(Timeout::TimeConstant(value_from_input.clone())))
                     },
                     None => fold_back!(AstNode::MarloweTimeout(Timeout::TimeParam(parameter_name))),
                 }
             },
             Rule::PayeeAccount => fold_back!(AstNode::MarlowePayee(Payee::Account(get_next_into!()))),
             Rule::PayeeParty => fold_back!(AstNode::MarlowePayee(Payee::Party(get_next_into!()))),
-            Rule::Role => fold_back!(AstNode::MarloweParty(Party::Role { role_token : get_next_node(&mut current_operation)?.try_into()?})),
-            Rule::Notify => fold_back!(AstNode::MarloweAction(Action::Notify { 
-                notify_if: get_next_node(&mut current_operation)?.try_into()? })),            
+            Rule::Role => fold_back!(AstNode::MarloweParty(Party::Role { role_token : get_next_into!() })),
+            Rule::Notify => fold_back!(AstNode::MarloweAction(Action::Notify { notify_if: get_next_into!() })),            
             Rule::Case => {
                 let continuation_contract = get_next!();
-                let contract_node = continuation_contract.try_into()?;
+                let contract_node = continuation_contract.try_into().map_err(|e:ParseError|e.at(span))?;
                 let action = get_next_into!();
-                fold_back!(AstNode::MarloweCase(crate::types::marlowe::Case {
+                fold_back!(AstNode::MarloweCase(crate::types::marlowe::Case::Case {
                     case: action,
                     then: contract_node
                 }));
             }
+            Rule::MerkleizedCase => {
+                let continuation_hash : String = get_next_into!();
+                let action = get_next_into!();
+                fold_back!(AstNode::MarloweCase(crate::types::marlowe::Case::MerkleizedCase {
+                    case: action,
+                    then: continuation_hash
+                }));
+            }
             Rule::When => {
                 let contract_node = get_next_into!();
                 let timeout = get_next_into!();
@@ -148,7 +192,11 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
             Rule::Currency => {
                 let v2 : String = get_next_into!();
                 let v1 : String = get_next_into!();
-                let token = Token::Custom { currency_symbol: v1, token_name: v2 };
+                let token = if v1.is_empty() && v2.is_empty() {
+                    Token::ADA
+                } else {
+                    Token::Custom { currency_symbol: v1, token_name: v2 }
+                };
                 fold_back!(AstNode::MarloweToken(token))
             }
             Rule::Deposit => {
@@ -187,9 +235,9 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
             Rule::FalseObs => fold_back!(AstNode::MarloweObservation(Observation::False)),
             Rule::Number => {                
                 let n = option_to_result(current_operation.string_representation,"Failed to parse a number!")?;
-                let nn = match n.parse::<i64>() {
+                let nn = match n.parse::<BigInt>() {
                     Ok(nnn) => nnn,
-                    Err(e) => return Err(format!("{}. Inner error: {:?}","Failed to parse a number",e))
+                    Err(_) => return Err(ParseError::InvalidNumber { span, text: n })
                 };
                 fold_back!(AstNode::MarloweNumber(nn))
             }
@@ -241,6 +289,40 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
                     lt_than: v2
                 }))
             }
+            Rule::ValueLE => {
+                let v2 = get_next_into!();
+                let v1 = get_next_into!();
+                fold_back!(AstNode::MarloweObservation(Observation::ValueLE {
+                    value: v1,
+                    le_than: v2
+                }))
+            }
+            Rule::ValueEQ => {
+                let v2 = get_next_into!();
+                let v1 = get_next_into!();
+                fold_back!(AstNode::MarloweObservation(Observation::ValueEQ {
+                    value: v1,
+                    equal_to: v2
+                }))
+            }
+            Rule::AndObs => {
+                let o2 = get_next_into!();
+                let o1 = get_next_into!();
+                fold_back!(AstNode::MarloweObservation(Observation::AndObs { both: o1, and: o2 }))
+            }
+            Rule::OrObs => {
+                let o2 = get_next_into!();
+                let o1 = get_next_into!();
+                fold_back!(AstNode::MarloweObservation(Observation::OrObs { either: o1, or: o2 }))
+            }
+            Rule::NotObs => {
+                let o = get_next_into!();
+                fold_back!(AstNode::MarloweObservation(Observation::NotObs { not: o }))
+            }
+            Rule::ChoseSomething => {
+                let choice_id = get_next_into!();
+                fold_back!(AstNode::MarloweObservation(Observation::ChoseSomething(choice_id)))
+            }
             Rule::NegValue => {
                 let v = get_next_into!();
                 fold_back!(AstNode::MarloweValue(Value::NegValue(v)))
@@ -278,9 +360,9 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
             }
             Rule::TimeConstant => {
                 let vv = option_to_result(current_operation.string_representation,"failed to parse time constant")?;
-                let vvv = match vv.parse::<i64>() {
+                let vvv = match vv.parse::<BigInt>() {
                     Ok(n) => n,
-                    Err(e) => return Err(format!("{} : {e:?}",format!("failed to convert time constant value {vv}' to i64."))),
+                    Err(_) => return Err(ParseError::InvalidNumber { span, text: vv }),
                 };
                 fold_back!(AstNode::MarloweTimeout(Timeout::TimeConstant(vvv)))
             }
@@ -292,19 +374,27 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
                     r#if: observation, then: then_contract, r#else: else_contract 
                 }))
             }
+            Rule::Assert => {
+                let continue_as = get_next_into!();
+                let observation = get_next_into!();
+                fold_back!(AstNode::MarloweContract(Contract::Assert { 
+                    assert: observation, 
+                    then: continue_as 
+                }))
+            }
             Rule::Let => {
                 let continue_as = get_next_into!();
                 let value = get_next_into!();
-                let s: Option<String> = get_next_into!();
+                let s: String = get_next_into!();
                 fold_back!(AstNode::MarloweContract(Contract::Let { 
-                    r#let: option_to_result(s,"Failed to parse a 'let' contract node.")?, 
+                    r#let: s, 
                     be: value, 
                     then: continue_as 
                 }))
             }
             Rule::Constant => {
                 let n = option_to_result(current_operation.extracted_child_ast_nodes.pop(),"failed to parse constant")?;
-                let nn : i64 = n.try_into()?;
+                let nn : BigInt = n.try_into().map_err(|e:ParseError|e.at(span))?;
                 fold_back!(AstNode::MarloweValue(Value::ConstantValue(nn)))
 
             }
@@ -324,50 +414,777 @@ fn parse_raw(pair:Pair<Rule>,input:HashMap<String,i64>) -> Result<AstNode,String
             Rule::FromPartyHole|
             Rule::BoundHole|
             Rule::PayeeHole|
-            Rule::TimeoutHole => fold_back!(AstNode::Null),
+            Rule::TimeoutHole => {
+                let hole = option_to_result(current_operation.string_representation,"failed to parse hole")?;
+                fold_back!(AstNode::Hole(hole.trim_start_matches('?').to_string()))
+            },
             
             unmatched_rule => {
-                return Err(format!("The rule {unmatched_rule:?} is ready to be intialized but there is no match for it.. it has these children {:?}. This is most likely a bug in the marlowe_lang library. ",current_operation.extracted_child_ast_nodes))
+                return Err(ParseError::Internal { 
+                    span: Some(span), 
+                    message: format!("The rule {unmatched_rule:?} is ready to be intialized but there is no match for it.. it has these children {:?}",current_operation.extracted_child_ast_nodes)
+                })
             }
         }
          
     }
     if result_stack.len() != 1 {
-        return Err(format!("Marlowe_Lang::ErrorCode(1) : {:?}",result_stack).to_string())
+        return Err(ParseError::internal(&format!("Expected a single root node, found: {:?}",result_stack)))
     };
+    if reject_unknown_keys {
+        if let Some(key) = input.keys().find(|x|!used_keys.contains(*x)) {
+            return Err(ParseError::UnknownInputKey { key: key.to_string() })
+        }
+    }
     match result_stack.pop() {
         Some(v) => {
-            Ok(v)
+            Ok((v,spans))
         }
-        _ => Err("Marlowe_Lang::ErrorCode(2)".to_string())
+        _ => Err(ParseError::internal("The root node went missing"))
     }
 }
 
 
 
 /// Parses a string into an instance of a Marlowe contract
-pub fn deserialize(content:&str) -> Result<Contract,String>  { 
+pub fn deserialize(content:&str) -> Result<Contract,ParseError>  { 
     deserialize_with_input(content,Default::default())
 }
 /// Parses a string into an instance of a Marlowe contract using the input data 
 /// to populate constant and timeout parameters.
-pub fn deserialize_with_input(content:&str,input:HashMap<String,i64>) -> Result<Contract,String>  {
+/// Keys in the input that are not parameters of the contract are ignored.
+pub fn deserialize_with_input(content:&str,input:HashMap<String,BigInt>) -> Result<Contract,ParseError>  {
+    Ok(deserialize_with_spans_and_input(content,input)?.0)
+}
+
+/// Same as [`deserialize_with_input`], but fails with [`ParseError::UnknownInputKey`]
+/// if the input contains a key that is not a parameter of the contract.
+pub fn deserialize_with_strict_input(content:&str,input:HashMap<String,BigInt>) -> Result<Contract,ParseError>  {
+    Ok(parse_contract(content,input,true)?.0)
+}
+
+/// Parses a string into an instance of a Marlowe contract and instantiates it
+/// with the given template inputs, see [`Contract::instantiate`].
+pub fn deserialize_with_template_inputs(content:&str,inputs:&TemplateInputs) -> Result<Contract,ParseError>  {
+    deserialize(content)?.instantiate(inputs).map_err(ParseError::Instantiation)
+}
+
+/// Parses a string into an instance of a Marlowe contract and instantiates it
+/// using a JSON or TOML parameters file, see [`TemplateInputs::from_file`].
+pub fn deserialize_with_params_file(content:&str,params_file:&std::path::Path) -> Result<Contract,ParseError>  {
+    let inputs = TemplateInputs::from_file(params_file).map_err(ParseError::Instantiation)?;
+    deserialize_with_template_inputs(content,&inputs)
+}
+
+/// Parses a string into an instance of a Marlowe contract, along with a table
+/// containing the source location of each node in the contract.
+pub fn deserialize_with_spans(content:&str) -> Result<(Contract,SpanTable),ParseError>  {
+    deserialize_with_spans_and_input(content,Default::default())
+}
+
+/// Parses a string into an instance of a Marlowe contract, along with the
+/// comments in it, attached to the nodes they belong to. 
+/// See [`crate::parsing::serialization::marlowe::serialize_with_comments`] for writing them back out.
+pub fn deserialize_with_comments(content:&str) -> Result<(Contract,CommentTable),ParseError>  {
+    let (contract,spans) = deserialize_with_spans(content)?;
+    Ok((contract,collect_comments(content,&spans)))
+}
+
+/// Same as [`deserialize`], but also checks that all key hashes, currency symbols and
+/// token names in the contract can exist on chain, see [`crate::validation::validate`].
+/// Fails with [`ParseError::Validation`] pointing at the first invalid party or token.
+pub fn deserialize_validated(content:&str) -> Result<Contract,ParseError>  {
+    let (contract,spans) = deserialize_with_spans(content)?;
+    match crate::validation::validate(&contract).into_iter().next() {
+        Some(e) => Err(ParseError::Validation { span: spans.get(&e.path), error: e.kind }),
+        None => Ok(contract)
+    }
+}
+
+/// Same as [`deserialize_with_input`], but also returns a table containing 
+/// the source location of each node in the contract.
+pub fn deserialize_with_spans_and_input(content:&str,input:HashMap<String,BigInt>) -> Result<(Contract,SpanTable),ParseError>  {
+    parse_contract(content,input,false)
+}
+
+fn parse_contract(content:&str,input:HashMap<String,BigInt>,reject_unknown_keys:bool) -> Result<(Contract,SpanTable),ParseError>  {
     match <super::MarloweParser as pest::Parser::<Rule>>::parse(
         Rule::MainContract, 
         content
     ) {
         Result::Ok(mut pairs) => {
             match pairs.next() {
-                None => Result::Err("it doesn't look like anything to me.".to_string()),
+                None => Result::Err(ParseError::internal("it doesn't look like anything to me.")),
                 Some(root) => {
-                    match parse_with_input::<Contract>(root,input) {
+                    match parse_with_input::<Contract>(root,input,reject_unknown_keys) {
                         Ok(v) => Ok(v),
                         Err(e) => Err(e),
                     }
                 }
             }
         }
-        Result::Err(e) => Err(format!("{e:#}"))
+        Result::Err(e) => Err(ParseError::from(e))
+    }
+}
+
+
+pub mod json {
+
+    // Decoding of the Marlowe "Core" json format, mirroring the Serialize 
+    // implementations in parsing::serialization::json.
+
+    // note: json contracts can not contain holes, so every optional field is populated.
+
+    use serde::{Deserialize, de::Error};
+    use serde_json::{Map, Value as JsonValue};
+
+    use crate::types::marlowe::*;
+
+    fn as_object<'a,E:Error>(json:&'a JsonValue,expected:&str) -> Result<&'a Map<String,JsonValue>,E> {
+        match json {
+            JsonValue::Object(o) => Ok(o),
+            other => Err(E::custom(format!("Expected {expected} object, found: {other}")))
+        }
+    }
+
+    fn field<'a,E:Error>(object:&'a Map<String,JsonValue>,name:&str) -> Result<&'a JsonValue,E> {
+        match object.get(name) {
+            Some(v) => Ok(v),
+            None => Err(E::custom(format!("Missing field '{name}' in: {}",JsonValue::Object(object.clone()))))
+        }
+    }
+
+    fn decode<T,E:Error>(json:&JsonValue) -> Result<T,E> 
+        where T: for<'de> Deserialize<'de> {
+        T::deserialize(json).map_err(E::custom)
+    }
+
+    /// Reads a json number of any size
+    fn integer<E:Error>(json:&JsonValue) -> Result<BigInt,E> {
+        match json {
+            JsonValue::Number(n) => n.to_string().parse::<BigInt>()
+                .map_err(|_|E::custom(format!("Expected an integer, found: {n}"))),
+            _ => Err(E::custom(format!("Expected a number, found: {json}")))
+        }
+    }
+
+    fn filled<T,E:Error>(object:&Map<String,JsonValue>,name:&str) -> Result<Slot<T>,E>
+        where T: for<'de> Deserialize<'de> {
+        Ok(Slot::Filled(decode(field(object,name)?)?))
+    }
+
+    fn boxed<T,E:Error>(object:&Map<String,JsonValue>,name:&str) -> Result<Slot<Box<T>>,E>
+        where T: for<'de> Deserialize<'de> {
+        Ok(Slot::Filled(Box::new(decode(field(object,name)?)?)))
+    }
+
+    /// Parses a Marlowe "Core" json document into an instance of a Marlowe contract.
+    /// Documents in the "Extended" format (with time and constant parameters) are also accepted.
+    pub fn deserialize(json:&str) -> Result<Contract,serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    impl<'de> Deserialize<'de> for Contract {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            if json.as_str() == Some("close") {
+                return Ok(Contract::Close)
+            }
+            let o = as_object(&json,"contract")?;
+            if o.contains_key("when") {
+                Ok(Contract::When {
+                    when: decode::<Vec<Case>,_>(field(o,"when")?)?.into_iter().map(Slot::Filled).collect(),
+                    timeout_continuation: boxed(o,"timeout_continuation")?,
+                    timeout: filled(o,"timeout")?
+                })
+            } else if o.contains_key("if") {
+                Ok(Contract::If { r#if: filled(o,"if")?, then: boxed(o,"then")?, r#else: boxed(o,"else")? })
+            } else if o.contains_key("assert") {
+                Ok(Contract::Assert { assert: filled(o,"assert")?, then: boxed(o,"then")? })
+            } else if o.contains_key("let") {
+                Ok(Contract::Let { r#let: decode(field(o,"let")?)?, be: boxed(o,"be")?, then: boxed(o,"then")? })
+            } else if o.contains_key("pay") {
+                Ok(Contract::Pay { 
+                    from_account: filled(o,"from_account")?, 
+                    to: filled(o,"to")?, 
+                    token: filled(o,"token")?, 
+                    pay: filled(o,"pay")?, 
+                    then: boxed(o,"then")? 
+                })
+            } else {
+                Err(Error::custom(format!("Unknown contract: {json}")))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Case {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"case")?;
+            match o.get("merkleized_then") {
+                Some(hash) => Ok(Case::MerkleizedCase { case: filled(o,"case")?, then: decode(hash)? }),
+                None => Ok(Case::Case { case: filled(o,"case")?, then: boxed(o,"then")? })
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Action {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"action")?;
+            if o.contains_key("deposits") {
+                Ok(Action::Deposit { 
+                    party: filled(o,"party")?, 
+                    of_token: filled(o,"of_token")?, 
+                    into_account: filled(o,"into_account")?, 
+                    deposits: filled(o,"deposits")? 
+                })
+            } else if o.contains_key("notify_if") {
+                Ok(Action::Notify { notify_if: filled(o,"notify_if")? })
+            } else if o.contains_key("for_choice") {
+                Ok(Action::Choice { 
+                    for_choice: filled(o,"for_choice")?, 
+                    choose_between: decode::<Vec<Bound>,_>(field(o,"choose_between")?)?.into_iter().map(Slot::Filled).collect()
+                })
+            } else {
+                Err(Error::custom(format!("Unknown action: {json}")))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bound {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"bound")?;
+            Ok(Bound(integer(field(o,"from")?)?,integer(field(o,"to")?)?))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Payee {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"payee")?;
+            if o.contains_key("party") {
+                Ok(Payee::Party(filled(o,"party")?))
+            } else if o.contains_key("account") {
+                Ok(Payee::Account(filled(o,"account")?))
+            } else {
+                Err(Error::custom(format!("Unknown payee: {json}")))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Timeout {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            match &json {
+                JsonValue::Number(_) => Ok(Timeout::TimeConstant(integer(&json)?)),
+                _ => Ok(Timeout::TimeParam(decode(field(as_object(&json,"timeout")?,"time_param")?)?))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Value {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            match &json {
+                JsonValue::String(s) if s == "time_interval_start" => return Ok(Value::TimeIntervalStart),
+                JsonValue::String(s) if s == "time_interval_end" => return Ok(Value::TimeIntervalEnd),
+                JsonValue::Number(_) => return Ok(Value::ConstantValue(integer(&json)?)),
+                _ => {}
+            }
+            let o = as_object(&json,"value")?;
+            if o.contains_key("amount_of_token") {
+                Ok(Value::AvailableMoney(filled(o,"in_account")?,filled(o,"amount_of_token")?))
+            } else if o.contains_key("constant_param") {
+                Ok(Value::ConstantParam(decode(field(o,"constant_param")?)?))
+            } else if o.contains_key("use_value") {
+                Ok(Value::UseValue(decode(field(o,"use_value")?)?))
+            } else if o.contains_key("multiply") {
+                Ok(Value::MulValue(boxed(o,"multiply")?,boxed(o,"times")?))
+            } else if o.contains_key("divide") {
+                Ok(Value::DivValue(boxed(o,"divide")?,boxed(o,"by")?))
+            } else if o.contains_key("minus") {
+                Ok(Value::SubValue(boxed(o,"value")?,boxed(o,"minus")?))
+            } else if o.contains_key("add") {
+                Ok(Value::AddValue(boxed(o,"add")?,boxed(o,"and")?))
+            } else if o.contains_key("negate") {
+                Ok(Value::NegValue(boxed(o,"negate")?))
+            } else if o.contains_key("value_of_choice") {
+                Ok(Value::ChoiceValue(filled(o,"value_of_choice")?))
+            } else if o.contains_key("if") {
+                Ok(Value::Cond(filled(o,"if")?,boxed(o,"then")?,boxed(o,"else")?))
+            } else {
+                Err(Error::custom(format!("Unknown value: {json}")))
+            }
+        }
     }
+
+    impl<'de> Deserialize<'de> for Observation {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            match json {
+                JsonValue::Bool(true) => return Ok(Observation::True),
+                JsonValue::Bool(false) => return Ok(Observation::False),
+                _ => {}
+            }
+            let o = as_object(&json,"observation")?;
+            if o.contains_key("gt_than") {
+                Ok(Observation::ValueGT { value: boxed(o,"value")?, gt_than: boxed(o,"gt_than")? })
+            } else if o.contains_key("ge_than") {
+                Ok(Observation::ValueGE { value: boxed(o,"value")?, ge_than: boxed(o,"ge_than")? })
+            } else if o.contains_key("lt_than") {
+                Ok(Observation::ValueLT { value: boxed(o,"value")?, lt_than: boxed(o,"lt_than")? })
+            } else if o.contains_key("le_than") {
+                Ok(Observation::ValueLE { value: boxed(o,"value")?, le_than: boxed(o,"le_than")? })
+            } else if o.contains_key("equal_to") {
+                Ok(Observation::ValueEQ { value: boxed(o,"value")?, equal_to: boxed(o,"equal_to")? })
+            } else if o.contains_key("choice_id") {
+                Ok(Observation::ChoseSomething(filled(o,"choice_id")?))
+            } else if o.contains_key("chose_something_for") {
+                Ok(Observation::ChoseSomething(filled(o,"chose_something_for")?))
+            } else if o.contains_key("either") {
+                Ok(Observation::OrObs { either: boxed(o,"either")?, or: boxed(o,"or")? })
+            } else if o.contains_key("both") {
+                Ok(Observation::AndObs { both: boxed(o,"both")?, and: boxed(o,"and")? })
+            } else if o.contains_key("not") {
+                Ok(Observation::NotObs { not: boxed(o,"not")? })
+            } else {
+                Err(Error::custom(format!("Unknown observation: {json}")))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Party {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"party")?;
+            if let Some(role_token) = o.get("role_token") {
+                Ok(Party::Role { role_token: decode(role_token)? })
+            } else if let Some(pk_hash) = o.get("pk_hash") {
+                Ok(Party::PK { pk_hash: decode(pk_hash)? })
+            } else if let Some(address) = o.get("address") {
+                let address : String = decode(address)?;
+                crate::types::address::network(&address).map_err(|e|Error::custom(format!("Invalid address '{address}': {e}")))?;
+                Ok(Party::Address { address })
+            } else {
+                Err(Error::custom(format!("Unknown party: {json}")))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Token {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"token")?;
+            let currency_symbol : String = decode(field(o,"currency_symbol")?)?;
+            let token_name : String = decode(field(o,"token_name")?)?;
+            if currency_symbol.is_empty() && token_name.is_empty() {
+                Ok(Token::ADA)
+            } else {
+                Ok(Token::Custom { token_name, currency_symbol })
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ChoiceId {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"choice id")?;
+            Ok(ChoiceId {
+                choice_name: decode(field(o,"choice_name")?)?,
+                choice_owner: Slot::Filled(decode(field(o,"choice_owner")?)?)
+            })
+        }
+    }
+
+    impl<'de> Deserialize<'de> for InputContent {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            if json.as_str() == Some("input_notify") {
+                return Ok(InputContent::INotify)
+            }
+            let o = as_object(&json,"input")?;
+            if o.contains_key("for_choice_id") {
+                Ok(InputContent::IChoice {
+                    for_choice_id: decode(field(o,"for_choice_id")?)?,
+                    input_that_chooses_num: integer(field(o,"input_that_chooses_num")?)?
+                })
+            } else if o.contains_key("that_deposits") {
+                Ok(InputContent::IDeposit {
+                    into_account: decode(field(o,"into_account")?)?,
+                    input_from_party: decode(field(o,"input_from_party")?)?,
+                    of_token: decode(field(o,"of_token")?)?,
+                    that_deposits: integer(field(o,"that_deposits")?)?
+                })
+            } else {
+                Err(Error::custom(format!("Unknown input: {json}")))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Input {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            match json {
+                JsonValue::Object(mut o) if o.contains_key("continuation_hash") => {
+                    let hash = decode(&o.remove("continuation_hash").unwrap_or_default())?;
+                    let continuation = decode(&field(&o,"merkleized_continuation")?.clone())?;
+                    o.remove("merkleized_continuation");
+                    let content = if o.is_empty() { InputContent::INotify } else { decode(&JsonValue::Object(o))? };
+                    Ok(Input::MerkleizedInput(content,hash,Box::new(continuation)))
+                },
+                json => Ok(Input::NormalInput(decode(&json)?))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TimeInterval {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"time interval")?;
+            Ok(TimeInterval { start: decode(field(o,"from")?)?, end: decode(field(o,"to")?)? })
+        }
+    }
+
+    impl<'de> Deserialize<'de> for TransactionInput {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+            let json = JsonValue::deserialize(deserializer)?;
+            let o = as_object(&json,"transaction input")?;
+            Ok(TransactionInput { 
+                interval: decode(field(o,"tx_interval")?)?, 
+                inputs: decode(field(o,"tx_inputs")?)? 
+            })
+        }
+    }
+
+    /// Parses a transaction input (time interval and inputs) from the Marlowe "Core" json format
+    pub fn deserialize_transaction_input(json:&str) -> Result<TransactionInput,serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
 }
 
+pub mod blockly {
+
+    // Reading of Blockly XML workspaces, mirroring parsing::serialization::blockly.
+    // Unconnected inputs and statements are read as holes. Blockly has no names for holes,
+    // so they are named after the kind of input they are in: an empty "value2" input is read as ?value.
+
+    use quick_xml::events::Event;
+
+    use crate::parsing::error::ParseError;
+    use crate::parsing::serialization::blockly::ROOT_BLOCK_TYPE;
+    use crate::validation::validate_party;
+    use crate::types::marlowe::*;
+
+    type Result<T> = std::result::Result<T,ParseError>;
+
+    #[derive(Debug,Default)]
+    struct Element {
+        name: String,
+        attributes: Vec<(String,String)>,
+        children: Vec<Element>,
+        text: String
+    }
+
+    fn invalid(message:impl std::fmt::Display) -> ParseError {
+        ParseError::Blockly { message: message.to_string() }
+    }
+
+    fn element(e:&quick_xml::events::BytesStart) -> Result<Element> {
+        let mut attributes = vec![];
+        for a in e.attributes() {
+            let a = a.map_err(invalid)?;
+            let key = String::from_utf8_lossy(a.key.local_name().as_ref()).to_string();
+            attributes.push((key,a.unescape_value().map_err(invalid)?.to_string()));
+        }
+        Ok(Element { name: String::from_utf8_lossy(e.local_name().as_ref()).to_string(), attributes, ..Default::default() })
+    }
+
+    fn parse_xml(xml:&str) -> Result<Element> {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        reader.trim_text(true);
+        let mut stack = vec![Element::default()];
+        loop {
+            match reader.read_event().map_err(invalid)? {
+                Event::Start(e) => stack.push(element(&e)?),
+                Event::Empty(e) => {
+                    let e = element(&e)?;
+                    stack.last_mut().ok_or_else(||invalid("unbalanced tags"))?.children.push(e)
+                },
+                Event::Text(t) => {
+                    let text = t.unescape().map_err(invalid)?;
+                    stack.last_mut().ok_or_else(||invalid("unbalanced tags"))?.text.push_str(&text)
+                },
+                Event::End(_) => {
+                    let e = stack.pop().ok_or_else(||invalid("unbalanced tags"))?;
+                    stack.last_mut().ok_or_else(||invalid("unbalanced tags"))?.children.push(e)
+                },
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        match (stack.pop(),stack.is_empty()) {
+            (Some(document),true) => Ok(document),
+            _ => Err(invalid("unexpected end of document"))
+        }
+    }
+
+    impl Element {
+        fn attribute(&self,name:&str) -> Option<&str> {
+            self.attributes.iter().find(|(k,_)|k == name).map(|(_,v)|v.as_str())
+        }
+        fn kind(&self) -> &str {
+            self.attribute("type").unwrap_or_default()
+        }
+        fn child(&self,name:&str) -> Option<&Element> {
+            self.children.iter().find(|x|x.name == name)
+        }
+        /// The block connected to the named value input or statement of this block
+        fn input(&self,tag:&str,name:&str) -> Option<&Element> {
+            self.children.iter()
+                .find(|x|x.name == tag && x.attribute("name") == Some(name))
+                .and_then(|x|x.child("block"))
+        }
+        /// All blocks connected to the named statement of this block
+        fn statement(&self,name:&str) -> Vec<&Element> {
+            let mut blocks = vec![];
+            let mut current = self.input("statement",name);
+            while let Some(b) = current {
+                blocks.push(b);
+                current = b.child("next").and_then(|x|x.child("block"));
+            }
+            blocks
+        }
+        fn field(&self,name:&str) -> Option<&str> {
+            self.children.iter()
+                .find(|x|x.name == "field" && x.attribute("name") == Some(name))
+                .map(|x|x.text.as_str())
+        }
+        fn string(&self,name:&str) -> Result<String> {
+            match self.field(name) {
+                Some(x) => Ok(x.to_string()),
+                None => Err(invalid(format!("the block {} is missing the field '{name}'",self.kind())))
+            }
+        }
+        fn number(&self,name:&str) -> Result<BigInt> {
+            let text = self.string(name)?;
+            text.trim().parse::<BigInt>().map_err(|_|invalid(format!("'{text}' is not a valid number")))
+        }
+        /// The block connected to the named value input, or a hole if it is empty, see [`hole`]
+        fn value<T>(&self,name:&str,read:fn(&Element) -> Result<T>) -> Result<Slot<T>> {
+            match self.input("value",name) {
+                Some(x) => Ok(Slot::Filled(read(x)?)),
+                None => Ok(hole(name))
+            }
+        }
+        fn boxed<T>(&self,name:&str,read:fn(&Element) -> Result<T>) -> Result<Slot<Box<T>>> {
+            Ok(self.value(name,read)?.map(Box::new))
+        }
+        fn contract(&self,name:&str) -> Result<Slot<Box<Contract>>> {
+            match self.input("statement",name) {
+                Some(x) => Ok(Slot::Filled(Box::new(contract(x)?))),
+                None => Ok(hole(name))
+            }
+        }
+    }
+
+    /// A hole named after the given input, without the number that tells inputs of the same kind apart
+    fn hole<T>(input:&str) -> Slot<T> {
+        Slot::hole(input.trim_end_matches(|c:char|c.is_ascii_digit()))
+    }
+
+    fn mismatch(expected:&str,block:&Element) -> ParseError {
+        ParseError::TypeMismatch { span: None, expected: expected.to_string(), found: block.kind().to_string() }
+    }
+
+    fn party(block:&Element) -> Result<Party> {
+        let party = match block.kind() {
+            "PKPartyType" => Party::PK { pk_hash: block.string("pubkey")? },
+            "RolePartyType" => Party::Role { role_token: block.string("role")? },
+            "AddressPartyType" => {
+                let party = Party::Address { address: block.string("address")? };
+                validate_party(&party).map_err(|error|ParseError::Validation { span: None, error })?;
+                party
+            },
+            _ => return Err(mismatch("party",block))
+        };
+        Ok(party)
+    }
+
+    fn token(block:&Element) -> Result<Token> {
+        let token = match block.kind() {
+            "AdaTokenType" => Token::ADA,
+            "CustomTokenType" => Token::Custom { 
+                currency_symbol: block.string("currency_symbol")?, 
+                token_name: block.string("token_name")? 
+            },
+            _ => return Err(mismatch("token",block))
+        };
+        Ok(token)
+    }
+
+    fn payee(block:&Element) -> Result<Payee> {
+        match block.kind() {
+            "AccountPayeeType" => Ok(Payee::Account(block.value("party",party)?)),
+            "PartyPayeeType" => Ok(Payee::Party(block.value("party",party)?)),
+            _ => Err(mismatch("payee",block))
+        }
+    }
+
+    fn choice_id(block:&Element) -> Result<Slot<ChoiceId>> {
+        match block.field("choice_name") {
+            Some(name) => Ok(Slot::Filled(ChoiceId { choice_name: name.to_string(), choice_owner: block.value("party",party)? })),
+            None => Ok(Slot::hole("choiceId"))
+        }
+    }
+
+    fn value(block:&Element) -> Result<Value> {
+        let binary = |f:fn(Slot<Box<Value>>,Slot<Box<Value>>) -> Value| 
+            Ok(f(block.boxed("value1",value)?,block.boxed("value2",value)?));
+        match block.kind() {
+            "TimeIntervalStartValueType" => Ok(Value::TimeIntervalStart),
+            "TimeIntervalEndValueType" => Ok(Value::TimeIntervalEnd),
+            "AvailableMoneyValueType" => Ok(Value::AvailableMoney(block.value("party",party)?,block.value("token",token)?)),
+            "ConstantValueType" => Ok(Value::ConstantValue(block.number("constant")?)),
+            "ConstantParamValueType" => Ok(Value::ConstantParam(block.string("paramName")?)),
+            "UseValueValueType" => Ok(Value::UseValue(block.string("value_id")?)),
+            "MulValueValueType" => binary(Value::MulValue),
+            "DivValueValueType" => binary(Value::DivValue),
+            "SubValueValueType" => binary(Value::SubValue),
+            "AddValueValueType" => binary(Value::AddValue),
+            "NegValueValueType" => Ok(Value::NegValue(block.boxed("value",value)?)),
+            "ChoiceValueValueType" => Ok(Value::ChoiceValue(choice_id(block)?)),
+            "CondObservationValueValueType" => Ok(Value::Cond(
+                block.value("condition",observation)?,
+                block.boxed("then",value)?,
+                block.boxed("else",value)?
+            )),
+            _ => Err(mismatch("value",block))
+        }
+    }
+
+    fn observation(block:&Element) -> Result<Observation> {
+        let values = || Ok::<_,ParseError>((block.boxed("value1",value)?,block.boxed("value2",value)?));
+        let observations = || Ok::<_,ParseError>((block.boxed("observation1",observation)?,block.boxed("observation2",observation)?));
+        match block.kind() {
+            "ValueGTObservationType" => values().map(|(value,gt_than)|Observation::ValueGT { value, gt_than }),
+            "ValueGEObservationType" => values().map(|(value,ge_than)|Observation::ValueGE { value, ge_than }),
+            "ValueLTObservationType" => values().map(|(value,lt_than)|Observation::ValueLT { value, lt_than }),
+            "ValueLEObservationType" => values().map(|(value,le_than)|Observation::ValueLE { value, le_than }),
+            "ValueEQObservationType" => values().map(|(value,equal_to)|Observation::ValueEQ { value, equal_to }),
+            "TrueObservationType" => Ok(Observation::True),
+            "FalseObservationType" => Ok(Observation::False),
+            "ChoseSomethingObservationType" => Ok(Observation::ChoseSomething(choice_id(block)?)),
+            "OrObservationType" => observations().map(|(either,or)|Observation::OrObs { either, or }),
+            "AndObservationType" => observations().map(|(both,and)|Observation::AndObs { both, and }),
+            "NotObservationType" => Ok(Observation::NotObs { not: block.boxed("observation",observation)? }),
+            _ => Err(mismatch("observation",block))
+        }
+    }
+
+    fn case(block:&Element) -> Result<Case> {
+        let action = match block.kind() {
+            "DepositActionType" => Action::Deposit {
+                party: block.value("from_party",party)?,
+                into_account: block.value("party",party)?,
+                of_token: block.value("token",token)?,
+                deposits: block.value("value",value)?
+            },
+            "ChoiceActionType" => Action::Choice {
+                for_choice: choice_id(block)?,
+                choose_between: block.statement("bounds").into_iter()
+                    .map(|b| match b.kind() {
+                        "BoundsType" => Ok(Slot::Filled(Bound(b.number("from")?,b.number("to")?))),
+                        _ => Err(mismatch("bound",b))
+                    })
+                    .collect::<Result<_>>()?
+            },
+            "NotifyActionType" => Action::Notify { notify_if: block.value("observation",observation)? },
+            _ => return Err(mismatch("action",block))
+        };
+        Ok(Case::Case { case: Slot::Filled(action), then: block.contract("contract")? })
+    }
+
+    fn contract(block:&Element) -> Result<Contract> {
+        match block.kind() {
+            "CloseContractType" => Ok(Contract::Close),
+            "WhenContractType" => Ok(Contract::When {
+                when: block.statement("case").into_iter().map(|x|case(x).map(Slot::Filled)).collect::<Result<_>>()?,
+                timeout: match (block.field("timeout_type"),block.field("timeout")) {
+                    (Some("time_param"),Some(name)) => Slot::Filled(Timeout::TimeParam(name.to_string())),
+                    (_,Some(_)) => Slot::Filled(Timeout::TimeConstant(block.number("timeout")?)),
+                    (_,None) => Slot::hole("timeout")
+                },
+                timeout_continuation: block.contract("contract")?
+            }),
+            "IfContractType" => Ok(Contract::If {
+                r#if: block.value("observation",observation)?,
+                then: block.contract("contract1")?,
+                r#else: block.contract("contract2")?
+            }),
+            "AssertContractType" => Ok(Contract::Assert {
+                assert: block.value("observation",observation)?,
+                then: block.contract("contract")?
+            }),
+            "LetContractType" => Ok(Contract::Let {
+                r#let: block.string("value_id")?,
+                be: block.boxed("value",value)?,
+                then: block.contract("contract")?
+            }),
+            "PayContractType" => Ok(Contract::Pay {
+                from_account: block.value("party",party)?,
+                to: block.value("payee",payee)?,
+                token: block.value("token",token)?,
+                pay: block.value("value",value)?,
+                then: block.contract("contract")?
+            }),
+            _ => Err(mismatch("contract",block))
+        }
+    }
+
+    /// Parses a Blockly XML workspace from the visual editor of the Marlowe playground
+    /// into an instance of a Marlowe contract.
+    pub fn deserialize(xml:&str) -> Result<Contract> {
+        let document = parse_xml(xml)?;
+        let workspace = document.child("xml").ok_or_else(||invalid("missing <xml> element"))?;
+        let root = workspace.children.iter()
+            .find(|x|x.name == "block" && x.kind() == ROOT_BLOCK_TYPE)
+            .ok_or_else(||invalid(format!("missing {ROOT_BLOCK_TYPE} block")))?;
+        match root.input("statement",ROOT_BLOCK_TYPE) {
+            Some(block) => contract(block),
+            None => Err(invalid("the workspace does not contain a contract"))
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::parsing::Rule;
+
+/// Byte offsets (start inclusive, end exclusive) into the parsed source
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    /// The 1-based line and column of the start of the span within the given source
+    pub fn line_col(&self,source:&str) -> (usize,usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|x|*x != '\n').count() + 1;
+        (line,column)
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(x: pest::Span<'_>) -> Self {
+        Span { start: x.start(), end: x.end() }
+    }
+}
+
+/// Everything that can go wrong when parsing a Marlowe contract
+#[derive(Debug,Clone,PartialEq)]
+pub enum ParseError {
+    /// The input does not match the Marlowe grammar
+    Syntax {
+        span: Span,
+        line: usize,
+        column: usize,
+        expected: Vec<Rule>,
+        message: String
+    },
+    /// The input data contains a key that is not a parameter of the contract
+    UnknownInputKey { key: String },
+    /// A number in the contract could not be represented
+    InvalidNumber { span: Span, text: String },
+    /// A party or token that can not exist on chain, see [`crate::validation`]
+    Validation { span: Option<Span>, error: crate::validation::ValidationErrorKind },
+    /// A node of one type was found where another type was expected
+    TypeMismatch { span: Option<Span>, expected: String, found: String },
+    /// The input is not a valid Blockly XML workspace
+    Blockly { message: String },
+    /// The contract could not be instantiated with the given template inputs
+    Instantiation(crate::types::template::InstantiationError),
+    /// Something that should not be possible happened. This is most likely a bug in the marlowe_lang library.
+    Internal { span: Option<Span>, message: String }
+}
+
+impl ParseError {
+
+    /// The location in the source that the error refers to, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::Syntax { span, .. } |
+            ParseError::InvalidNumber { span, .. } => Some(*span),
+            ParseError::TypeMismatch { span, .. } |
+            ParseError::Validation { span, .. } |
+            ParseError::Internal { span, .. } => *span,
+            ParseError::UnknownInputKey { .. } |
+            ParseError::Blockly { .. } |
+            ParseError::Instantiation(_) => None
+        }
+    }
+
+    /// Attaches a span to the error unless it already has one
+    pub(crate) fn at(self,location:Span) -> ParseError {
+        match self {
+            ParseError::TypeMismatch { span: None, expected, found } =>
+                ParseError::TypeMismatch { span: Some(location), expected, found },
+            ParseError::Validation { span: None, error } =>
+                ParseError::Validation { span: Some(location), error },
+            ParseError::Internal { span: None, message } =>
+                ParseError::Internal { span: Some(location), message },
+            other => other
+        }
+    }
+
+    pub(crate) fn internal(message:&str) -> ParseError {
+        ParseError::Internal { span: None, message: message.to_string() }
+    }
+}
+
+impl From<pest::error::Error<Rule>> for ParseError {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        let span = match e.location {
+            pest::error::InputLocation::Pos(p) => Span { start: p, end: p },
+            pest::error::InputLocation::Span((start,end)) => Span { start, end }
+        };
+        let (line,column) = match e.line_col {
+            pest::error::LineColLocation::Pos(x) => x,
+            pest::error::LineColLocation::Span(x,_) => x
+        };
+        let expected = match &e.variant {
+            pest::error::ErrorVariant::ParsingError { positives, .. } => positives.clone(),
+            pest::error::ErrorVariant::CustomError { .. } => vec![]
+        };
+        ParseError::Syntax { span, line, column, expected, message: format!("{e}") }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Syntax { message, .. } => write!(f, "{message}"),
+            ParseError::UnknownInputKey { key } =>
+                write!(f, "The input data contains the key '{key}' which is not a parameter of the contract."),
+            ParseError::InvalidNumber { span, text } =>
+                write!(f, "Failed to parse the number '{text}' at {}..{}.",span.start,span.end),
+            ParseError::Validation { span: Some(span), error } =>
+                write!(f, "{error} (at {}..{})",span.start,span.end),
+            ParseError::Validation { span: None, error } => write!(f, "{error}"),
+            ParseError::TypeMismatch { span: Some(span), expected, found } =>
+                write!(f, "Expected {expected} at {}..{}, received: {found}",span.start,span.end),
+            ParseError::TypeMismatch { span: None, expected, found } =>
+                write!(f, "Expected {expected}, received: {found}"),
+            ParseError::Blockly { message } => write!(f, "Invalid Blockly workspace: {message}"),
+            ParseError::Instantiation(e) => write!(f, "{e}"),
+            ParseError::Internal { span, message } => {
+                write!(f, "{message}")?;
+                if let Some(span) = span {
+                    write!(f, " (at {}..{})",span.start,span.end)?;
+                }
+                write!(f, ". This is most likely a bug in the marlowe_lang library.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -6,3 +6,8 @@ pub struct MarloweParser;
 
 pub mod serialization;
 pub mod deserialization;
+pub mod error;
+pub mod spans;
+pub mod pretty;
+pub mod comments;
+pub mod cst;
@@ -0,0 +1,443 @@
+// Pretty printer for the Marlowe DSL, producing the same layout as the
+// formatter of the Marlowe playground:
+//
+//   - constructors are hung: arguments that are constructors themselves go on
+//     their own line, indented relative to the opening parenthesis, while
+//     strings, numbers, timeouts, holes and constructors without arguments
+//     (Close, TrueObs, ...) stay on the line of the constructor.
+//   - a NegValue of a constructor that only has such arguments, (NegValue (Constant 1))
+//     for example, is written on one line.
+//   - lists with a single item put the item on its own line, lists with more
+//     items are aligned on the opening bracket with the commas on lines of their
+//     own, unless the whole list fits within the line width.
+//
+// Comments are written on the lines before the node they are attached to, or
+// after it on the same line. Whatever follows a line comment starts on the next line.
+//
+// The layout is computed with a small Wadler/Leijen style document printer.
+
+use crate::parsing::comments::{Comment, CommentTable, Placement};
+use crate::parsing::spans::NodePath;
+use crate::types::marlowe::*;
+
+/// Settings for [`format`]
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct FormatOptions {
+    /// Indentation of arguments, relative to the opening parenthesis of their constructor
+    pub indent: usize,
+    /// The line width that lists of several items have to fit in to be kept on one line
+    pub width: usize
+}
+
+impl Default for FormatOptions {
+    /// The settings used by the playground
+    fn default() -> Self {
+        FormatOptions { indent: 2, width: 80 }
+    }
+}
+
+/// Formats a contract in the layout of the Marlowe playground
+pub fn format(contract:&Contract,options:&FormatOptions) -> String {
+    format_with_comments(contract,&CommentTable::default(),options)
+}
+
+/// Same as [`format`], but also writes out the comments attached to the nodes of the contract,
+/// see [`crate::parsing::deserialization::deserialize_with_comments`]
+pub fn format_with_comments(contract:&Contract,comments:&CommentTable,options:&FormatOptions) -> String {
+    let context = Context { indent: options.indent, comments };
+    render(&root(contract.to_term(),&context),options.width,0)
+}
+
+/// Formats a node that is written into existing source at the given column, see [`crate::parsing::cst`].
+/// Constructors are wrapped in parentheses, except for the contract at the root.
+pub(crate) fn format_node<T:ToTerm>(node:&T,is_root:bool,column:usize,options:&FormatOptions) -> String {
+    let comments = CommentTable::default();
+    let context = Context { indent: options.indent, comments: &comments };
+    let doc = if is_root { root(node.to_term(),&context) } else { node.to_term().into_doc(vec![],&context) };
+    render(&doc,options.width,column)
+}
+
+/// The root contract is written without parentheses
+fn root(term:Term,context:&Context) -> Doc {
+    match term {
+        Term::Node(name,args) => context.commented(&[],false,hang(name,args,vec![],context)),
+        other => other.into_doc(vec![],context)
+    }
+}
+
+struct Context<'a> {
+    indent: usize,
+    comments: &'a CommentTable
+}
+
+impl Context<'_> {
+    /// Surrounds the document of a node with its comments.
+    /// For nodes that are written as a single piece of text, the comments of
+    /// their sub-nodes (such as the name in a TimeParam) are included as well.
+    fn commented(&self,path:&[usize],include_sub_nodes:bool,doc:Doc) -> Doc {
+        let comments : Vec<&Comment> = if include_sub_nodes {
+            self.comments.below(path).collect()
+        } else {
+            self.comments.get(path).iter().collect()
+        };
+        if comments.is_empty() {
+            return doc
+        }
+        let mut parts = vec![];
+        for c in comments.iter().filter(|c|c.placement == Placement::Before) {
+            parts.push(Doc::Comment(c.text.clone()))
+        }
+        parts.push(doc);
+        for c in comments.iter().filter(|c|c.placement == Placement::After) {
+            parts.push(text(" "));
+            if c.is_line_comment() {
+                parts.push(Doc::Comment(c.text.clone()))
+            } else {
+                parts.push(Doc::Text(c.text.clone()))
+            }
+        }
+        Doc::Concat(parts)
+    }
+}
+
+enum Doc {
+    Text(String),
+    /// A line break, or the given text when the enclosing group fits on one line
+    Line(&'static str),
+    /// A comment that ends the line
+    Comment(String),
+    Concat(Vec<Doc>),
+    Nest(usize,Box<Doc>),
+    /// Sets the indentation to the current column
+    Align(Box<Doc>),
+    Group(Box<Doc>)
+}
+
+fn text(x:&str) -> Doc {
+    Doc::Text(x.to_string())
+}
+
+pub(crate) enum Term {
+    Atom(String),
+    Node(&'static str,Vec<Term>),
+    List(Vec<Term>)
+}
+
+impl Term {
+    fn is_node(&self) -> bool {
+        matches!(self,Term::Node(..))
+    }
+
+    /// Atoms, and constructors of which all arguments are atoms
+    fn is_short(&self) -> bool {
+        match self {
+            Term::Atom(_) => true,
+            Term::Node(_,args) => args.iter().all(|x|!x.is_node() && !matches!(x,Term::List(_))),
+            Term::List(_) => false
+        }
+    }
+
+    /// The argument, preceded by a line break or a space (always a space if `inline`)
+    fn argument(self,path:NodePath,inline:bool,context:&Context) -> Doc {
+        let separator = if self.is_node() && !inline { Doc::Line(" ") } else { text(" ") };
+        Doc::Concat(vec![separator,self.into_doc(path,context)])
+    }
+
+    /// A list item, starting on a new line unless it is an atom
+    fn item(self,path:NodePath,context:&Context) -> Doc {
+        if self.is_node() {
+            Doc::Concat(vec![Doc::Line(" "),self.into_doc(path,context)])
+        } else {
+            self.into_doc(path,context)
+        }
+    }
+
+    fn into_doc(self,path:NodePath,context:&Context) -> Doc {
+        let doc = match self {
+            Term::Atom(x) => return context.commented(&path,true,Doc::Text(x)),
+            Term::Node(name,args) => Doc::Concat(vec![text("("),hang(name,args,path.clone(),context),text(")")]),
+            Term::List(items) if items.is_empty() => text("[]"),
+            Term::List(mut items) if items.len() == 1 => {
+                let item = items.remove(0).item(child(&path,0),context);
+                Doc::Concat(vec![text("["),item,text("]")])
+            },
+            Term::List(items) => {
+                let mut parts = vec![];
+                for (i,x) in items.into_iter().enumerate() {
+                    if i > 0 {
+                        parts.push(Doc::Line(""));
+                    }
+                    parts.push(text(if i == 0 { "[" } else { "," }));
+                    parts.push(x.item(child(&path,i),context));
+                }
+                parts.push(text("]"));
+                Doc::Group(Box::new(Doc::Align(Box::new(Doc::Concat(parts)))))
+            }
+        };
+        context.commented(&path,false,doc)
+    }
+}
+
+fn child(path:&[usize],index:usize) -> NodePath {
+    let mut out = path.to_vec();
+    out.push(index);
+    out
+}
+
+/// A constructor followed by its arguments, indented relative to the current column
+fn hang(name:&str,args:Vec<Term>,path:NodePath,context:&Context) -> Doc {
+    let inline = name == "NegValue" && args.iter().all(Term::is_short);
+    let mut parts = vec![text(name)];
+    parts.extend(args.into_iter().enumerate().map(|(i,x)|x.argument(child(&path,i),inline,context)));
+    Doc::Align(Box::new(Doc::Nest(context.indent,Box::new(Doc::Concat(parts)))))
+}
+
+#[derive(Clone,Copy,PartialEq)]
+enum Mode {
+    Flat,
+    Break
+}
+
+/// The width of a piece of text, in characters rather than bytes
+fn length(x:&str) -> usize {
+    x.chars().count()
+}
+
+/// Checks if the rest of the current line fits in the given width
+fn fits(mut width:isize,items:&[(usize,Mode,&Doc)]) -> bool {
+    let mut stack : Vec<(usize,Mode,&Doc)> = items.to_vec();
+    while let Some((i,mode,doc)) = stack.pop() {
+        if width < 0 {
+            return false
+        }
+        match doc {
+            Doc::Text(x) => width -= length(x) as isize,
+            Doc::Line(flat) if mode == Mode::Flat => width -= length(flat) as isize,
+            Doc::Line(_) => return true,
+            // nothing can follow a comment on the same line
+            Doc::Comment(x) => return mode == Mode::Break && width >= length(x) as isize,
+            Doc::Concat(parts) => stack.extend(parts.iter().rev().map(|x|(i,mode,x))),
+            Doc::Nest(n,x) => stack.push((i + n,mode,x)),
+            Doc::Align(x) => stack.push((i,mode,x)),
+            Doc::Group(x) => stack.push((i,mode,x))
+        }
+    }
+    width >= 0
+}
+
+fn render(doc:&Doc,width:usize,mut column:usize) -> String {
+    let mut out = String::new();
+    // set after a comment to the indentation of the comment, the next text goes on a new line
+    let mut pending_break : Option<usize> = None;
+    let mut stack = vec![(column,Mode::Break,doc)];
+    let new_line = |out:&mut String,indent:usize| {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent));
+    };
+    while let Some((i,mode,doc)) = stack.pop() {
+        match doc {
+            Doc::Text(x) => {
+                let x = match pending_break {
+                    Some(_) if x.trim_start().is_empty() => continue,
+                    Some(indent) => {
+                        new_line(&mut out,indent);
+                        column = indent;
+                        pending_break = None;
+                        x.trim_start()
+                    },
+                    None => x.as_str()
+                };
+                out.push_str(x);
+                column += length(x);
+            },
+            Doc::Comment(x) => {
+                if let Some(indent) = pending_break {
+                    new_line(&mut out,indent);
+                    column = indent;
+                }
+                out.push_str(x);
+                column += length(x);
+                pending_break = Some(i);
+            },
+            Doc::Line(flat) if mode == Mode::Flat => {
+                out.push_str(flat);
+                column += length(flat);
+            },
+            Doc::Line(_) => {
+                new_line(&mut out,i);
+                column = i;
+                pending_break = None;
+            },
+            Doc::Concat(parts) => stack.extend(parts.iter().rev().map(|x|(i,mode,x))),
+            Doc::Nest(n,x) => stack.push((i + n,mode,x)),
+            Doc::Align(x) => stack.push((pending_break.unwrap_or(column),mode,x)),
+            Doc::Group(x) if mode == Mode::Flat => stack.push((i,mode,x)),
+            Doc::Group(x) => {
+                let mut rest = stack.clone();
+                rest.push((i,Mode::Flat,x));
+                let column = pending_break.unwrap_or(column);
+                let mode = if fits(width as isize - column as isize,&rest) { Mode::Flat } else { Mode::Break };
+                stack.push((i,mode,x))
+            }
+        }
+    }
+    out
+}
+
+fn string(x:&str) -> Term {
+    Term::Atom(format!("\"{x}\""))
+}
+
+pub(crate) trait ToTerm {
+    fn to_term(&self) -> Term;
+}
+
+impl<T:ToTerm> ToTerm for Slot<T> {
+    fn to_term(&self) -> Term {
+        match self {
+            Slot::Filled(x) => x.to_term(),
+            Slot::Hole(name) => Term::Atom(format!("?{name}"))
+        }
+    }
+}
+
+impl<T:ToTerm> ToTerm for Box<T> {
+    fn to_term(&self) -> Term {
+        self.as_ref().to_term()
+    }
+}
+
+impl ToTerm for Party {
+    fn to_term(&self) -> Term {
+        match self {
+            Party::Role { role_token } => Term::Node("Role",vec![string(role_token)]),
+            Party::PK { pk_hash } => Term::Node("PK",vec![string(pk_hash)]),
+            Party::Address { address } => Term::Node("Address",vec![string(address)])
+        }
+    }
+}
+
+impl ToTerm for Token {
+    fn to_term(&self) -> Term {
+        match self {
+            Token::ADA => Term::Node("Token",vec![string(""),string("")]),
+            Token::Custom { currency_symbol, token_name } => Term::Node("Token",vec![string(currency_symbol),string(token_name)])
+        }
+    }
+}
+
+impl ToTerm for Payee {
+    fn to_term(&self) -> Term {
+        match self {
+            Payee::Account(p) => Term::Node("Account",vec![p.to_term()]),
+            Payee::Party(p) => Term::Node("Party",vec![p.to_term()])
+        }
+    }
+}
+
+impl ToTerm for ChoiceId {
+    fn to_term(&self) -> Term {
+        Term::Node("ChoiceId",vec![string(&self.choice_name),self.choice_owner.to_term()])
+    }
+}
+
+impl ToTerm for Bound {
+    fn to_term(&self) -> Term {
+        Term::Node("Bound",vec![Term::Atom(self.0.to_string()),Term::Atom(self.1.to_string())])
+    }
+}
+
+impl ToTerm for Timeout {
+    /// Timeouts are always kept on the line of the When they belong to
+    fn to_term(&self) -> Term {
+        Term::Atom(format!("{self}"))
+    }
+}
+
+impl ToTerm for Value {
+    fn to_term(&self) -> Term {
+        match self {
+            Value::TimeIntervalStart => Term::Atom("TimeIntervalStart".into()),
+            Value::TimeIntervalEnd => Term::Atom("TimeIntervalEnd".into()),
+            Value::AvailableMoney(a,b) => Term::Node("AvailableMoney",vec![a.to_term(),b.to_term()]),
+            Value::ConstantValue(n) => Term::Node("Constant",vec![Term::Atom(n.to_string())]),
+            Value::ConstantParam(x) => Term::Node("ConstantParam",vec![string(x)]),
+            Value::UseValue(x) => Term::Node("UseValue",vec![string(x)]),
+            Value::MulValue(a,b) => Term::Node("MulValue",vec![a.to_term(),b.to_term()]),
+            Value::DivValue(a,b) => Term::Node("DivValue",vec![a.to_term(),b.to_term()]),
+            Value::SubValue(a,b) => Term::Node("SubValue",vec![a.to_term(),b.to_term()]),
+            Value::AddValue(a,b) => Term::Node("AddValue",vec![a.to_term(),b.to_term()]),
+            Value::NegValue(a) => Term::Node("NegValue",vec![a.to_term()]),
+            Value::ChoiceValue(a) => Term::Node("ChoiceValue",vec![a.to_term()]),
+            Value::Cond(a,b,c) => Term::Node("Cond",vec![a.to_term(),b.to_term(),c.to_term()])
+        }
+    }
+}
+
+impl ToTerm for Observation {
+    fn to_term(&self) -> Term {
+        match self {
+            Observation::ValueGT { value: a, gt_than: b } => Term::Node("ValueGT",vec![a.to_term(),b.to_term()]),
+            Observation::ValueGE { value: a, ge_than: b } => Term::Node("ValueGE",vec![a.to_term(),b.to_term()]),
+            Observation::ValueLT { value: a, lt_than: b } => Term::Node("ValueLT",vec![a.to_term(),b.to_term()]),
+            Observation::ValueLE { value: a, le_than: b } => Term::Node("ValueLE",vec![a.to_term(),b.to_term()]),
+            Observation::ValueEQ { value: a, equal_to: b } => Term::Node("ValueEQ",vec![a.to_term(),b.to_term()]),
+            Observation::True => Term::Atom("TrueObs".into()),
+            Observation::False => Term::Atom("FalseObs".into()),
+            Observation::ChoseSomething(a) => Term::Node("ChoseSomething",vec![a.to_term()]),
+            Observation::OrObs { either, or } => Term::Node("OrObs",vec![either.to_term(),or.to_term()]),
+            Observation::AndObs { both, and } => Term::Node("AndObs",vec![both.to_term(),and.to_term()]),
+            Observation::NotObs { not } => Term::Node("NotObs",vec![not.to_term()])
+        }
+    }
+}
+
+impl ToTerm for Action {
+    fn to_term(&self) -> Term {
+        match self {
+            Action::Deposit { into_account, party, of_token, deposits } => Term::Node("Deposit",vec![
+                into_account.to_term(),
+                party.to_term(),
+                of_token.to_term(),
+                deposits.to_term()
+            ]),
+            Action::Notify { notify_if } => Term::Node("Notify",vec![notify_if.to_term()]),
+            Action::Choice { for_choice, choose_between } => Term::Node("Choice",vec![
+                for_choice.to_term(),
+                Term::List(choose_between.iter().map(|x|x.to_term()).collect())
+            ])
+        }
+    }
+}
+
+impl ToTerm for Case {
+    fn to_term(&self) -> Term {
+        match self {
+            Case::Case { case, then } => Term::Node("Case",vec![case.to_term(),then.to_term()]),
+            Case::MerkleizedCase { case, then } => Term::Node("MerkleizedCase",vec![case.to_term(),string(then)])
+        }
+    }
+}
+
+impl ToTerm for Contract {
+    fn to_term(&self) -> Term {
+        match self {
+            Contract::Close => Term::Atom("Close".into()),
+            Contract::When { when, timeout, timeout_continuation } => Term::Node("When",vec![
+                Term::List(when.iter().map(|x|x.to_term()).collect()),
+                timeout.to_term(),
+                timeout_continuation.to_term()
+            ]),
+            Contract::If { r#if, then, r#else } => Term::Node("If",vec![r#if.to_term(),then.to_term(),r#else.to_term()]),
+            Contract::Assert { assert, then } => Term::Node("Assert",vec![assert.to_term(),then.to_term()]),
+            Contract::Let { r#let, be, then } => Term::Node("Let",vec![string(r#let),be.to_term(),then.to_term()]),
+            Contract::Pay { from_account, to, token, pay, then } => Term::Node("Pay",vec![
+                from_account.to_term(),
+                to.to_term(),
+                token.to_term(),
+                pay.to_term(),
+                then.to_term()
+            ])
+        }
+    }
+}
@@ -11,37 +11,143 @@ pub mod json {
 
     use crate::types::marlowe::*;
 
+    /// Writes an integer as a json number, also when it does not fit in 64 bits
+    struct Integer<'a>(&'a BigInt);
+
+    impl Serialize for Integer<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            match i64::try_from(self.0) {
+                Ok(n) => serializer.serialize_i64(n),
+                Err(_) => self.0.to_string().parse::<serde_json::Number>()
+                    .map_err(serde::ser::Error::custom)?
+                    .serialize(serializer)
+            }
+        }
+    }
+
+    /// Serializes a contract, or a part of one, in the Marlowe "Extended" json format,
+    /// which allows for TimeParam and ConstantParam values:
+    /// `serde_json::to_string(&Extended(&contract))`
+    pub struct Extended<'a,T>(pub &'a T);
+
+    /// Serialization of the nodes that may contain parameters, in either the core or the extended format
+    trait SerializeAs {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer;
+    }
+
+    /// A node along with the format it is written in, used for the children of a node
+    struct As<'a,T:?Sized>(&'a T,bool);
+
+    impl<T:SerializeAs+?Sized> Serialize for As<'_,T> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            self.0.serialize_as(serializer,self.1)
+        }
+    }
+
+    impl<T:SerializeAs> SerializeAs for Box<T> {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            self.as_ref().serialize_as(serializer,extended)
+        }
+    }
+
+    impl<T:SerializeAs> SerializeAs for Slot<T> {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            match self {
+                Slot::Filled(x) => x.serialize_as(serializer,extended),
+                Slot::Hole(name) => Err(serde::ser::Error::custom(format!("The contract contains a hole: '?{name}'.")))
+            }
+        }
+    }
+
+    impl<T:SerializeAs> SerializeAs for Vec<T> {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            serializer.collect_seq(self.iter().map(|x|As(x,extended)))
+        }
+    }
+
+    macro_rules! serialize_as {
+        ($($x:ty),*) => {$(
+            impl Serialize for $x {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer {
+                    self.serialize_as(serializer,false)
+                }
+            }
+
+            impl Serialize for Extended<'_,$x> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer {
+                    self.0.serialize_as(serializer,true)
+                }
+            }
+        )*};
+    }
+
+    serialize_as!(Contract,Case,Action,Observation,Value,Timeout);
+
     /// Takes an instance of a Marlowe contract and serializes
     /// it into the Marlowe "Core" json format
     pub fn serialize(contract:Contract) -> Result<String, serde_json::Error>  { 
         serde_json::to_string_pretty(&contract)
     }
 
-    impl Serialize for Action {
+    /// Takes an instance of a Marlowe contract and serializes it into the 
+    /// Marlowe "Extended" json format, as used by the playground for templates.
+    /// Unlike the core format, this allows for TimeParam and ConstantParam values.
+    pub fn serialize_extended(contract:Contract) -> Result<String, serde_json::Error>  { 
+        serde_json::to_string_pretty(&Extended(&contract))
+    }
+
+    impl<T:Serialize> Serialize for Slot<T> {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            match self {
+                Slot::Filled(x) => x.serialize(serializer),
+                Slot::Hole(name) => Err(serde::ser::Error::custom(format!("The contract contains a hole: '?{name}'.")))
+            }
+        }
+    }
+
+    impl SerializeAs for Action {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer {
         match self {
             Action::Deposit { 
-                party: Some(party), 
-                of_token: Some(of_token), 
-                into_account: Some(into_account), 
-                deposits : Some(deposits)
+                party: Slot::Filled(party), 
+                of_token: Slot::Filled(of_token), 
+                into_account: Slot::Filled(into_account), 
+                deposits : Slot::Filled(deposits)
             } => {
                     let mut s = serializer.serialize_struct("action", 4)?;
                     s.serialize_field("party", party)?;
                     s.serialize_field("of_token", of_token)?;
                     s.serialize_field("into_account", into_account)?;
-                    s.serialize_field("deposits", deposits)?;
+                    s.serialize_field("deposits", &As(deposits,extended))?;
                     s.end()
             },                
-            Action::Notify { notify_if:Some(notify_if) } => {
+            Action::Notify { notify_if:Slot::Filled(notify_if) } => {
                 let mut s = serializer.serialize_struct("action", 1)?;
-                s.serialize_field("notify_if", notify_if)?;
+                s.serialize_field("notify_if", &As(notify_if,extended))?;
                 s.end()
             },
-            Action::Choice { for_choice:Some(for_choice), choose_between } => {
-                if choose_between.iter().any(|x|x.is_none()) {
+            Action::Choice { for_choice:Slot::Filled(for_choice), choose_between } => {
+                if choose_between.iter().any(|x|x.is_hole()) {
                     return Err(serde::ser::Error::custom(format!("A choice action contains null-cases in its list of bounds (a hole in the list of bounds). Lists of bounds are allowed to be empty, but they are not allwed to have placeholder values such as holes.")))
                 }
                 let mut s = serializer.serialize_struct("action", 2)?;
@@ -71,6 +177,11 @@ pub mod json {
                     what.serialize_field("pk_hash", pk_hash)?;
                     what.end()
                 },
+                Party::Address { address } => {
+                    let mut what = serializer.serialize_struct("address", 1)?;
+                    what.serialize_field("address", address)?;
+                    what.end()
+                },
             }
         }
     }
@@ -80,8 +191,8 @@ pub mod json {
         where
             S: serde::Serializer {
             let mut s = serializer.serialize_struct("choose_between", 2)?;
-            s.serialize_field("to",&self.0)?;
-            s.serialize_field("from",&self.1)?;
+            s.serialize_field("to",&Integer(&self.1))?;
+            s.serialize_field("from",&Integer(&self.0))?;
             s.end()
         }
     }
@@ -113,12 +224,12 @@ pub mod json {
     where
         S: serde::Serializer {
         match self {
-            Payee::Party(Some(a)) => {
+            Payee::Party(Slot::Filled(a)) => {
                 let mut s = serializer.serialize_struct("payee", 1)?;
                 s.serialize_field("party",a)?;
                 s.end()
             },
-            Payee::Account(Some(a)) => {
+            Payee::Account(Slot::Filled(a)) => {
                 let mut s = serializer.serialize_struct("payee", 1)?;
                 s.serialize_field("account",a)?;
                 s.end()
@@ -133,7 +244,7 @@ pub mod json {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
-            if self.choice_owner.is_none() {
+            if self.choice_owner.is_hole() {
                 return Err(serde::ser::Error::custom(format!("A choice id is not fully initialized. Missing choice owner.")))
             }
             let mut s = serializer.serialize_struct("for_choice", 2)?;
@@ -143,26 +254,105 @@ pub mod json {
         }
     }
 
-    impl Serialize for Case {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    impl SerializeAs for Case {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer {
-            if self.then.is_none() || self.case.is_none() {
-                return Err(serde::ser::Error::custom(format!("A case is not fully initialized. Missing action or continuation contract.")))
+            match self {
+                Case::Case { case: Slot::Filled(case), then: Slot::Filled(then) } => {
+                    let mut s = serializer.serialize_struct("case", 2)?;
+                    s.serialize_field("then", &As(then,extended))?;
+                    s.serialize_field("case", &As(case,extended))?;            
+                    s.end()
+                },
+                Case::MerkleizedCase { case: Slot::Filled(case), then } => {
+                    let mut s = serializer.serialize_struct("case", 2)?;
+                    s.serialize_field("merkleized_then", then)?;
+                    s.serialize_field("case", &As(case,extended))?;            
+                    s.end()
+                },
+                _ => Err(serde::ser::Error::custom(format!("A case is not fully initialized. Missing action or continuation contract.")))
             }
-            let mut s = serializer.serialize_struct("case", 2)?;
-            s.serialize_field("then", &self.then)?;
-            s.serialize_field("case", &self.case)?;            
-            s.end()
     }
     }
 
-    impl Serialize for Timeout {
+    impl Serialize for InputContent {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
             match self {
-                Timeout::TimeConstant(n) => serializer.serialize_i64(*n),
+                InputContent::IDeposit { into_account, input_from_party, of_token, that_deposits } => {
+                    let mut s = serializer.serialize_struct("input", 4)?;
+                    s.serialize_field("input_from_party", input_from_party)?;
+                    s.serialize_field("that_deposits", &Integer(that_deposits))?;
+                    s.serialize_field("of_token", of_token)?;
+                    s.serialize_field("into_account", into_account)?;
+                    s.end()
+                },
+                InputContent::IChoice { for_choice_id, input_that_chooses_num } => {
+                    let mut s = serializer.serialize_struct("input", 2)?;
+                    s.serialize_field("for_choice_id", for_choice_id)?;
+                    s.serialize_field("input_that_chooses_num", &Integer(input_that_chooses_num))?;
+                    s.end()
+                },
+                InputContent::INotify => serializer.serialize_str("input_notify")
+            }
+        }
+    }
+
+    impl Serialize for Input {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            match self {
+                Input::NormalInput(content) => content.serialize(serializer),
+                Input::MerkleizedInput(content,hash,continuation) => {
+                    // the fields of the content are written next to the continuation, a notify has none
+                    let mut fields = match serde_json::to_value(content).map_err(serde::ser::Error::custom)? {
+                        serde_json::Value::Object(o) => o,
+                        _ => serde_json::Map::new()
+                    };
+                    fields.insert("continuation_hash".into(),hash.clone().into());
+                    fields.insert("merkleized_continuation".into(),serde_json::to_value(continuation).map_err(serde::ser::Error::custom)?);
+                    fields.serialize(serializer)
+                }
+            }
+        }
+    }
+
+    impl Serialize for TimeInterval {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            let mut s = serializer.serialize_struct("tx_interval", 2)?;
+            s.serialize_field("from", &self.start)?;
+            s.serialize_field("to", &self.end)?;
+            s.end()
+        }
+    }
+
+    impl Serialize for TransactionInput {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            let mut s = serializer.serialize_struct("transaction_input", 2)?;
+            s.serialize_field("tx_interval", &self.interval)?;
+            s.serialize_field("tx_inputs", &self.inputs)?;
+            s.end()
+        }
+    }
+
+    impl SerializeAs for Timeout {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer {
+            match self {
+                Timeout::TimeConstant(n) => Integer(n).serialize(serializer),
+                Timeout::TimeParam(v) if extended => {
+                    let mut s = serializer.serialize_struct("timeout", 1)?;
+                    s.serialize_field("time_param", v)?;
+                    s.end()
+                },
                 Timeout::TimeParam(v) => {
                     return Err(serde::ser::Error::custom(format!("TimeParam not initialized: '{v}'.")))
                 }
@@ -171,8 +361,8 @@ pub mod json {
     }
 
 
-    impl Serialize for Contract {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    impl SerializeAs for Contract {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
             
@@ -180,63 +370,63 @@ pub mod json {
                 Contract::Close => serializer.serialize_str("close"),
                 Contract::When { 
                     when, 
-                    timeout_continuation: Some(timeout_continuation),
-                    timeout: Some(timeout) 
+                    timeout_continuation: Slot::Filled(timeout_continuation),
+                    timeout: Slot::Filled(timeout) 
                 } => {
-                    if when.iter().any(|x|x.is_none()) {
+                    if when.iter().any(|x|x.is_hole()) {
                         return Err(serde::ser::Error::custom(format!("A when contract contains null-cases (a hole in the list of cases). The list of cases is allowed to be empty, but it may not contain placeholder items such as holes.")))
                     }
                     let mut what = serializer.serialize_struct("when", 3)?;
-                    what.serialize_field("when", when)?;
-                    what.serialize_field("timeout_continuation", timeout_continuation)?;
-                    what.serialize_field("timeout", timeout)?;
+                    what.serialize_field("when", &As(when,extended))?;
+                    what.serialize_field("timeout_continuation", &As(timeout_continuation,extended))?;
+                    what.serialize_field("timeout", &As(timeout,extended))?;
                     what.end()
 
                 }
                 Contract::If { 
-                    r#if: Some(r#if), 
-                    then: Some(then),
-                    r#else: Some(r#else) 
+                    r#if: Slot::Filled(r#if), 
+                    then: Slot::Filled(then),
+                    r#else: Slot::Filled(r#else) 
                 } => {
 
                     let mut what = serializer.serialize_struct("if", 3)?;
-                    what.serialize_field("if", r#if)?;
-                    what.serialize_field("then", then)?;
-                    what.serialize_field("else", r#else)?;
+                    what.serialize_field("if", &As(r#if,extended))?;
+                    what.serialize_field("then", &As(then,extended))?;
+                    what.serialize_field("else", &As(r#else,extended))?;
                     what.end()
                 }
                 Contract::Assert { 
-                    assert: Some(assert),
-                    then: Some(then) 
+                    assert: Slot::Filled(assert),
+                    then: Slot::Filled(then) 
                 } => {
                     let mut what = serializer.serialize_struct("assert", 2)?;
-                    what.serialize_field("assert", assert)?;
-                    what.serialize_field("then", then)?;
+                    what.serialize_field("assert", &As(assert,extended))?;
+                    what.serialize_field("then", &As(then,extended))?;
                     what.end()
                 }
                 Contract::Let { 
                     r#let,
-                    be: Some(be),
-                    then : Some(then)
+                    be: Slot::Filled(be),
+                    then : Slot::Filled(then)
                 } => {
                     let mut what = serializer.serialize_struct("let", 3)?;
                     what.serialize_field("let", r#let)?;
-                    what.serialize_field("be", be)?;
-                    what.serialize_field("then", then)?;
+                    what.serialize_field("be", &As(be,extended))?;
+                    what.serialize_field("then", &As(then,extended))?;
                     what.end()
                 }
                 Contract::Pay { 
-                    from_account: Some(from_account),
-                    to: Some(to), 
-                    token: Some(token), 
-                    pay: Some(pay),
-                    then : Some(then)
+                    from_account: Slot::Filled(from_account),
+                    to: Slot::Filled(to), 
+                    token: Slot::Filled(token), 
+                    pay: Slot::Filled(pay),
+                    then : Slot::Filled(then)
                 } => {
                     let mut what = serializer.serialize_struct("pay", 5)?;
                     what.serialize_field("token", token)?;
                     what.serialize_field("to", to)?;
-                    what.serialize_field("then", then)?;
-                    what.serialize_field("pay", pay)?;
+                    what.serialize_field("then", &As(then,extended))?;
+                    what.serialize_field("pay", &As(pay,extended))?;
                     what.serialize_field("from_account", from_account)?;
                     
                     what.end()
@@ -248,86 +438,86 @@ pub mod json {
         }
     }
 
-    impl Serialize for Observation {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    impl SerializeAs for Observation {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
             match self {
                 Observation::ValueGT { 
-                    value: Some(value), 
-                    gt_than: Some(gt_than) 
+                    value: Slot::Filled(value), 
+                    gt_than: Slot::Filled(gt_than) 
                 } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("value", value)?;
-                    s.serialize_field("gt_than", gt_than)?;
+                    s.serialize_field("value", &As(value,extended))?;
+                    s.serialize_field("gt_than", &As(gt_than,extended))?;
                     s.end()
                 },
                 Observation::ValueGE { 
-                    value: Some(value), 
-                    ge_than: Some(ge_than) 
+                    value: Slot::Filled(value), 
+                    ge_than: Slot::Filled(ge_than) 
                 } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("value", value)?;
-                    s.serialize_field("ge_than", ge_than)?;
+                    s.serialize_field("value", &As(value,extended))?;
+                    s.serialize_field("ge_than", &As(ge_than,extended))?;
                     s.end()
                 },
                 Observation::ValueLT { 
-                    value: Some(value), 
-                    lt_than: Some(lt_than) 
+                    value: Slot::Filled(value), 
+                    lt_than: Slot::Filled(lt_than) 
                 } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("value", value)?;
-                    s.serialize_field("lt_than", lt_than)?;
+                    s.serialize_field("value", &As(value,extended))?;
+                    s.serialize_field("lt_than", &As(lt_than,extended))?;
                     s.end()
                 },
                 Observation::ValueLE { 
-                    value: Some(value), 
-                    le_than: Some(le_than) 
+                    value: Slot::Filled(value), 
+                    le_than: Slot::Filled(le_than) 
                 } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("value", value)?;
-                    s.serialize_field("le_than", le_than)?;
+                    s.serialize_field("value", &As(value,extended))?;
+                    s.serialize_field("le_than", &As(le_than,extended))?;
                     s.end()
                 },
                 Observation::ValueEQ { 
-                    value: Some(value), 
-                    equal_to: Some(equal_to) 
+                    value: Slot::Filled(value), 
+                    equal_to: Slot::Filled(equal_to) 
                 } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("value", value)?;
-                    s.serialize_field("equal_to", equal_to)?;
+                    s.serialize_field("value", &As(value,extended))?;
+                    s.serialize_field("equal_to", &As(equal_to,extended))?;
                     s.end()
                 },
                 Observation::True => serializer.serialize_bool(true),
                 Observation::False => serializer.serialize_bool(false),
-                Observation::ChoseSomething(Some(choice_id)) => {
+                Observation::ChoseSomething(Slot::Filled(choice_id)) => {
                     let mut s = serializer.serialize_struct("observation", 1)?;
                     s.serialize_field("choice_id", choice_id)?;
                     s.end()
                 },
                 Observation::OrObs { 
-                    either: Some(either), 
-                    or: Some(or)
+                    either: Slot::Filled(either), 
+                    or: Slot::Filled(or)
                 } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("either", either)?;
-                    s.serialize_field("or", or)?;
+                    s.serialize_field("either", &As(either,extended))?;
+                    s.serialize_field("or", &As(or,extended))?;
                     s.end()
                 },
                 Observation::AndObs { 
-                    both: Some(both), 
-                    and: Some(and)
+                    both: Slot::Filled(both), 
+                    and: Slot::Filled(and)
                  } => {
                     let mut s = serializer.serialize_struct("observation", 2)?;
-                    s.serialize_field("both", both)?;
-                    s.serialize_field("and", and)?;
+                    s.serialize_field("both", &As(both,extended))?;
+                    s.serialize_field("and", &As(and,extended))?;
                     s.end()
                 },
                 Observation::NotObs { 
-                    not: Some(not)
+                    not: Slot::Filled(not)
                  } => {
                     let mut s = serializer.serialize_struct("observation", 1)?;
-                    s.serialize_field("not", not)?;
+                    s.serialize_field("not", &As(not,extended))?;
                     s.end()
                 },
                 _ => {
@@ -337,20 +527,25 @@ pub mod json {
         }
     }
 
-    impl Serialize for Value {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    impl SerializeAs for Value {
+        fn serialize_as<S>(&self, serializer: S, extended: bool) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
             match self {
                 Value::TimeIntervalStart => serializer.serialize_str("time_interval_start") ,
                 Value::TimeIntervalEnd => serializer.serialize_str("time_interval_end"), 
-                Value::AvailableMoney(Some(a), Some(b)) => {
+                Value::AvailableMoney(Slot::Filled(a), Slot::Filled(b)) => {
                     let mut s = serializer.serialize_struct("value", 2)?;
                     s.serialize_field("in_account", a)?;
                     s.serialize_field("amount_of_token", b)?;
                     s.end()
                 },
-                Value::ConstantValue(v) => serializer.serialize_i64(*v),
+                Value::ConstantValue(v) => Integer(v).serialize(serializer),
+                Value::ConstantParam(v) if extended => {
+                    let mut s = serializer.serialize_struct("value", 1)?;
+                    s.serialize_field("constant_param", v)?;
+                    s.end()
+                },
                 Value::ConstantParam(v) => {
                     return Err(serde::ser::Error::custom(format!("Constant param not initialized: '{v}'")))
                 },
@@ -359,49 +554,49 @@ pub mod json {
                     s.serialize_field("use_value", v)?;
                     s.end()
                 },
-                Value::MulValue(Some(a),Some(b)) => {
+                Value::MulValue(Slot::Filled(a),Slot::Filled(b)) => {
                     let mut s = serializer.serialize_struct("value", 2)?;
-                    s.serialize_field("times", b)?;
-                    s.serialize_field("multiply", a)?;
+                    s.serialize_field("times", &As(b,extended))?;
+                    s.serialize_field("multiply", &As(a,extended))?;
                     s.end()
                 }
-                Value::DivValue(Some(a),Some(b)) => {
+                Value::DivValue(Slot::Filled(a),Slot::Filled(b)) => {
                     let mut s = serializer.serialize_struct("value", 2)?;
-                    s.serialize_field("divide", a)?;
-                    s.serialize_field("by", b)?;
+                    s.serialize_field("divide", &As(a,extended))?;
+                    s.serialize_field("by", &As(b,extended))?;
                     s.end()
                 }
-                Value::SubValue(Some(a),Some(b)) => {
+                Value::SubValue(Slot::Filled(a),Slot::Filled(b)) => {
                     let mut s = serializer.serialize_struct("value", 2)?;
-                    s.serialize_field("value", a)?;
-                    s.serialize_field("minus", b)?;
+                    s.serialize_field("value", &As(a,extended))?;
+                    s.serialize_field("minus", &As(b,extended))?;
                     s.end()
                 }
-                Value::AddValue(Some(a),Some(b)) => {
+                Value::AddValue(Slot::Filled(a),Slot::Filled(b)) => {
                     let mut s = serializer.serialize_struct("value", 2)?;
-                    s.serialize_field("and", b)?;
-                    s.serialize_field("add", a)?;                    
+                    s.serialize_field("and", &As(b,extended))?;
+                    s.serialize_field("add", &As(a,extended))?;                    
                     s.end()
                 }
-                Value::NegValue(Some(a)) => {
+                Value::NegValue(Slot::Filled(a)) => {
                     let mut s = serializer.serialize_struct("value", 1)?;
-                    s.serialize_field("negate", a)?;
+                    s.serialize_field("negate", &As(a,extended))?;
                     s.end()
                 },
-                Value::ChoiceValue(Some(choice_id)) => {
+                Value::ChoiceValue(Slot::Filled(choice_id)) => {
                     let mut s = serializer.serialize_struct("value", 1)?;
                     s.serialize_field("value_of_choice", choice_id)?;
                     s.end()
                 },
                 Value::Cond(
-                    Some(r#if),
-                    Some(then),
-                    Some(r#else)
+                    Slot::Filled(r#if),
+                    Slot::Filled(then),
+                    Slot::Filled(r#else)
                 ) => {
                     let mut s = serializer.serialize_struct("value", 3)?;
-                    s.serialize_field("then", then)?;
-                    s.serialize_field("if", r#if)?;
-                    s.serialize_field("else", r#else)?;
+                    s.serialize_field("then", &As(then,extended))?;
+                    s.serialize_field("if", &As(r#if,extended))?;
+                    s.serialize_field("else", &As(r#else,extended))?;
                     s.end()
                 },
                 _ => {
@@ -415,37 +610,50 @@ pub mod json {
 
 pub mod marlowe {
     
+    use crate::parsing::comments::CommentTable;
+    use crate::parsing::pretty::{format_with_comments, FormatOptions};
     use crate::types::marlowe::*;
 
     /// Takes an instance of a Marlowe contract and serializes
-    /// it into the Marlowe DSL format
+    /// it into the Marlowe DSL format.
+    /// See [`crate::parsing::pretty::format`] for the layout used by the playground.
     pub fn serialize(contract:Contract) -> String { 
         format!("{:#}",contract)
     }
 
+    /// Serializes a contract into the Marlowe DSL format along with its comments,
+    /// using the layout of the playground (see [`crate::parsing::pretty::format_with_comments`]).
+    pub fn serialize_with_comments(contract:Contract,comments:&CommentTable) -> String { 
+        format_with_comments(&contract,comments,&FormatOptions::default())
+    }
+
+    impl<T:std::fmt::Display> std::fmt::Display for Slot<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Slot::Filled(x) => write!(f, "{x}"),
+                Slot::Hole(name) => write!(f, "?{name}")
+            }
+        }
+    }
+
     impl std::fmt::Display for Action {
         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
             match self {
                 Action::Deposit { party, into_account, of_token, deposits } => 
                     write!(f, "(Deposit {} {} {} {})",
-                        match into_account {None=>"?party".to_string(),Some(v)=>format!("{v}")},    
-                        match party {None=>"?from_party".to_string(),Some(v)=>format!("{v}")},
-                        match of_token {None=>"?token".to_string(),Some(v)=>format!("{v}")},
-                        match deposits {None=>"?value".to_string(),Some(v)=>format!("{v}")},
+                        into_account,    
+                        party,
+                        of_token,
+                        deposits,
                     ),
                 Action::Notify { notify_if: observation } => 
                     write!(f, "(Notify {})",
-                        match observation {None=>"?observation".to_string(),Some(v)=>format!("{v}")},
+                        observation,
                     ),
                 Action::Choice { for_choice, choose_between } => {
-                    let stritems : Vec<String> = choose_between.iter().map(|x|
-                        match x {
-                            Some(v) => format!("{}",v),
-                            None => String::from("?bound")
-                        }
-                    ).collect();
+                    let stritems : Vec<String> = choose_between.iter().map(|x|x.to_string()).collect();
                     write!(f, "(Choice {} [{}])",
-                        match for_choice {None=>"?choiceId".to_string(),Some(v)=>format!("{v}")},
+                        for_choice,
                         stritems.join(",")
                     )
                 },
@@ -457,9 +665,9 @@ pub mod marlowe {
         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
             match self {
                 Payee::Account(p) => write!(f, "(Account {})",
-                    match p {None=>"?party".to_string(),Some(v)=>format!("{v}")}),
+                    p),
                 Payee::Party(p) => write!(f, "(Party {})",
-                    match p {None=>"?party".to_string(),Some(v)=>format!("{v}")}
+                    p
                 ),
             }
         }
@@ -470,11 +678,10 @@ pub mod marlowe {
 
             match self {
                 Party::Role { role_token: s } => write!(f, "(Role \"{}\")",s),
-                Party::PK { pk_hash: s } => {
-                    // todo: we currently allow serializing any length and content string
-                    // here but we should probably not ...
-                    write!(f, "(PK \"{}\")",s)
-                }
+                // no validation here, the DSL is also used for contracts that are still being written.
+                // see crate::validation for checking the hash
+                Party::PK { pk_hash: s } => write!(f, "(PK \"{}\")",s),
+                Party::Address { address: s } => write!(f, "(Address \"{}\")",s)
             }
         }
     }
@@ -484,28 +691,28 @@ pub mod marlowe {
             match self {
                 Observation::ValueGT { value: a, gt_than: b } => 
                     write!(f, "(ValueGT {} {})",
-                    match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b
                 ),
                 Observation::ValueGE { value: a, ge_than: b } => 
                     write!(f, "(ValueGE {} {})",
-                    match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b
                 ),
                 Observation::ValueLT { value: a, lt_than: b } => 
                     write!(f, "(ValueLT {} {})",
-                    match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b
                 ),
                 Observation::ValueLE { value: a, le_than: b } => 
                     write!(f, "(ValueLE {} {})",
-                    match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b
                 ),
                 Observation::ValueEQ { value: a, equal_to: b } =>  
                     write!(f, "(ValueEQ {} {})",
-                    match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b
                 ),
                 Observation::True => 
                     write!(f, "TrueObs"),            
@@ -513,21 +720,21 @@ pub mod marlowe {
                     write!(f, "FalseObs"),
                 Observation::ChoseSomething(a) => 
                     write!(f, "(ChoseSomething {})",
-                        match a {None=>"?choiceId".to_string(),Some(v)=>format!("{v}")}
+                        a
                     ),
                 Observation::OrObs { either: a, or: b } => 
                     write!(f, "(OrObs {} {})",
-                        match a {None=>"?observation".to_string(),Some(v)=>format!("{v}")},
-                        match b {None=>"?observation".to_string(),Some(v)=>format!("{v}")}
+                        a,
+                        b
                     ),
                 Observation::AndObs { both: a, and: b } => 
                     write!(f, "(AndObs {} {})",
-                    match a {None=>"?observation".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?observation".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b
                 ),    
                 Observation::NotObs { not: a } => 
                     write!(f, "(NotObs {})",
-                        match a {None=>"?observation".to_string(),Some(v)=>format!("{v}")}
+                        a
                     )
             }
         }
@@ -540,8 +747,8 @@ pub mod marlowe {
                 Value::TimeIntervalStart => write!(f,"TimeIntervalStart"),
                 Value::AvailableMoney(a, b) => 
                     write!(f, "(AvailableMoney {} {})",
-                        match a {None=>"?party".to_string(),Some(v)=>format!("{v}")},
-                        match b {None=>"?token".to_string(),Some(v)=>format!("{v}")}
+                        a,
+                        b
                     ),
                 Value::ConstantValue(a) => 
                     write!(f, "(Constant {})",a),
@@ -551,37 +758,37 @@ pub mod marlowe {
                     write!(f, "(UseValue \"{}\")",a),
                 Value::MulValue(a, b) => 
                     write!(f, "(MulValue {} {})",
-                        match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                        match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                        a,
+                        b
                     ),
                 Value::DivValue(a, b) => 
                     write!(f, "(DivValue {} {})",
-                        match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                        match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                        a,
+                        b
                     ),
                 Value::SubValue(a, b) => 
                     write!(f, "(SubValue {} {})",
-                        match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                        match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                        a,
+                        b
                     ),
                 Value::AddValue(a, b) => 
                     write!(f, "(AddValue {} {})",
-                        match a {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                        match b {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                        a,
+                        b
                     ),
                 Value::NegValue(a) => 
                     write!(f, "(NegValue {})",
-                        match a {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                        a
                     ),
                 Value::ChoiceValue(a) => 
                     write!(f, "(ChoiceValue {})",
-                        match a {None=>"?choiceId".to_string(),Some(v)=>format!("{v}")}
+                        a
                     ),
                 Value::Cond(a, b, c) => 
                     write!(f, "(Cond {} {} {})",
-                    match a {None=>"?observation".to_string(),Some(v)=>format!("{v}")},
-                    match b {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                    match c {None=>"?value".to_string(),Some(v)=>format!("{v}")}
+                    a,
+                    b,
+                    c
                 ),
             }
         }
@@ -600,7 +807,7 @@ pub mod marlowe {
     impl std::fmt::Display for ChoiceId {
         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
             write!(f, "(ChoiceId \"{}\" {})",self.choice_name,
-                match &self.choice_owner {None=>"?party".to_string(),Some(v)=>format!("{v}")}
+                &self.choice_owner
             )
         }
     }
@@ -629,10 +836,16 @@ pub mod marlowe {
 
     impl std::fmt::Display for Case {
         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-            write!(f, "(Case {} {})",
-                match &self.case {None=>"?action".to_string(),Some(v)=>format!("{v}")},
-                match &self.then {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
-            )
+            match self {
+                Case::Case { case, then } => write!(f, "(Case {} {})",
+                    case,
+                    then,
+                ),
+                Case::MerkleizedCase { case, then } => write!(f, "(MerkleizedCase {} \"{}\")",
+                    case,
+                    then
+                )
+            }
         }
     }
 
@@ -643,50 +856,45 @@ pub mod marlowe {
                 Contract::Close => write!(f, "Close"),
                 Contract::When { when, timeout, timeout_continuation } => {
                     
-                    let case_str : Vec<String> = when.iter().map(|x|
-                        match x {
-                            Some(v) => format!("{}",v),
-                            None => "?case".to_string()
-                        }
-                    ).collect();
+                    let case_str : Vec<String> = when.iter().map(|x|x.to_string()).collect();
 
                     if f.alternate() {
                         write!(f, "When [ {} ] {} {}",case_str.join(",\n"),
-                            match timeout {None=>"?timeout".to_string(),Some(v)=>format!("{v}")},
-                            match timeout_continuation {None=>"?contract".to_string(),Some(v)=>format!("{v}")}
+                            timeout,
+                            timeout_continuation
                         )
                     } else {
                         write!(f, "(When [ {} ] {} {})",case_str.join(",\n"),
-                            match timeout {None=>"?timeout".to_string(),Some(v)=>format!("{v}")},
-                            match timeout_continuation {None=>"?contract".to_string(),Some(v)=>format!("{v}")}
+                            timeout,
+                            timeout_continuation
                         )
                     }
                 }
                 Contract::If { r#if, then, r#else } => {
                     if f.alternate() {
                         write!(f, "If {} {} {}",
-                            match r#if {None=>"?observation".to_string(),Some(v)=>format!("{v}")}, 
-                            match then {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
-                            match r#else {None=>"?contract".to_string(),Some(v)=>format!("{v}")}
+                            r#if, 
+                            then,
+                            r#else
                         )
                     } else {
                         write!(f, "(If {} {} {})",
-                            match r#if {None=>"?observation".to_string(),Some(v)=>format!("{v}")}, 
-                            match then {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
-                            match r#else {None=>"?contract".to_string(),Some(v)=>format!("{v}")}
+                            r#if, 
+                            then,
+                            r#else
                         )
                     }
                 },
                 Contract::Assert { assert, then } => {
                     if f.alternate() {
                         write!(f, "Assert {} {}",
-                            match assert {None=>"?observation".to_string(),Some(v)=>format!("{v}")}, 
-                            match then {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
+                            assert, 
+                            then,
                         )
                     } else {
                         write!(f, "(Assert {} {})",
-                            match assert {None=>"?observation".to_string(),Some(v)=>format!("{v}")}, 
-                            match then {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
+                            assert, 
+                            then,
                         )
                     }
                 },
@@ -694,14 +902,14 @@ pub mod marlowe {
                     if f.alternate() {
                         write!(f, "Let \"{}\" {} {}",
                             value_name,
-                            match value {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                            match continue_as {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
+                            value,
+                            continue_as,
                     )
                     } else {
                         write!(f, "(Let \"{}\" {} {})",
                             value_name,
-                            match value {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                            match continue_as {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
+                            value,
+                            continue_as,
                         )
                     }
                 },
@@ -714,19 +922,19 @@ pub mod marlowe {
                 } => { 
                     if f.alternate() {
                         write!(f, "Pay {} {} {} {} {}",
-                            match party {None=>"?party".to_string(),Some(v)=>format!("{v}")},
-                            match payee {None=>"?payee".to_string(),Some(v)=>format!("{v}")},
-                            match currency {None=>"?token".to_string(),Some(v)=>format!("{v}")},
-                            match amount {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                            match continue_as {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
+                            party,
+                            payee,
+                            currency,
+                            amount,
+                            continue_as,
                         )
                     } else {
                         write!(f, "(Pay {} {} {} {} {})",
-                            match party {None=>"?party".to_string(),Some(v)=>format!("{v}")},
-                            match payee {None=>"?payee".to_string(),Some(v)=>format!("{v}")},
-                            match currency {None=>"?token".to_string(),Some(v)=>format!("{v}")},
-                            match amount {None=>"?value".to_string(),Some(v)=>format!("{v}")},
-                            match continue_as {None=>"?contract".to_string(),Some(v)=>format!("{v}")},
+                            party,
+                            payee,
+                            currency,
+                            amount,
+                            continue_as,
                         )
                     }
                 },
@@ -736,4 +944,245 @@ pub mod marlowe {
         }
 
     }
-}
\ No newline at end of file
+}
+pub mod blockly {
+
+    // Blockly XML as used by the visual editor of the Marlowe playground.
+    // Holes are written as unconnected inputs and statements. Blockly has nothing
+    // to connect a case to other than its action, and no empty items in lists of
+    // cases and bounds, so holes in those places can not be written and are
+    // reported as errors rather than left out. The same goes for merkleized cases,
+    // which can not be edited in Blockly (rehydrate the contract first).
+
+    use crate::types::marlowe::*;
+
+    /// The type of the block that holds the root contract of a workspace
+    pub const ROOT_BLOCK_TYPE : &str = "BaseContractType";
+
+    /// A part of a contract that can not be written as Blockly blocks
+    #[derive(Debug,Clone,PartialEq)]
+    pub enum BlocklyError {
+        /// A hole where Blockly needs a block, such as the action of a case or an item in a list of cases or bounds
+        Hole { name: String, expected: &'static str },
+        /// A merkleized case, with the hash of its continuation, see [`crate::merkleization::rehydrate`]
+        MerkleizedCase { hash: String }
+    }
+
+    impl std::fmt::Display for BlocklyError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                BlocklyError::Hole { name, expected } =>
+                    write!(f, "The hole '?{name}' can not be written to Blockly, which has no place for a missing {expected}."),
+                BlocklyError::MerkleizedCase { hash } =>
+                    write!(f, "The merkleized case with the continuation '{hash}' can not be written to Blockly, rehydrate the contract first.")
+            }
+        }
+    }
+
+    impl std::error::Error for BlocklyError {}
+
+    type Result<T> = std::result::Result<T,BlocklyError>;
+
+    fn escape(text:&str) -> String {
+        text.replace('&',"&amp;").replace('<',"&lt;").replace('>',"&gt;").replace('"',"&quot;").replace('\'',"&apos;")
+    }
+
+    fn field(name:&str,text:&str) -> String {
+        format!("<field name=\"{name}\">{}</field>",escape(text))
+    }
+
+    /// The content of a slot that Blockly can not leave empty
+    fn filled<'a,T>(x:&'a Slot<T>,expected:&'static str) -> Result<&'a T> {
+        match x {
+            Slot::Filled(x) => Ok(x),
+            Slot::Hole(name) => Err(BlocklyError::Hole { name: name.clone(), expected })
+        }
+    }
+
+    fn value<T:ToBlock>(name:&str,input:Option<&T>) -> Result<String> {
+        match input {
+            Some(x) => Ok(format!("<value name=\"{name}\">{}</value>",x.to_block()?)),
+            None => Ok(String::new())
+        }
+    }
+
+    fn statement(name:&str,blocks:Vec<(&str,String)>) -> String {
+        // each block is connected to the previous one through its "next" element
+        let chain = blocks.into_iter().rev().fold(String::new(),|next,(kind,content)| {
+            if next.is_empty() {
+                block(kind,content)
+            } else {
+                block(kind,format!("{content}<next>{next}</next>"))
+            }
+        });
+        if chain.is_empty() { chain } else { format!("<statement name=\"{name}\">{chain}</statement>") }
+    }
+
+    fn contract_statement(name:&str,contract:Option<&Contract>) -> Result<String> {
+        match contract {
+            Some(c) => Ok(format!("<statement name=\"{name}\">{}</statement>",c.to_block()?)),
+            None => Ok(String::new())
+        }
+    }
+
+    fn block(kind:&str,content:String) -> String {
+        format!("<block type=\"{kind}\">{content}</block>")
+    }
+
+    fn choice_id(choice:&Slot<ChoiceId>) -> Result<String> {
+        match choice {
+            Slot::Filled(c) => Ok(format!("{}{}",field("choice_name",&c.choice_name),value("party",c.choice_owner.as_ref())?)),
+            Slot::Hole(_) => Ok(String::new())
+        }
+    }
+
+    /// Converts a node into a Blockly block
+    pub trait ToBlock {
+        fn to_block(&self) -> Result<String>;
+    }
+
+    impl<T:ToBlock> ToBlock for Box<T> {
+        fn to_block(&self) -> Result<String> { self.as_ref().to_block() }
+    }
+
+    impl ToBlock for Party {
+        fn to_block(&self) -> Result<String> {
+            Ok(match self {
+                Party::PK { pk_hash } => block("PKPartyType",field("pubkey",pk_hash)),
+                Party::Role { role_token } => block("RolePartyType",field("role",role_token)),
+                Party::Address { address } => block("AddressPartyType",field("address",address))
+            })
+        }
+    }
+
+    impl ToBlock for Token {
+        fn to_block(&self) -> Result<String> {
+            Ok(match self {
+                Token::ADA => block("AdaTokenType",String::new()),
+                Token::Custom { token_name, currency_symbol } => 
+                    block("CustomTokenType",format!("{}{}",field("currency_symbol",currency_symbol),field("token_name",token_name)))
+            })
+        }
+    }
+
+    impl ToBlock for Payee {
+        fn to_block(&self) -> Result<String> {
+            Ok(match self {
+                Payee::Account(p) => block("AccountPayeeType",value("party",p.as_ref())?),
+                Payee::Party(p) => block("PartyPayeeType",value("party",p.as_ref())?)
+            })
+        }
+    }
+
+    impl ToBlock for Value {
+        fn to_block(&self) -> Result<String> {
+            let binary = |kind,a:&Slot<Box<Value>>,b:&Slot<Box<Value>>| 
+                Ok(block(kind,format!("{}{}",value("value1",a.as_ref())?,value("value2",b.as_ref())?)));
+            match self {
+                Value::TimeIntervalStart => Ok(block("TimeIntervalStartValueType",String::new())),
+                Value::TimeIntervalEnd => Ok(block("TimeIntervalEndValueType",String::new())),
+                Value::AvailableMoney(p,t) => 
+                    Ok(block("AvailableMoneyValueType",format!("{}{}",value("party",p.as_ref())?,value("token",t.as_ref())?))),
+                Value::ConstantValue(n) => Ok(block("ConstantValueType",field("constant",&n.to_string()))),
+                Value::ConstantParam(name) => Ok(block("ConstantParamValueType",field("paramName",name))),
+                Value::UseValue(name) => Ok(block("UseValueValueType",field("value_id",name))),
+                Value::MulValue(a,b) => binary("MulValueValueType",a,b),
+                Value::DivValue(a,b) => binary("DivValueValueType",a,b),
+                Value::SubValue(a,b) => binary("SubValueValueType",a,b),
+                Value::AddValue(a,b) => binary("AddValueValueType",a,b),
+                Value::NegValue(a) => Ok(block("NegValueValueType",value("value",a.as_ref())?)),
+                Value::ChoiceValue(c) => Ok(block("ChoiceValueValueType",choice_id(c)?)),
+                Value::Cond(o,a,b) => Ok(block("CondObservationValueValueType",format!("{}{}{}",
+                    value("condition",o.as_ref())?,value("then",a.as_ref())?,value("else",b.as_ref())?)))
+            }
+        }
+    }
+
+    impl ToBlock for Observation {
+        fn to_block(&self) -> Result<String> {
+            let values = |kind,a:&Slot<Box<Value>>,b:&Slot<Box<Value>>| 
+                Ok(block(kind,format!("{}{}",value("value1",a.as_ref())?,value("value2",b.as_ref())?)));
+            let observations = |kind,a:&Slot<Box<Observation>>,b:&Slot<Box<Observation>>| 
+                Ok(block(kind,format!("{}{}",value("observation1",a.as_ref())?,value("observation2",b.as_ref())?)));
+            match self {
+                Observation::ValueGT { value: a, gt_than: b } => values("ValueGTObservationType",a,b),
+                Observation::ValueGE { value: a, ge_than: b } => values("ValueGEObservationType",a,b),
+                Observation::ValueLT { value: a, lt_than: b } => values("ValueLTObservationType",a,b),
+                Observation::ValueLE { value: a, le_than: b } => values("ValueLEObservationType",a,b),
+                Observation::ValueEQ { value: a, equal_to: b } => values("ValueEQObservationType",a,b),
+                Observation::True => Ok(block("TrueObservationType",String::new())),
+                Observation::False => Ok(block("FalseObservationType",String::new())),
+                Observation::ChoseSomething(c) => Ok(block("ChoseSomethingObservationType",choice_id(c)?)),
+                Observation::OrObs { either, or } => observations("OrObservationType",either,or),
+                Observation::AndObs { both, and } => observations("AndObservationType",both,and),
+                Observation::NotObs { not } => Ok(block("NotObservationType",value("observation",not.as_ref())?))
+            }
+        }
+    }
+
+    fn case(case:&Case) -> Result<(&'static str,String)> {
+        let (action,then) = match case {
+            Case::Case { case, then } => (filled(case,"action")?,contract_statement("contract",then.as_deref())?),
+            Case::MerkleizedCase { then, .. } => return Err(BlocklyError::MerkleizedCase { hash: then.clone() })
+        };
+        Ok(match action {
+            Action::Deposit { party, of_token, into_account, deposits } => ("DepositActionType",format!("{}{}{}{}{then}",
+                value("from_party",party.as_ref())?,
+                value("party",into_account.as_ref())?,
+                value("token",of_token.as_ref())?,
+                value("value",deposits.as_ref())?)),
+            Action::Choice { for_choice, choose_between } => {
+                let bounds = choose_between.iter()
+                    .map(|x|filled(x,"bound").map(|Bound(from,to)|("BoundsType",format!("{}{}",field("from",&from.to_string()),field("to",&to.to_string())))))
+                    .collect::<Result<_>>()?;
+                ("ChoiceActionType",format!("{}{}{then}",choice_id(for_choice)?,statement("bounds",bounds)))
+            },
+            Action::Notify { notify_if } => 
+                ("NotifyActionType",format!("{}{then}",value("observation",notify_if.as_ref())?))
+        })
+    }
+
+    impl ToBlock for Contract {
+        fn to_block(&self) -> Result<String> {
+            match self {
+                Contract::Close => Ok(block("CloseContractType",String::new())),
+                Contract::When { when, timeout_continuation, timeout } => {
+                    let cases = when.iter().map(|x|case(filled(x,"case")?)).collect::<Result<_>>()?;
+                    let timeout = match timeout.as_ref() {
+                        Some(Timeout::TimeConstant(t)) => format!("{}{}",field("timeout_type","time"),field("timeout",&t.to_string())),
+                        Some(Timeout::TimeParam(p)) => format!("{}{}",field("timeout_type","time_param"),field("timeout",p)),
+                        None => String::new()
+                    };
+                    Ok(block("WhenContractType",format!("{}{timeout}{}",
+                        statement("case",cases),
+                        contract_statement("contract",timeout_continuation.as_deref())?)))
+                },
+                Contract::If { r#if, then, r#else } => Ok(block("IfContractType",format!("{}{}{}",
+                    value("observation",r#if.as_ref())?,
+                    contract_statement("contract1",then.as_deref())?,
+                    contract_statement("contract2",r#else.as_deref())?))),
+                Contract::Assert { assert, then } => Ok(block("AssertContractType",format!("{}{}",
+                    value("observation",assert.as_ref())?,
+                    contract_statement("contract",then.as_deref())?))),
+                Contract::Let { r#let, be, then } => Ok(block("LetContractType",format!("{}{}{}",
+                    field("value_id",r#let),
+                    value("value",be.as_ref())?,
+                    contract_statement("contract",then.as_deref())?))),
+                Contract::Pay { from_account, to, token, pay, then } => Ok(block("PayContractType",format!("{}{}{}{}{}",
+                    value("party",from_account.as_ref())?,
+                    value("payee",to.as_ref())?,
+                    value("token",token.as_ref())?,
+                    value("value",pay.as_ref())?,
+                    contract_statement("contract",then.as_deref())?)))
+            }
+        }
+    }
+
+    /// Takes an instance of a Marlowe contract and serializes it into a
+    /// Blockly XML workspace as used by the visual editor of the Marlowe playground.
+    /// Fails if the contract contains a hole or merkleized case that Blockly can not represent, see [`BlocklyError`].
+    pub fn serialize(contract:Contract) -> Result<String> {
+        Ok(format!("<xml xmlns=\"https://developers.google.com/blockly/xml\"><block type=\"{ROOT_BLOCK_TYPE}\" x=\"13\" y=\"187\">{}</block></xml>",
+            contract_statement(ROOT_BLOCK_TYPE,Some(&contract))?))
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use crate::parsing::error::Span;
+
+/// Location of a node within a contract, given as the indexes of the 
+/// sub-nodes leading up to it from the root contract. Sub-nodes are 
+/// counted in the order they are written in the Marlowe DSL, including
+/// holes, names and numbers. 
+/// 
+/// For example, in `When [ (Case (Notify TrueObs) Close) ] 42 Close` 
+/// the path `[0,0,1]` refers to the `Close` continuation of the first case,
+/// `[1]` to the timeout and `[2]` to the timeout continuation.
+pub type NodePath = Vec<usize>;
+
+/// Side table with the source location of each node in a deserialized contract
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct SpanTable {
+    spans: HashMap<NodePath,Span>
+}
+
+impl SpanTable {
+
+    pub(crate) fn insert(&mut self,path:NodePath,span:Span) {
+        self.spans.insert(path,span);
+    }
+
+    /// The location of the node found at the given path, if any
+    pub fn get(&self,path:&[usize]) -> Option<Span> {
+        self.spans.get(path).copied()
+    }
+
+    /// All known nodes and their locations, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item=(&NodePath,&Span)> {
+        self.spans.iter()
+    }
+
+    /// The path of the innermost node that contains the given byte offset
+    pub fn path_at(&self,offset:usize) -> Option<&NodePath> {
+        self.spans.iter()
+            .filter(|(_,span)| span.start <= offset && offset < span.end)
+            .max_by_key(|(path,_)| path.len())
+            .map(|(path,_)| path)
+    }
+}
@@ -0,0 +1,857 @@
+// Plutus Data representation of contracts, as used for on-chain datums.
+// The constructor indexes are the ones given to makeIsDataIndexed in the
+// Marlowe V1 semantics (Language.Marlowe.Core.V1.Semantics.Types), and the
+// CBOR encoding follows the one used by the Plutus ledger: non-empty lists
+// are indefinite-length, byte strings longer than 64 bytes are chunked.
+
+use std::collections::BTreeMap;
+
+use num_bigint::{BigUint, Sign};
+
+use crate::semantics::State;
+use crate::types::address::{Credential, Network, ShelleyAddress, StakeReference};
+use crate::validation::{validate_party, validate_token, ValidationErrorKind};
+use crate::types::marlowe::*;
+
+/// The generic data type used by Plutus scripts
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum PlutusData {
+    Constr(u64,Vec<PlutusData>),
+    Map(Vec<(PlutusData,PlutusData)>),
+    List(Vec<PlutusData>),
+    Integer(BigInt),
+    Bytes(Vec<u8>)
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum PlutusDataError {
+    /// The contract contains a hole (named here without the '?'), which can not be represented as Plutus Data
+    Hole(String),
+    /// The contract contains a time or constant parameter that has not been given a value
+    UninitializedParam(String),
+    /// A string that must be hex encoded (currency symbols and public key hashes) is not
+    InvalidHex(String),
+    /// A party or token that can not exist on chain, see [`crate::validation`]
+    Validation(ValidationErrorKind),
+    /// The data is valid Plutus Data, but does not represent the expected type
+    UnexpectedData { expected: String, found: String },
+    /// The input is not valid CBOR encoded Plutus Data
+    Cbor(String)
+}
+
+impl std::fmt::Display for PlutusDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlutusDataError::Hole(name) => write!(f, "The contract contains the hole '?{name}', holes can not be encoded as Plutus Data."),
+            PlutusDataError::UninitializedParam(name) => write!(f, "The parameter '{name}' has not been initialized."),
+            PlutusDataError::InvalidHex(text) => write!(f, "'{text}' is not a valid hex string."),
+            PlutusDataError::Validation(e) => write!(f, "{e}"),
+            PlutusDataError::UnexpectedData { expected, found } => write!(f, "Expected {expected}, found: {found}"),
+            PlutusDataError::Cbor(message) => write!(f, "Invalid CBOR: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PlutusDataError {}
+
+type Result<T> = std::result::Result<T,PlutusDataError>;
+
+pub trait ToPlutusData {
+    fn to_plutus_data(&self) -> Result<PlutusData>;
+}
+
+pub trait FromPlutusData : Sized {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self>;
+}
+
+/// Encodes a contract (or any part of one) as CBOR encoded Plutus Data
+pub fn to_cbor<T:ToPlutusData>(x:&T) -> Result<Vec<u8>> {
+    Ok(x.to_plutus_data()?.to_cbor())
+}
+
+/// Same as [`to_cbor`], but returns the CBOR as a hex string
+pub fn to_cbor_hex<T:ToPlutusData>(x:&T) -> Result<String> {
+    Ok(hex::encode(to_cbor(x)?))
+}
+
+/// Decodes a contract (or any part of one) from CBOR encoded Plutus Data
+pub fn from_cbor<T:FromPlutusData>(bytes:&[u8]) -> Result<T> {
+    T::from_plutus_data(&PlutusData::from_cbor(bytes)?)
+}
+
+/// Same as [`from_cbor`], but takes the CBOR as a hex string
+pub fn from_cbor_hex<T:FromPlutusData>(text:&str) -> Result<T> {
+    let bytes = hex::decode(text.trim()).map_err(|_|PlutusDataError::InvalidHex(text.to_string()))?;
+    from_cbor(&bytes)
+}
+
+// ========== CBOR ==========
+
+fn write_head(major:u8,argument:u64,out:&mut Vec<u8>) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => { out.push(major | 24); out.push(argument as u8) },
+        0x100..=0xffff => { out.push(major | 25); out.extend((argument as u16).to_be_bytes()) },
+        0x10000..=0xffff_ffff => { out.push(major | 26); out.extend((argument as u32).to_be_bytes()) },
+        _ => { out.push(major | 27); out.extend(argument.to_be_bytes()) }
+    }
+}
+
+fn write_bytes(bytes:&[u8],out:&mut Vec<u8>) {
+    if bytes.len() <= 64 {
+        write_head(2,bytes.len() as u64,out);
+        out.extend(bytes);
+    } else {
+        out.push(0x5f);
+        for chunk in bytes.chunks(64) {
+            write_head(2,chunk.len() as u64,out);
+            out.extend(chunk);
+        }
+        out.push(0xff);
+    }
+}
+
+fn write_list(items:&[PlutusData],out:&mut Vec<u8>) {
+    if items.is_empty() {
+        out.push(0x80);
+    } else {
+        out.push(0x9f);
+        for x in items { x.write_cbor(out) }
+        out.push(0xff);
+    }
+}
+
+fn write_big_integer(tag:u64,n:&BigUint,out:&mut Vec<u8>) {
+    write_head(6,tag,out);
+    write_bytes(&n.to_bytes_be(),out);
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> Decoder<'a> {
+
+    fn error<T>(&self,message:&str) -> Result<T> {
+        Err(PlutusDataError::Cbor(format!("{message} at byte {}",self.position)))
+    }
+
+    fn take(&mut self,count:usize) -> Result<&'a [u8]> {
+        if self.position + count > self.bytes.len() {
+            return self.error("unexpected end of input")
+        }
+        let slice = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(slice)
+    }
+
+    fn peek(&self) -> Result<u8> {
+        match self.bytes.get(self.position) {
+            Some(x) => Ok(*x),
+            None => self.error("unexpected end of input")
+        }
+    }
+
+    /// Reads the major type and argument of the next item. The argument is None for indefinite length items.
+    fn head(&mut self) -> Result<(u8,Option<u64>)> {
+        let initial = self.take(1)?[0];
+        let (major,info) = (initial >> 5,initial & 0x1f);
+        let argument = match info {
+            0..=23 => Some(info as u64),
+            24 => Some(self.take(1)?[0] as u64),
+            25 => Some(u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as u64),
+            26 => Some(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as u64),
+            27 => Some(u64::from_be_bytes(self.take(8)?.try_into().unwrap())),
+            31 if matches!(major,2 | 4 | 5) => None,
+            _ => return self.error("unsupported additional information")
+        };
+        Ok((major,argument))
+    }
+
+    fn is_break(&mut self) -> Result<bool> {
+        if self.peek()? == 0xff {
+            self.position += 1;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn length(&self,argument:u64) -> Result<usize> {
+        // every item takes at least one byte, so anything longer than the input is invalid
+        match usize::try_from(argument) {
+            Ok(n) if n <= self.bytes.len() - self.position => Ok(n),
+            _ => self.error("length out of range")
+        }
+    }
+
+    fn bytes(&mut self,argument:Option<u64>) -> Result<Vec<u8>> {
+        match argument {
+            Some(n) => {
+                let n = self.length(n)?;
+                Ok(self.take(n)?.to_vec())
+            },
+            None => {
+                let mut out = vec![];
+                while !self.is_break()? {
+                    match self.head()? {
+                        (2,Some(n)) => {
+                            let n = self.length(n)?;
+                            out.extend(self.take(n)?)
+                        },
+                        _ => return self.error("expected a byte string chunk")
+                    }
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    fn items(&mut self,argument:Option<u64>) -> Result<Vec<PlutusData>> {
+        let mut out = vec![];
+        match argument {
+            Some(n) => for _ in 0..self.length(n)? { out.push(self.data()?) },
+            None => while !self.is_break()? { out.push(self.data()?) }
+        }
+        Ok(out)
+    }
+
+    fn list(&mut self) -> Result<Vec<PlutusData>> {
+        match self.head()? {
+            (4,argument) => self.items(argument),
+            _ => self.error("expected an array")
+        }
+    }
+
+    fn big_integer(&mut self) -> Result<BigInt> {
+        match self.head()? {
+            (2,argument) => Ok(BigInt::from_bytes_be(Sign::Plus,&self.bytes(argument)?)),
+            _ => self.error("expected a byte string")
+        }
+    }
+
+    fn data(&mut self) -> Result<PlutusData> {
+        match self.head()? {
+            (0,Some(n)) => Ok(PlutusData::Integer(n.into())),
+            (1,Some(n)) => Ok(PlutusData::Integer(-1 - BigInt::from(n))),
+            (2,argument) => Ok(PlutusData::Bytes(self.bytes(argument)?)),
+            (4,argument) => Ok(PlutusData::List(self.items(argument)?)),
+            (5,argument) => {
+                let mut out = vec![];
+                match argument {
+                    Some(n) => for _ in 0..self.length(n)? { out.push((self.data()?,self.data()?)) },
+                    None => while !self.is_break()? { out.push((self.data()?,self.data()?)) }
+                }
+                Ok(PlutusData::Map(out))
+            },
+            (6,Some(tag @ 121..=127)) => Ok(PlutusData::Constr(tag - 121,self.list()?)),
+            (6,Some(tag @ 1280..=1400)) => Ok(PlutusData::Constr(tag - 1280 + 7,self.list()?)),
+            (6,Some(102)) => {
+                match self.head()? {
+                    (4,Some(2)) => {},
+                    _ => return self.error("expected an array of two items")
+                }
+                let index = match self.head()? {
+                    (0,Some(n)) => n,
+                    _ => return self.error("expected a constructor index")
+                };
+                Ok(PlutusData::Constr(index,self.list()?))
+            },
+            (6,Some(2)) => Ok(PlutusData::Integer(self.big_integer()?)),
+            (6,Some(3)) => Ok(PlutusData::Integer(-1 - self.big_integer()?)),
+            _ => self.error("unsupported item")
+        }
+    }
+}
+
+impl PlutusData {
+
+    fn write_cbor(&self,out:&mut Vec<u8>) {
+        match self {
+            PlutusData::Constr(index,fields) => {
+                match index {
+                    0..=6 => write_head(6,121 + index,out),
+                    7..=127 => write_head(6,1280 + index - 7,out),
+                    _ => {
+                        write_head(6,102,out);
+                        write_head(4,2,out);
+                        write_head(0,*index,out);
+                    }
+                }
+                write_list(fields,out)
+            },
+            PlutusData::Map(entries) => {
+                write_head(5,entries.len() as u64,out);
+                for (k,v) in entries {
+                    k.write_cbor(out);
+                    v.write_cbor(out);
+                }
+            },
+            PlutusData::List(items) => write_list(items,out),
+            PlutusData::Integer(n) => {
+                let negated : BigInt = -1 - n;
+                match (u64::try_from(n),u64::try_from(&negated)) {
+                    (Ok(n),_) => write_head(0,n,out),
+                    (_,Ok(n)) => write_head(1,n,out),
+                    _ if n.sign() != Sign::Minus => write_big_integer(2,n.magnitude(),out),
+                    _ => write_big_integer(3,negated.magnitude(),out)
+                }
+            },
+            PlutusData::Bytes(bytes) => write_bytes(bytes,out)
+        }
+    }
+
+    /// Encodes the data as CBOR
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.write_cbor(&mut out);
+        out
+    }
+
+    /// Decodes CBOR encoded Plutus Data
+    pub fn from_cbor(bytes:&[u8]) -> Result<PlutusData> {
+        let mut decoder = Decoder { bytes, position: 0 };
+        let data = decoder.data()?;
+        if decoder.position != bytes.len() {
+            return decoder.error("trailing bytes")
+        }
+        Ok(data)
+    }
+}
+
+// ========== Marlowe types ==========
+
+fn constr(index:u64,fields:Vec<PlutusData>) -> PlutusData {
+    PlutusData::Constr(index,fields)
+}
+
+fn utf8(text:&str) -> PlutusData {
+    PlutusData::Bytes(text.as_bytes().to_vec())
+}
+
+fn hex_bytes(text:&str) -> Result<PlutusData> {
+    hex::decode(text).map(PlutusData::Bytes).map_err(|_|PlutusDataError::InvalidHex(text.to_string()))
+}
+
+fn required<T:ToPlutusData>(x:&Slot<T>) -> Result<PlutusData> {
+    match x {
+        Slot::Filled(x) => x.to_plutus_data(),
+        Slot::Hole(name) => Err(PlutusDataError::Hole(name.clone()))
+    }
+}
+
+impl<T:ToPlutusData> ToPlutusData for Box<T> {
+    fn to_plutus_data(&self) -> Result<PlutusData> { self.as_ref().to_plutus_data() }
+}
+
+impl<T:FromPlutusData> FromPlutusData for Box<T> {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> { Ok(Box::new(T::from_plutus_data(data)?)) }
+}
+
+impl ToPlutusData for Party {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        validate_party(self).map_err(PlutusDataError::Validation)?;
+        match self {
+            Party::PK { pk_hash } => Ok(constr(0,vec![hex_bytes(pk_hash)?])),
+            Party::Role { role_token } => Ok(constr(1,vec![utf8(role_token)])),
+            Party::Address { address } => {
+                let address = ShelleyAddress::from_bech32(address)
+                    .map_err(|error|PlutusDataError::Validation(ValidationErrorKind::InvalidAddress { address: address.clone(), error }))?;
+                // the Marlowe validator uses a bool for the network: False is mainnet, True is testnet
+                let network = constr(if address.network == Network::Mainnet { 0 } else { 1 },vec![]);
+                Ok(constr(0,vec![network,plutus_address(&address)]))
+            }
+        }
+    }
+}
+
+fn plutus_credential(credential:&Credential) -> PlutusData {
+    match credential {
+        Credential::Key(hash) => constr(0,vec![PlutusData::Bytes(hash.clone())]),
+        Credential::Script(hash) => constr(1,vec![PlutusData::Bytes(hash.clone())])
+    }
+}
+
+/// The Plutus ledger representation of an address: the payment credential and a Maybe StakingCredential
+fn plutus_address(address:&ShelleyAddress) -> PlutusData {
+    let stake = match &address.stake {
+        StakeReference::None => constr(1,vec![]),
+        StakeReference::Credential(c) => constr(0,vec![constr(0,vec![plutus_credential(c)])]),
+        StakeReference::Pointer { slot, tx_index, cert_index } => constr(0,vec![constr(1,vec![
+            PlutusData::Integer((*slot).into()),
+            PlutusData::Integer((*tx_index).into()),
+            PlutusData::Integer((*cert_index).into())
+        ])])
+    };
+    constr(0,vec![plutus_credential(&address.payment),stake])
+}
+
+fn credential_from_plutus(data:&PlutusData) -> Result<Credential> {
+    match fields(data,"credential",|i| if i <= 1 { Some(1) } else { None })? {
+        (0,[PlutusData::Bytes(hash)]) => Ok(Credential::Key(hash.clone())),
+        (1,[PlutusData::Bytes(hash)]) => Ok(Credential::Script(hash.clone())),
+        _ => unexpected("credential",data)
+    }
+}
+
+fn natural(data:&PlutusData) -> Result<u64> {
+    match data {
+        PlutusData::Integer(n) => u64::try_from(n).or_else(|_|unexpected("natural number",data)),
+        _ => unexpected("integer",data)
+    }
+}
+
+fn address_from_plutus(network:Network,data:&PlutusData) -> Result<ShelleyAddress> {
+    let [payment,stake] = fields(data,"address",|i| if i == 0 { Some(2) } else { None })?.1 else {
+        return unexpected("address",data)
+    };
+    let stake = match fields(stake,"maybe staking credential",|i| [Some(1),Some(0)].get(i as usize).copied().flatten())? {
+        (1,_) => StakeReference::None,
+        (_,[s]) => match fields(s,"staking credential",|i| [Some(1),Some(3)].get(i as usize).copied().flatten())? {
+            (0,[c]) => StakeReference::Credential(credential_from_plutus(c)?),
+            (_,[slot,tx_index,cert_index]) => StakeReference::Pointer {
+                slot: natural(slot)?,
+                tx_index: natural(tx_index)?,
+                cert_index: natural(cert_index)?
+            },
+            _ => return unexpected("staking credential",s)
+        },
+        _ => return unexpected("maybe staking credential",stake)
+    };
+    Ok(ShelleyAddress { network, payment: credential_from_plutus(payment)?, stake })
+}
+
+impl ToPlutusData for Token {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        validate_token(self).map_err(PlutusDataError::Validation)?;
+        match self {
+            Token::ADA => Ok(constr(0,vec![PlutusData::Bytes(vec![]),PlutusData::Bytes(vec![])])),
+            Token::Custom { token_name, currency_symbol } => Ok(constr(0,vec![hex_bytes(currency_symbol)?,utf8(token_name)]))
+        }
+    }
+}
+
+impl ToPlutusData for Payee {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        match self {
+            Payee::Account(p) => Ok(constr(0,vec![required(p)?])),
+            Payee::Party(p) => Ok(constr(1,vec![required(p)?]))
+        }
+    }
+}
+
+impl ToPlutusData for ChoiceId {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(constr(0,vec![utf8(&self.choice_name),required(&self.choice_owner)?]))
+    }
+}
+
+impl ToPlutusData for Bound {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(constr(0,vec![integer_data(&self.0),integer_data(&self.1)]))
+    }
+}
+
+impl ToPlutusData for Value {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        let value = |x:&Slot<Box<Value>>| required(x);
+        Ok(match self {
+            Value::AvailableMoney(p,t) => constr(0,vec![required(p)?,required(t)?]),
+            Value::ConstantValue(n) => constr(1,vec![integer_data(n)]),
+            Value::ConstantParam(name) => return Err(PlutusDataError::UninitializedParam(name.clone())),
+            Value::NegValue(a) => constr(2,vec![value(a)?]),
+            Value::AddValue(a,b) => constr(3,vec![value(a)?,value(b)?]),
+            Value::SubValue(a,b) => constr(4,vec![value(a)?,value(b)?]),
+            Value::MulValue(a,b) => constr(5,vec![value(a)?,value(b)?]),
+            Value::DivValue(a,b) => constr(6,vec![value(a)?,value(b)?]),
+            Value::ChoiceValue(c) => constr(7,vec![required(c)?]),
+            Value::TimeIntervalStart => constr(8,vec![]),
+            Value::TimeIntervalEnd => constr(9,vec![]),
+            Value::UseValue(name) => constr(10,vec![utf8(name)]),
+            Value::Cond(o,a,b) => constr(11,vec![required(o)?,value(a)?,value(b)?])
+        })
+    }
+}
+
+impl ToPlutusData for Observation {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        let obs = |x:&Slot<Box<Observation>>| required(x);
+        let value = |x:&Slot<Box<Value>>| required(x);
+        Ok(match self {
+            Observation::AndObs { both, and } => constr(0,vec![obs(both)?,obs(and)?]),
+            Observation::OrObs { either, or } => constr(1,vec![obs(either)?,obs(or)?]),
+            Observation::NotObs { not } => constr(2,vec![obs(not)?]),
+            Observation::ChoseSomething(c) => constr(3,vec![required(c)?]),
+            Observation::ValueGE { value: a, ge_than: b } => constr(4,vec![value(a)?,value(b)?]),
+            Observation::ValueGT { value: a, gt_than: b } => constr(5,vec![value(a)?,value(b)?]),
+            Observation::ValueLT { value: a, lt_than: b } => constr(6,vec![value(a)?,value(b)?]),
+            Observation::ValueLE { value: a, le_than: b } => constr(7,vec![value(a)?,value(b)?]),
+            Observation::ValueEQ { value: a, equal_to: b } => constr(8,vec![value(a)?,value(b)?]),
+            Observation::True => constr(9,vec![]),
+            Observation::False => constr(10,vec![])
+        })
+    }
+}
+
+impl ToPlutusData for Action {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(match self {
+            Action::Deposit { party, of_token, into_account, deposits } => constr(0,vec![
+                required(into_account)?,
+                required(party)?,
+                required(of_token)?,
+                required(deposits)?
+            ]),
+            Action::Choice { for_choice, choose_between } => constr(1,vec![
+                required(for_choice)?,
+                PlutusData::List(choose_between.iter().map(required).collect::<Result<_>>()?)
+            ]),
+            Action::Notify { notify_if } => constr(2,vec![required(notify_if)?])
+        })
+    }
+}
+
+impl ToPlutusData for Case {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(match self {
+            Case::Case { case, then } => constr(0,vec![required(case)?,required(then)?]),
+            Case::MerkleizedCase { case, then } => constr(1,vec![required(case)?,hex_bytes(then)?])
+        })
+    }
+}
+
+impl ToPlutusData for Contract {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        let contract = |x:&Slot<Box<Contract>>| required(x);
+        Ok(match self {
+            Contract::Close => constr(0,vec![]),
+            Contract::Pay { from_account, to, token, pay, then } => constr(1,vec![
+                required(from_account)?,
+                required(to)?,
+                required(token)?,
+                required(pay)?,
+                contract(then)?
+            ]),
+            Contract::If { r#if, then, r#else } => constr(2,vec![required(r#if)?,contract(then)?,contract(r#else)?]),
+            Contract::When { when, timeout_continuation, timeout } => constr(3,vec![
+                PlutusData::List(when.iter().map(required).collect::<Result<_>>()?),
+                match timeout {
+                    Slot::Filled(Timeout::TimeConstant(t)) => integer_data(t),
+                    Slot::Filled(Timeout::TimeParam(name)) => return Err(PlutusDataError::UninitializedParam(name.clone())),
+                    Slot::Hole(name) => return Err(PlutusDataError::Hole(name.clone()))
+                },
+                contract(timeout_continuation)?
+            ]),
+            Contract::Let { r#let, be, then } => constr(4,vec![utf8(r#let),required(be)?,contract(then)?]),
+            Contract::Assert { assert, then } => constr(5,vec![required(assert)?,contract(then)?])
+        })
+    }
+}
+
+fn unexpected<T>(expected:&str,data:&PlutusData) -> Result<T> {
+    Err(PlutusDataError::UnexpectedData { expected: expected.to_string(), found: format!("{data:?}") })
+}
+
+/// The constructor index and fields, if the data is a constructor with the expected number of fields
+fn fields<'a>(data:&'a PlutusData,expected:&str,arity:fn(u64) -> Option<usize>) -> Result<(u64,&'a [PlutusData])> {
+    match data {
+        PlutusData::Constr(index,fields) if arity(*index) == Some(fields.len()) => Ok((*index,fields)),
+        _ => unexpected(expected,data)
+    }
+}
+
+fn text(data:&PlutusData) -> Result<String> {
+    match data {
+        PlutusData::Bytes(b) => String::from_utf8(b.clone()).or_else(|_|unexpected("utf-8 text",data)),
+        _ => unexpected("bytes",data)
+    }
+}
+
+fn hex_text(data:&PlutusData) -> Result<String> {
+    match data {
+        PlutusData::Bytes(b) => Ok(hex::encode(b)),
+        _ => unexpected("bytes",data)
+    }
+}
+
+fn integer(data:&PlutusData) -> Result<BigInt> {
+    match data {
+        PlutusData::Integer(n) => Ok(n.clone()),
+        _ => unexpected("integer",data)
+    }
+}
+
+fn filled<T:FromPlutusData>(data:&PlutusData) -> Result<Slot<T>> {
+    Ok(Slot::Filled(T::from_plutus_data(data)?))
+}
+
+impl FromPlutusData for Party {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        if let PlutusData::Constr(0,f) = data {
+            if let [network,address] = &f[..] {
+                let network = match network {
+                    PlutusData::Constr(0,x) if x.is_empty() => Network::Mainnet,
+                    PlutusData::Constr(1,x) if x.is_empty() => Network::Testnet,
+                    _ => return unexpected("network",network)
+                };
+                return Ok(Party::Address { address: address_from_plutus(network,address)?.to_bech32() })
+            }
+        }
+        match fields(data,"party",|i| if i <= 1 { Some(1) } else { None })? {
+            (0,[pk]) => Ok(Party::PK { pk_hash: hex_text(pk)? }),
+            (_,[role]) => Ok(Party::Role { role_token: text(role)? }),
+            _ => unexpected("party",data)
+        }
+    }
+}
+
+impl FromPlutusData for Token {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"token",|i| if i == 0 { Some(2) } else { None })?.1 {
+            [PlutusData::Bytes(cs),PlutusData::Bytes(tn)] if cs.is_empty() && tn.is_empty() => Ok(Token::ADA),
+            [cs,tn] => Ok(Token::Custom { currency_symbol: hex_text(cs)?, token_name: text(tn)? }),
+            _ => unexpected("token",data)
+        }
+    }
+}
+
+impl FromPlutusData for Payee {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"payee",|i| if i <= 1 { Some(1) } else { None })? {
+            (0,[p]) => Ok(Payee::Account(filled(p)?)),
+            (_,[p]) => Ok(Payee::Party(filled(p)?)),
+            _ => unexpected("payee",data)
+        }
+    }
+}
+
+impl FromPlutusData for ChoiceId {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"choice id",|i| if i == 0 { Some(2) } else { None })?.1 {
+            [name,owner] => Ok(ChoiceId { choice_name: text(name)?, choice_owner: filled(owner)? }),
+            _ => unexpected("choice id",data)
+        }
+    }
+}
+
+impl FromPlutusData for Bound {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"bound",|i| if i == 0 { Some(2) } else { None })?.1 {
+            [a,b] => Ok(Bound(integer(a)?,integer(b)?)),
+            _ => unexpected("bound",data)
+        }
+    }
+}
+
+impl FromPlutusData for Value {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        let arity = |i| match i { 8 | 9 => Some(0), 1 | 2 | 7 | 10 => Some(1), 0 | 3..=6 => Some(2), 11 => Some(3), _ => None };
+        Ok(match fields(data,"value",arity)? {
+            (0,[p,t]) => Value::AvailableMoney(filled(p)?,filled(t)?),
+            (1,[n]) => Value::ConstantValue(integer(n)?),
+            (2,[a]) => Value::NegValue(filled(a)?),
+            (3,[a,b]) => Value::AddValue(filled(a)?,filled(b)?),
+            (4,[a,b]) => Value::SubValue(filled(a)?,filled(b)?),
+            (5,[a,b]) => Value::MulValue(filled(a)?,filled(b)?),
+            (6,[a,b]) => Value::DivValue(filled(a)?,filled(b)?),
+            (7,[c]) => Value::ChoiceValue(filled(c)?),
+            (8,[]) => Value::TimeIntervalStart,
+            (9,[]) => Value::TimeIntervalEnd,
+            (10,[name]) => Value::UseValue(text(name)?),
+            (11,[o,a,b]) => Value::Cond(filled(o)?,filled(a)?,filled(b)?),
+            _ => return unexpected("value",data)
+        })
+    }
+}
+
+impl FromPlutusData for Observation {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        let arity = |i| match i { 9 | 10 => Some(0), 2 | 3 => Some(1), 0 | 1 | 4..=8 => Some(2), _ => None };
+        Ok(match fields(data,"observation",arity)? {
+            (0,[a,b]) => Observation::AndObs { both: filled(a)?, and: filled(b)? },
+            (1,[a,b]) => Observation::OrObs { either: filled(a)?, or: filled(b)? },
+            (2,[a]) => Observation::NotObs { not: filled(a)? },
+            (3,[c]) => Observation::ChoseSomething(filled(c)?),
+            (4,[a,b]) => Observation::ValueGE { value: filled(a)?, ge_than: filled(b)? },
+            (5,[a,b]) => Observation::ValueGT { value: filled(a)?, gt_than: filled(b)? },
+            (6,[a,b]) => Observation::ValueLT { value: filled(a)?, lt_than: filled(b)? },
+            (7,[a,b]) => Observation::ValueLE { value: filled(a)?, le_than: filled(b)? },
+            (8,[a,b]) => Observation::ValueEQ { value: filled(a)?, equal_to: filled(b)? },
+            (9,[]) => Observation::True,
+            (10,[]) => Observation::False,
+            _ => return unexpected("observation",data)
+        })
+    }
+}
+
+impl FromPlutusData for Action {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        let arity = |i| match i { 0 => Some(4), 1 => Some(2), 2 => Some(1), _ => None };
+        Ok(match fields(data,"action",arity)? {
+            (0,[into_account,party,token,value]) => Action::Deposit {
+                into_account: filled(into_account)?, party: filled(party)?, of_token: filled(token)?, deposits: filled(value)?
+            },
+            (1,[choice,PlutusData::List(bounds)]) => Action::Choice {
+                for_choice: filled(choice)?,
+                choose_between: bounds.iter().map(filled).collect::<Result<_>>()?
+            },
+            (2,[o]) => Action::Notify { notify_if: filled(o)? },
+            _ => return unexpected("action",data)
+        })
+    }
+}
+
+impl FromPlutusData for Case {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"case",|i| if i < 2 { Some(2) } else { None })? {
+            (0,[action,then]) => Ok(Case::Case { case: filled(action)?, then: filled(then)? }),
+            (1,[action,then]) => Ok(Case::MerkleizedCase { case: filled(action)?, then: hex_text(then)? }),
+            _ => unexpected("case",data)
+        }
+    }
+}
+
+impl FromPlutusData for Contract {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        let arity = |i| match i { 0 => Some(0), 1 => Some(5), 2..=4 => Some(3), 5 => Some(2), _ => None };
+        Ok(match fields(data,"contract",arity)? {
+            (0,[]) => Contract::Close,
+            (1,[from,to,token,value,then]) => Contract::Pay {
+                from_account: filled(from)?, to: filled(to)?, token: filled(token)?, pay: filled(value)?, then: filled(then)?
+            },
+            (2,[o,a,b]) => Contract::If { r#if: filled(o)?, then: filled(a)?, r#else: filled(b)? },
+            (3,[PlutusData::List(cases),timeout,then]) => Contract::When {
+                when: cases.iter().map(filled).collect::<Result<_>>()?,
+                timeout: Slot::Filled(Timeout::TimeConstant(integer(timeout)?)),
+                timeout_continuation: filled(then)?
+            },
+            (4,[name,value,then]) => Contract::Let { r#let: text(name)?, be: filled(value)?, then: filled(then)? },
+            (5,[o,then]) => Contract::Assert { assert: filled(o)?, then: filled(then)? },
+            _ => return unexpected("contract",data)
+        })
+    }
+}
+
+// ========== Datum ==========
+
+/// Parameters of a running Marlowe contract
+#[derive(Debug,Clone,PartialEq)]
+pub struct MarloweParams {
+    /// Currency symbol (hex) of the role tokens
+    pub roles_currency: String
+}
+
+/// The datum of a Marlowe validator output: the parameters, the current state and the continuation contract
+#[derive(Debug,Clone,PartialEq)]
+pub struct MarloweData {
+    pub params: MarloweParams,
+    pub state: State,
+    pub contract: Contract
+}
+
+fn map<K:ToPlutusData,V>(entries:&BTreeMap<K,V>,value:fn(&V) -> PlutusData) -> Result<PlutusData> {
+    Ok(PlutusData::Map(entries.iter().map(|(k,v)|Ok((k.to_plutus_data()?,value(v)))).collect::<Result<_>>()?))
+}
+
+fn from_map<K:Ord,V>(data:&PlutusData,key:fn(&PlutusData) -> Result<K>,value:fn(&PlutusData) -> Result<V>) -> Result<BTreeMap<K,V>> {
+    match data {
+        PlutusData::Map(entries) => entries.iter().map(|(k,v)|Ok((key(k)?,value(v)?))).collect(),
+        _ => unexpected("map",data)
+    }
+}
+
+/// Accounts are keyed by a tuple of the account owner and the token
+struct AccountKey<'a>(&'a Party,&'a Token);
+
+impl ToPlutusData for AccountKey<'_> {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(constr(0,vec![self.0.to_plutus_data()?,self.1.to_plutus_data()?]))
+    }
+}
+
+fn integer_data(n:&BigInt) -> PlutusData {
+    PlutusData::Integer(n.clone())
+}
+
+impl ToPlutusData for String {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(utf8(self))
+    }
+}
+
+impl ToPlutusData for State {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        let accounts = self.accounts.iter()
+            .map(|((party,token),amount)|Ok((AccountKey(party,token).to_plutus_data()?,integer_data(amount))))
+            .collect::<Result<_>>()?;
+        Ok(constr(0,vec![
+            PlutusData::Map(accounts),
+            map(&self.choices,integer_data)?,
+            map(&self.bound_values,integer_data)?,
+            PlutusData::Integer(self.min_time.into())
+        ]))
+    }
+}
+
+impl FromPlutusData for State {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"state",|i| if i == 0 { Some(4) } else { None })?.1 {
+            [accounts,choices,bound_values,min_time] => Ok(State {
+                accounts: from_map(accounts,|k| match fields(k,"account id",|i| if i == 0 { Some(2) } else { None })?.1 {
+                    [party,token] => Ok((Party::from_plutus_data(party)?,Token::from_plutus_data(token)?)),
+                    _ => unexpected("account id",k)
+                },integer)?,
+                choices: from_map(choices,ChoiceId::from_plutus_data,integer)?,
+                bound_values: from_map(bound_values,text,integer)?,
+                min_time: match min_time {
+                    PlutusData::Integer(n) => i64::try_from(n).or_else(|_|unexpected("a 64 bit integer",min_time))?,
+                    _ => unexpected("integer",min_time)?
+                }
+            }),
+            _ => unexpected("state",data)
+        }
+    }
+}
+
+impl ToPlutusData for MarloweParams {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(constr(0,vec![hex_bytes(&self.roles_currency)?]))
+    }
+}
+
+impl FromPlutusData for MarloweParams {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"marlowe params",|i| if i == 0 { Some(1) } else { None })?.1 {
+            [roles_currency] => Ok(MarloweParams { roles_currency: hex_text(roles_currency)? }),
+            _ => unexpected("marlowe params",data)
+        }
+    }
+}
+
+impl ToPlutusData for MarloweData {
+    fn to_plutus_data(&self) -> Result<PlutusData> {
+        Ok(constr(0,vec![self.params.to_plutus_data()?,self.state.to_plutus_data()?,self.contract.to_plutus_data()?]))
+    }
+}
+
+impl FromPlutusData for MarloweData {
+    fn from_plutus_data(data:&PlutusData) -> Result<Self> {
+        match fields(data,"marlowe data",|i| if i == 0 { Some(3) } else { None })?.1 {
+            [params,state,contract] => Ok(MarloweData {
+                params: MarloweParams::from_plutus_data(params)?,
+                state: State::from_plutus_data(state)?,
+                contract: Contract::from_plutus_data(contract)?
+            }),
+            _ => unexpected("marlowe data",data)
+        }
+    }
+}
+
+impl MarloweData {
+    /// Decodes a datum from its CBOR hex representation, as shown by chain explorers
+    pub fn from_cbor_hex(text:&str) -> Result<MarloweData> {
+        from_cbor_hex(text)
+    }
+}
@@ -0,0 +1,486 @@
+// The semantics implemented in here are based on this file:
+// https://github.com/input-output-hk/marlowe-cardano/blob/00c54fcf65ce18f82c580224f82f56b38e84bb68/marlowe/src/Language/Marlowe/Core/V1/Semantics.hs
+
+// note: the reference semantics operate on fully initialized contracts only.
+//       holes and uninitialized parameters are reported as errors rather than being guessed at.
+
+use std::collections::BTreeMap;
+
+use num_traits::{Signed, Zero};
+
+use crate::types::marlowe::*;
+use crate::merkleization::contract_hash;
+
+/// Information about the transaction that is being evaluated
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Environment {
+    pub time_interval: TimeInterval
+}
+
+/// Balances of each account, per token
+pub type Accounts = BTreeMap<(Party,Token),BigInt>;
+
+/// The on-chain state of a running contract
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct State {
+    pub accounts: Accounts,
+    pub choices: BTreeMap<ChoiceId,BigInt>,
+    pub bound_values: BTreeMap<String,BigInt>,
+    pub min_time: i64
+}
+
+impl State {
+    /// Creates a state without any accounts, choices or bound values
+    pub fn empty_state(min_time:i64) -> State {
+        State { min_time, ..Default::default() }
+    }
+}
+
+/// Money leaving an account, either to a party or to another account
+#[derive(Debug,Clone,PartialEq)]
+pub struct Payment {
+    pub from_account: Party,
+    pub to: Payee,
+    pub token: Token,
+    pub amount: BigInt
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum IntervalError {
+    InvalidInterval(TimeInterval),
+    IntervalInPast { min_time: i64, interval: TimeInterval }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum IntervalResult {
+    IntervalTrapped(Environment,State),
+    IntervalError(IntervalError)
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum ReduceWarning {
+    NoWarning,
+    NonPositivePay { account: Party, payee: Payee, token: Token, amount: BigInt },
+    PartialPay { account: Party, payee: Payee, token: Token, paid: BigInt, expected: BigInt },
+    Shadowing { value_id: String, old_value: BigInt, new_value: BigInt },
+    AssertionFailed
+}
+
+/// The outcome of a step that reduced the contract
+#[derive(Debug,Clone,PartialEq)]
+pub struct ReducedStep {
+    pub warning: ReduceWarning,
+    pub payment: Option<Payment>,
+    pub state: State,
+    pub contract: Contract
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum ReduceStepResult {
+    Reduced(Box<ReducedStep>),
+    NotReduced,
+    AmbiguousTimeIntervalReductionError
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum ReduceResult {
+    ContractQuiescent { reduced: bool, warnings: Vec<ReduceWarning>, payments: Vec<Payment>, state: Box<State>, contract: Box<Contract> },
+    AmbiguousTimeIntervalError
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum ApplyWarning {
+    NoWarning,
+    NonPositiveDeposit { party: Party, account: Party, token: Token, amount: BigInt }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum ApplyResult {
+    Applied { warning: ApplyWarning, state: Box<State>, contract: Box<Contract> },
+    ApplyNoMatchError,
+    ApplyHashMismatch
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum TransactionWarning {
+    NonPositiveDeposit { party: Party, account: Party, token: Token, amount: BigInt },
+    NonPositivePay { account: Party, payee: Payee, token: Token, amount: BigInt },
+    PartialPay { account: Party, payee: Payee, token: Token, paid: BigInt, expected: BigInt },
+    Shadowing { value_id: String, old_value: BigInt, new_value: BigInt },
+    AssertionFailed
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum TransactionError {
+    AmbiguousTimeIntervalError,
+    ApplyNoMatchError,
+    HashMismatch,
+    IntervalError(IntervalError),
+    UselessTransaction,
+    /// The contract contains holes or uninitialized parameters and can not be evaluated
+    InvalidContract(String)
+}
+
+/// The result of successfully applying a transaction to a contract
+#[derive(Debug,Clone,PartialEq)]
+pub struct TransactionOutput {
+    pub warnings: Vec<TransactionWarning>,
+    pub payments: Vec<Payment>,
+    pub state: State,
+    pub contract: Contract
+}
+
+fn required<'a,T>(x:&'a Slot<T>,what:&str) -> Result<&'a T,String> {
+    match x {
+        Slot::Filled(v) => Ok(v),
+        Slot::Hole(name) => Err(format!("The contract contains the hole '?{name}' where a {what} was expected."))
+    }
+}
+
+/// Checks that the interval is valid and not in the past, moving its start up to the min time of the state
+pub fn fix_interval(interval:TimeInterval,state:&State) -> IntervalResult {
+    if interval.end < interval.start {
+        IntervalResult::IntervalError(IntervalError::InvalidInterval(interval))
+    } else if interval.end < state.min_time {
+        IntervalResult::IntervalError(IntervalError::IntervalInPast { min_time: state.min_time, interval })
+    } else {
+        let start = std::cmp::max(interval.start,state.min_time);
+        let env = Environment { time_interval: TimeInterval { start, end: interval.end } };
+        IntervalResult::IntervalTrapped(env,State { min_time: start, ..state.clone() })
+    }
+}
+
+/// Evaluates a value in the given environment and state
+pub fn eval_value(env:&Environment,state:&State,value:&Value) -> Result<BigInt,String> {
+    let eval = |v:&Slot<Box<Value>>| eval_value(env,state,required(v,"value")?);
+    let or_zero = |x:Option<&BigInt>| x.cloned().unwrap_or_default();
+    match value {
+        Value::AvailableMoney(account,token) => {
+            let key = (required(account,"party")?.clone(),required(token,"token")?.clone());
+            Ok(or_zero(state.accounts.get(&key)))
+        },
+        Value::ConstantValue(n) => Ok(n.clone()),
+        Value::ConstantParam(name) => Err(format!("Constant param not initialized: '{name}'")),
+        Value::NegValue(a) => Ok(-eval(a)?),
+        Value::AddValue(a,b) => Ok(eval(a)? + eval(b)?),
+        Value::SubValue(a,b) => Ok(eval(a)? - eval(b)?),
+        Value::MulValue(a,b) => Ok(eval(a)? * eval(b)?),
+        Value::DivValue(a,b) => {
+            // rounds towards zero, like quot in the Haskell implementation
+            let (n,d) = (eval(a)?,eval(b)?);
+            if d.is_zero() { Ok(BigInt::zero()) } else { Ok(n / d) }
+        },
+        Value::ChoiceValue(choice_id) =>
+            Ok(or_zero(state.choices.get(required(choice_id,"choice id")?))),
+        Value::TimeIntervalStart => Ok(env.time_interval.start.into()),
+        Value::TimeIntervalEnd => Ok(env.time_interval.end.into()),
+        Value::UseValue(name) => Ok(or_zero(state.bound_values.get(name))),
+        Value::Cond(obs,a,b) => {
+            if eval_observation(env,state,required(obs,"observation")?)? { eval(a) } else { eval(b) }
+        }
+    }
+}
+
+/// Evaluates an observation in the given environment and state
+pub fn eval_observation(env:&Environment,state:&State,observation:&Observation) -> Result<bool,String> {
+    let obs = |o:&Slot<Box<Observation>>| eval_observation(env,state,required(o,"observation")?);
+    let val = |v:&Slot<Box<Value>>| eval_value(env,state,required(v,"value")?);
+    match observation {
+        Observation::AndObs { both, and } => Ok(obs(both)? && obs(and)?),
+        Observation::OrObs { either, or } => Ok(obs(either)? || obs(or)?),
+        Observation::NotObs { not } => Ok(!obs(not)?),
+        Observation::ChoseSomething(choice_id) =>
+            Ok(state.choices.contains_key(required(choice_id,"choice id")?)),
+        Observation::ValueGE { value, ge_than } => Ok(val(value)? >= val(ge_than)?),
+        Observation::ValueGT { value, gt_than } => Ok(val(value)? > val(gt_than)?),
+        Observation::ValueLT { value, lt_than } => Ok(val(value)? < val(lt_than)?),
+        Observation::ValueLE { value, le_than } => Ok(val(value)? <= val(le_than)?),
+        Observation::ValueEQ { value, equal_to } => Ok(val(value)? == val(equal_to)?),
+        Observation::True => Ok(true),
+        Observation::False => Ok(false)
+    }
+}
+
+/// Pays out the first account with a positive balance, dropping any empty accounts on the way
+fn refund_one(accounts:&Accounts) -> Option<((Party,Token,BigInt),Accounts)> {
+    let mut remaining = accounts.clone();
+    while let Some(((party,token),balance)) = remaining.pop_first() {
+        if balance.is_positive() {
+            return Some(((party,token,balance),remaining))
+        }
+    }
+    None
+}
+
+fn add_money_to_account(accounts:&mut Accounts,account:&Party,token:&Token,amount:&BigInt) {
+    if !amount.is_positive() { return }
+    *accounts.entry((account.clone(),token.clone())).or_default() += amount;
+}
+
+fn update_money_in_account(accounts:&mut Accounts,account:&Party,token:&Token,amount:BigInt) {
+    let key = (account.clone(),token.clone());
+    if !amount.is_positive() {
+        accounts.remove(&key);
+    } else {
+        accounts.insert(key,amount);
+    }
+}
+
+/// Carries out a single step of the contract, if possible
+pub fn reduce_contract_step(env:&Environment,state:&State,contract:&Contract) -> Result<ReduceStepResult,String> {
+    let continuation = |c:&Slot<Box<Contract>>| required(c,"contract").map(|c|(**c).clone());
+    let reduced = |warning,state,contract| ReduceStepResult::Reduced(Box::new(ReducedStep { warning, payment: None, state, contract }));
+    match contract {
+        Contract::Close => match refund_one(&state.accounts) {
+            Some(((party,token,amount),accounts)) => Ok(ReduceStepResult::Reduced(Box::new(ReducedStep {
+                warning: ReduceWarning::NoWarning,
+                payment: Some(Payment { from_account: party.clone(), to: Payee::Party(Slot::Filled(party)), token, amount }),
+                state: State { accounts, ..state.clone() },
+                contract: Contract::Close
+            }))),
+            None => Ok(ReduceStepResult::NotReduced)
+        },
+        Contract::Pay { from_account, to, token, pay, then } => {
+            let account = required(from_account,"party")?;
+            let payee = required(to,"payee")?;
+            let token = required(token,"token")?;
+            let money_to_pay = eval_value(env,state,required(pay,"value")?)?;
+            let then = continuation(then)?;
+            if !money_to_pay.is_positive() {
+                let warning = ReduceWarning::NonPositivePay {
+                    account: account.clone(), payee: payee.clone(), token: token.clone(), amount: money_to_pay };
+                return Ok(reduced(warning,state.clone(),then))
+            }
+            let balance = state.accounts.get(&(account.clone(),token.clone())).cloned().unwrap_or_default();
+            let paid_money = std::cmp::min(&balance,&money_to_pay).clone();
+            let mut new_state = state.clone();
+            update_money_in_account(&mut new_state.accounts,account,token,&balance - &paid_money);
+            let warning = if paid_money < money_to_pay {
+                ReduceWarning::PartialPay {
+                    account: account.clone(), payee: payee.clone(), token: token.clone(), paid: paid_money.clone(), expected: money_to_pay }
+            } else {
+                ReduceWarning::NoWarning
+            };
+            if let Payee::Account(to_account) = payee {
+                add_money_to_account(&mut new_state.accounts,required(to_account,"party")?,token,&paid_money);
+            }
+            Ok(ReduceStepResult::Reduced(Box::new(ReducedStep {
+                warning,
+                payment: Some(Payment { from_account: account.clone(), to: payee.clone(), token: token.clone(), amount: paid_money }),
+                state: new_state,
+                contract: then
+            })))
+        },
+        Contract::If { r#if, then, r#else } => {
+            let cont = if eval_observation(env,state,required(r#if,"observation")?)? { then } else { r#else };
+            Ok(reduced(ReduceWarning::NoWarning,state.clone(),continuation(cont)?))
+        },
+        Contract::When { timeout, timeout_continuation, .. } => {
+            let timeout = match required(timeout,"timeout")? {
+                Timeout::TimeConstant(t) => t,
+                Timeout::TimeParam(name) => return Err(format!("TimeParam not initialized: '{name}'."))
+            };
+            if BigInt::from(env.time_interval.end) < *timeout {
+                Ok(ReduceStepResult::NotReduced)
+            } else if *timeout <= BigInt::from(env.time_interval.start) {
+                Ok(reduced(ReduceWarning::NoWarning,state.clone(),continuation(timeout_continuation)?))
+            } else {
+                Ok(ReduceStepResult::AmbiguousTimeIntervalReductionError)
+            }
+        },
+        Contract::Let { r#let, be, then } => {
+            let evaluated_value = eval_value(env,state,required(be,"value")?)?;
+            let warning = match state.bound_values.get(r#let) {
+                Some(old_value) => ReduceWarning::Shadowing {
+                    value_id: r#let.clone(), old_value: old_value.clone(), new_value: evaluated_value.clone() },
+                None => ReduceWarning::NoWarning
+            };
+            let mut new_state = state.clone();
+            new_state.bound_values.insert(r#let.clone(),evaluated_value);
+            Ok(reduced(warning,new_state,continuation(then)?))
+        },
+        Contract::Assert { assert, then } => {
+            let warning = if eval_observation(env,state,required(assert,"observation")?)? {
+                ReduceWarning::NoWarning
+            } else {
+                ReduceWarning::AssertionFailed
+            };
+            Ok(reduced(warning,state.clone(),continuation(then)?))
+        }
+    }
+}
+
+/// Reduces the contract until it can not be reduced any further without inputs
+pub fn reduce_contract_until_quiescent(env:&Environment,state:&State,contract:&Contract) -> Result<ReduceResult,String> {
+    let mut reduced = false;
+    let mut warnings = vec![];
+    let mut payments = vec![];
+    let mut state = state.clone();
+    let mut contract = contract.clone();
+    loop {
+        match reduce_contract_step(env,&state,&contract)? {
+            ReduceStepResult::Reduced(step) => {
+                let ReducedStep { warning, payment, state: new_state, contract: new_contract } = *step;
+                if warning != ReduceWarning::NoWarning {
+                    warnings.push(warning)
+                }
+                if let Some(p) = payment {
+                    payments.push(p)
+                }
+                reduced = true;
+                state = new_state;
+                contract = new_contract;
+            },
+            ReduceStepResult::NotReduced => return Ok(ReduceResult::ContractQuiescent {
+                reduced, warnings, payments, state: Box::new(state), contract: Box::new(contract)
+            }),
+            ReduceStepResult::AmbiguousTimeIntervalReductionError =>
+                return Ok(ReduceResult::AmbiguousTimeIntervalError)
+        }
+    }
+}
+
+fn in_bounds(num:&BigInt,bounds:&[Slot<Bound>]) -> Result<bool,String> {
+    for bound in bounds {
+        let Bound(from,to) = required(bound,"bound")?;
+        if num >= from && num <= to {
+            return Ok(true)
+        }
+    }
+    Ok(false)
+}
+
+fn apply_action(env:&Environment,state:&State,input:&InputContent,action:&Action) -> Result<Option<(ApplyWarning,State)>,String> {
+    match (input,action) {
+        (
+            InputContent::IDeposit { into_account, input_from_party, of_token: of_tokens, that_deposits },
+            Action::Deposit { into_account: account, party, of_token, deposits }
+        ) => {
+            let account = required(account,"party")?;
+            let party = required(party,"party")?;
+            let token = required(of_token,"token")?;
+            let amount = eval_value(env,state,required(deposits,"value")?)?;
+            if into_account != account || input_from_party != party || of_tokens != token || *that_deposits != amount {
+                return Ok(None)
+            }
+            let warning = if that_deposits.is_positive() {
+                ApplyWarning::NoWarning
+            } else {
+                ApplyWarning::NonPositiveDeposit { party: party.clone(), account: account.clone(), token: token.clone(), amount: that_deposits.clone() }
+            };
+            let mut new_state = state.clone();
+            add_money_to_account(&mut new_state.accounts,account,token,that_deposits);
+            Ok(Some((warning,new_state)))
+        },
+        (
+            InputContent::IChoice { for_choice_id, input_that_chooses_num },
+            Action::Choice { for_choice, choose_between }
+        ) => {
+            if for_choice_id != required(for_choice,"choice id")? || !in_bounds(input_that_chooses_num,choose_between)? {
+                return Ok(None)
+            }
+            let mut new_state = state.clone();
+            new_state.choices.insert(for_choice_id.clone(),input_that_chooses_num.clone());
+            Ok(Some((ApplyWarning::NoWarning,new_state)))
+        },
+        (InputContent::INotify,Action::Notify { notify_if }) => {
+            if eval_observation(env,state,required(notify_if,"observation")?)? {
+                Ok(Some((ApplyWarning::NoWarning,state.clone())))
+            } else {
+                Ok(None)
+            }
+        },
+        _ => Ok(None)
+    }
+}
+
+/// Applies a single input to a quiescent contract
+pub fn apply_input(env:&Environment,state:&State,input:&Input,contract:&Contract) -> Result<ApplyResult,String> {
+    match contract {
+        Contract::When { when, .. } => {
+            for case in when {
+                let case = required(case,"case")?;
+                let (Case::Case { case: action, .. } | Case::MerkleizedCase { case: action, .. }) = case;
+                if let Some((warning,new_state)) = apply_action(env,state,input.content(),required(action,"action")?)? {
+                    let continuation = match (input,case) {
+                        (Input::NormalInput(_),Case::Case { then, .. }) => required(then,"contract")?.clone(),
+                        (Input::MerkleizedInput(_,hash,continuation),Case::MerkleizedCase { then, .. })
+                            if hash == then && contract_hash(continuation).map_err(|e|e.to_string())? == *hash => continuation.clone(),
+                        _ => return Ok(ApplyResult::ApplyHashMismatch)
+                    };
+                    return Ok(ApplyResult::Applied { warning, state: Box::new(new_state), contract: continuation })
+                }
+            }
+            Ok(ApplyResult::ApplyNoMatchError)
+        },
+        _ => Ok(ApplyResult::ApplyNoMatchError)
+    }
+}
+
+fn convert_reduce_warning(warning:ReduceWarning) -> Option<TransactionWarning> {
+    match warning {
+        ReduceWarning::NoWarning => None,
+        ReduceWarning::NonPositivePay { account, payee, token, amount } =>
+            Some(TransactionWarning::NonPositivePay { account, payee, token, amount }),
+        ReduceWarning::PartialPay { account, payee, token, paid, expected } =>
+            Some(TransactionWarning::PartialPay { account, payee, token, paid, expected }),
+        ReduceWarning::Shadowing { value_id, old_value, new_value } =>
+            Some(TransactionWarning::Shadowing { value_id, old_value, new_value }),
+        ReduceWarning::AssertionFailed => Some(TransactionWarning::AssertionFailed)
+    }
+}
+
+fn convert_apply_warning(warning:ApplyWarning) -> Option<TransactionWarning> {
+    match warning {
+        ApplyWarning::NoWarning => None,
+        ApplyWarning::NonPositiveDeposit { party, account, token, amount } =>
+            Some(TransactionWarning::NonPositiveDeposit { party, account, token, amount })
+    }
+}
+
+fn apply_all_inputs(env:&Environment,state:&State,contract:&Contract,inputs:&[Input]) -> Result<(bool,TransactionOutput),TransactionError> {
+    let mut contract_changed = false;
+    let mut warnings = vec![];
+    let mut payments = vec![];
+    let mut state = state.clone();
+    let mut contract = contract.clone();
+    let mut inputs = inputs.iter();
+    loop {
+        match reduce_contract_until_quiescent(env,&state,&contract).map_err(TransactionError::InvalidContract)? {
+            ReduceResult::AmbiguousTimeIntervalError => return Err(TransactionError::AmbiguousTimeIntervalError),
+            ReduceResult::ContractQuiescent { reduced, warnings: reduce_warnings, payments: reduce_payments, state: cur_state, contract: cont } => {
+                warnings.extend(reduce_warnings.into_iter().filter_map(convert_reduce_warning));
+                payments.extend(reduce_payments);
+                match inputs.next() {
+                    None => return Ok((contract_changed || reduced,TransactionOutput { warnings, payments, state: *cur_state, contract: *cont })),
+                    Some(input) => match apply_input(env,&cur_state,input,&cont).map_err(TransactionError::InvalidContract)? {
+                        ApplyResult::Applied { warning, state: new_state, contract: new_contract } => {
+                            warnings.extend(convert_apply_warning(warning));
+                            contract_changed = true;
+                            state = *new_state;
+                            contract = *new_contract;
+                        },
+                        ApplyResult::ApplyNoMatchError => return Err(TransactionError::ApplyNoMatchError),
+                        ApplyResult::ApplyHashMismatch => return Err(TransactionError::HashMismatch)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applies a transaction to a contract, returning the payments, warnings,
+/// new state and continuation contract.
+pub fn compute_transaction(tx:&TransactionInput,state:&State,contract:&Contract) -> Result<TransactionOutput,TransactionError> {
+    match fix_interval(tx.interval,state) {
+        IntervalResult::IntervalError(e) => Err(TransactionError::IntervalError(e)),
+        IntervalResult::IntervalTrapped(env,fixed_state) => {
+            let (reduced,output) = apply_all_inputs(&env,&fixed_state,contract,&tx.inputs)?;
+            if !reduced && (*contract != Contract::Close || state.accounts.is_empty()) {
+                Err(TransactionError::UselessTransaction)
+            } else {
+                Ok(output)
+            }
+        }
+    }
+}
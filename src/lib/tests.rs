@@ -37,14 +37,14 @@ fn deserialize_wrapped_simple_observation_should_fail() {
 fn serialize_and_print() {
     let my_contract = Contract::When {
         when: vec![
-            Some(Case { 
-                case: Some(Action::Notify { 
-                    notify_if: Some(Observation::True) 
+            Slot::Filled(Case::Case {
+                case: Slot::Filled(Action::Notify { 
+                    notify_if: Slot::Filled(Observation::True) 
                 }), 
-                then: Some(Contract::Close.boxed()) }
+                then: Slot::Filled(Contract::Close.boxed()) }
             )],
-        timeout: Some(Timeout::TimeParam("test".into())),
-        timeout_continuation: Some(Contract::Close.boxed()),
+        timeout: Slot::Filled(Timeout::TimeParam("test".into())),
+        timeout_continuation: Slot::Filled(Contract::Close.boxed()),
     };
 
     let serialized = serialize(my_contract);
@@ -59,26 +59,26 @@ fn serialize_and_print() {
 fn can_generate_contract() {
     Contract::When {
         when: vec![
-            Some(Case {
-                case: Some(Action::Notify { 
-                    notify_if: Some(Observation::True)
+            Slot::Filled(Case::Case {
+                case: Slot::Filled(Action::Notify { 
+                    notify_if: Slot::Filled(Observation::True)
                 }),
-                then: Some(Contract::Pay { 
-                    from_account: Some(Party::Role { role_token: "test".to_string() }), 
-                    to: Some(Payee::Account(Some(Party::PK { pk_hash : "00000000000000000000".into() }))), 
-                    token: Some(Token::ADA), 
-                    pay: Some(Value::ConstantValue(42)), 
-                    then: Some(Contract::Close.boxed())
+                then: Slot::Filled(Contract::Pay { 
+                    from_account: Slot::Filled(Party::Role { role_token: "test".to_string() }), 
+                    to: Slot::Filled(Payee::Account(Slot::Filled(Party::PK { pk_hash : "00000000000000000000".into() }))), 
+                    token: Slot::Filled(Token::ADA), 
+                    pay: Slot::Filled(Value::ConstantValue(42.into())), 
+                    then: Slot::Filled(Contract::Close.boxed())
                 }.boxed())
             }),
-            Some(Case { 
-                case: Some(Action::Notify { 
-                    notify_if: Some(Observation::True) 
+            Slot::Filled(Case::Case {
+                case: Slot::Filled(Action::Notify { 
+                    notify_if: Slot::Filled(Observation::True) 
                 }), 
-                then: Some(Contract::Close.boxed()) })
+                then: Slot::Filled(Contract::Close.boxed()) })
         ],
-        timeout: Some(Timeout::TimeParam("test".to_owned())),
-        timeout_continuation: Some(Contract::Close.boxed()),
+        timeout: Slot::Filled(Timeout::TimeParam("test".to_owned())),
+        timeout_continuation: Slot::Filled(Contract::Close.boxed()),
     };
 }
 
@@ -198,19 +198,19 @@ fn modify(contract:Contract) -> Contract {
         Contract::If { r#if: observation, then: then_contract, r#else: else_contract } => 
             Contract::If { 
                 r#if: observation, 
-                then: Some(modify(*then_contract.unwrap()).boxed()), 
-                r#else: Some(modify(*else_contract.unwrap()).boxed())
+                then: Slot::Filled(modify(*then_contract.unwrap()).boxed()), 
+                r#else: Slot::Filled(modify(*else_contract.unwrap()).boxed())
             },
         Contract::Assert { assert, then } => 
             Contract::Assert { 
                 assert, 
-                then: Some(modify(*then.unwrap()).boxed()) 
+                then: Slot::Filled(modify(*then.unwrap()).boxed()) 
             },
         Contract::Let { r#let: let_x, be: be_value, then:continue_as } => 
             Contract::Let { 
                 r#let: let_x, 
                 be: be_value, 
-                then: Some(Box::new(modify(*continue_as.unwrap())))  
+                then: Slot::Filled(Box::new(modify(*continue_as.unwrap())))  
             },
         Contract::Pay { from_account: party, to: payee, token: currency, pay: amount, then: continue_as } => 
             Contract::Pay {
@@ -218,7 +218,7 @@ fn modify(contract:Contract) -> Contract {
                 to: payee,
                 token: currency,
                 pay: amount,
-                then: Some(modify(*continue_as.unwrap()).boxed()) 
+                then: Slot::Filled(modify(*continue_as.unwrap()).boxed()) 
             },
     }
 }
@@ -316,9 +316,9 @@ fn json_core_should_return_output_identical_to_playground() {
     }
 
     let mut input = HashMap::new();
-    input.insert("TEST_PARAMETER_ONE".to_string(),666);
-    input.insert("TEST_PARAMETER_TWO".to_string(),4242);
-    input.insert("TEST_PARAMETER_THREE".to_string(),1658504132546);
+    input.insert("TEST_PARAMETER_ONE".to_string(),666.into());
+    input.insert("TEST_PARAMETER_TWO".to_string(),4242.into());
+    input.insert("TEST_PARAMETER_THREE".to_string(),1658504132546i64.into());
     let deserialized = deserialize_with_input(&serialized_contract,input).unwrap();
 
     match parsing::serialization::json::serialize(deserialized) {
@@ -347,4 +347,939 @@ fn json_core_should_return_output_identical_to_playground() {
         },
     }
     
-}
\ No newline at end of file
+}
+#[test]
+fn semantics_deposit_then_close_refunds_the_account() {
+    use crate::semantics::*;
+    let contract = deserialize("When [ (Case (Deposit (Role \"Seller\") (Role \"Buyer\") (Token \"abc\" \"coin\") (Constant 100)) Close) ] 1000 Close").unwrap();
+    let coin = Token::Custom { currency_symbol: "abc".into(), token_name: "coin".into() };
+    let seller = Party::Role { role_token: "Seller".into() };
+    let buyer = Party::Role { role_token: "Buyer".into() };
+    let tx = TransactionInput {
+        interval: TimeInterval { start: 10, end: 20 },
+        inputs: vec![Input::NormalInput(InputContent::IDeposit { into_account: seller.clone(), input_from_party: buyer, of_token: coin.clone(), that_deposits: 100.into() })]
+    };
+    let output = compute_transaction(&tx,&State::empty_state(0),&contract).unwrap();
+    assert_eq!(output.contract,Contract::Close);
+    assert!(output.warnings.is_empty());
+    assert!(output.state.accounts.is_empty());
+    assert_eq!(output.payments,vec![Payment { from_account: seller.clone(), to: Payee::Party(Slot::Filled(seller)), token: coin, amount: 100.into() }]);
+}
+
+#[test]
+fn semantics_close_refunds_accounts_in_the_order_of_the_reference_semantics() {
+    use crate::semantics::*;
+    let role = Party::Role { role_token: "A".into() };
+    let pk = Party::PK { pk_hash: "b".into() };
+    let address = Party::Address { address: "addr_test1vrssw4edjdvd2".into() };
+    let coin = Token::Custom { currency_symbol: "abc".into(), token_name: "coin".into() };
+    let other = Token::Custom { currency_symbol: "abd".into(), token_name: "a".into() };
+    let mut state = State::empty_state(0);
+    for party in [&role,&address,&pk] {
+        for token in [&other,&coin,&Token::ADA] {
+            state.accounts.insert((party.clone(),token.clone()),1.into());
+        }
+    }
+    let tx = TransactionInput { interval: TimeInterval { start: 10, end: 20 }, inputs: vec![] };
+    let output = compute_transaction(&tx,&state,&Contract::Close).unwrap();
+    let refunded : Vec<(Party,Token)> = output.payments.into_iter().map(|p|(p.from_account,p.token)).collect();
+    let mut expected = vec![];
+    for party in [&pk,&address,&role] {
+        for token in [&Token::ADA,&coin,&other] {
+            expected.push((party.clone(),token.clone()));
+        }
+    }
+    assert_eq!(refunded,expected);
+}
+
+#[test]
+fn semantics_can_apply_merkleized_inputs() {
+    use crate::semantics::*;
+    let contract = deserialize("When [ (Case (Deposit (Role \"A\") (Role \"A\") (Token \"\" \"\") (Constant 10)) (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (Constant 10) Close)) ] 1000 Close").unwrap();
+    let (merkleized,store) = crate::merkleization::merkleize(&contract,None).unwrap();
+    let (hash,continuation) = store.iter().next().unwrap();
+    let a = Party::Role { role_token: "A".into() };
+    let deposit = InputContent::IDeposit { into_account: a.clone(), input_from_party: a, of_token: Token::ADA, that_deposits: 10.into() };
+    let tx = |input| TransactionInput { interval: TimeInterval { start: 10, end: 20 }, inputs: vec![input] };
+
+    let output = compute_transaction(&tx(Input::MerkleizedInput(deposit.clone(),hash.clone(),continuation.clone().boxed())),&State::empty_state(0),&merkleized).unwrap();
+    assert_eq!(output.contract,Contract::Close);
+    assert_eq!(output.payments.len(),1);
+    assert_eq!(output.payments[0].amount,10.into());
+
+    // a continuation that does not match its hash, or an input of the wrong kind, is refused
+    let wrong = Input::MerkleizedInput(deposit.clone(),hash.clone(),Contract::Close.boxed());
+    assert_eq!(compute_transaction(&tx(wrong),&State::empty_state(0),&merkleized),Err(TransactionError::HashMismatch));
+    let normal = Input::NormalInput(deposit.clone());
+    assert_eq!(compute_transaction(&tx(normal),&State::empty_state(0),&merkleized),Err(TransactionError::HashMismatch));
+    let merkleized_input = Input::MerkleizedInput(deposit,hash.clone(),continuation.clone().boxed());
+    assert_eq!(compute_transaction(&tx(merkleized_input),&State::empty_state(0),&contract),Err(TransactionError::HashMismatch));
+}
+
+#[test]
+fn semantics_should_reduce_timed_out_when_and_report_partial_pay() {
+    use crate::semantics::*;
+    let contract = deserialize("When [ ] 1000 (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (Constant 10) Close)").unwrap();
+    let tx = TransactionInput { interval: TimeInterval { start: 1000, end: 2000 }, inputs: vec![] };
+    let output = compute_transaction(&tx,&State::empty_state(0),&contract).unwrap();
+    assert_eq!(output.contract,Contract::Close);
+    assert!(matches!(&output.warnings[..],[TransactionWarning::PartialPay { paid, expected, .. }] if *paid == 0.into() && *expected == 10.into()));
+
+    let ambiguous = TransactionInput { interval: TimeInterval { start: 500, end: 2000 }, inputs: vec![] };
+    assert_eq!(compute_transaction(&ambiguous,&State::empty_state(0),&contract),Err(TransactionError::AmbiguousTimeIntervalError));
+}
+
+#[test]
+fn semantics_should_refuse_contracts_with_uninitialized_params() {
+    use crate::semantics::*;
+    let contract = deserialize("When [ ] (TimeParam \"deadline\") Close").unwrap();
+    let tx = TransactionInput { interval: TimeInterval { start: 0, end: 10 }, inputs: vec![] };
+    match compute_transaction(&tx,&State::empty_state(0),&contract) {
+        Err(TransactionError::InvalidContract(_)) => {},
+        other => panic!("Expected an invalid contract error, got: {other:?}")
+    }
+}
+
+#[test]
+fn transaction_input_json_round_trip() {
+    let json = r#"{
+        "tx_interval": { "from": 1000, "to": 2000 },
+        "tx_inputs": [
+            {
+                "input_from_party": { "role_token": "Buyer" },
+                "that_deposits": 100,
+                "of_token": { "currency_symbol": "", "token_name": "" },
+                "into_account": { "role_token": "Seller" }
+            },
+            {
+                "for_choice_id": { "choice_name": "Everything is alright", "choice_owner": { "role_token": "Buyer" } },
+                "input_that_chooses_num": 0
+            },
+            "input_notify"
+        ]
+    }"#;
+    let tx = parsing::deserialization::json::deserialize_transaction_input(json).unwrap();
+    assert_eq!(tx.interval,TimeInterval { start: 1000, end: 2000 });
+    assert_eq!(tx.inputs[0],Input::NormalInput(InputContent::IDeposit {
+        into_account: Party::Role { role_token: "Seller".into() },
+        input_from_party: Party::Role { role_token: "Buyer".into() },
+        of_token: Token::ADA,
+        that_deposits: 100.into()
+    }));
+    assert_eq!(tx.inputs[2],Input::NormalInput(InputContent::INotify));
+    
+    let reserialized = serde_json::to_string(&tx).unwrap();
+    assert_eq!(parsing::deserialization::json::deserialize_transaction_input(&reserialized).unwrap(),tx);
+
+    let merkleized = r#"{
+        "tx_interval": { "from": 1000, "to": 2000 },
+        "tx_inputs": [
+            {
+                "input_from_party": { "role_token": "Buyer" },
+                "that_deposits": 100,
+                "of_token": { "currency_symbol": "", "token_name": "" },
+                "into_account": { "role_token": "Seller" },
+                "continuation_hash": "923918e403bf43c34b4ef6b48eb2ee04babed17320d8d1b9ff9ad086e86f44ec",
+                "merkleized_continuation": "close"
+            },
+            {
+                "continuation_hash": "923918e403bf43c34b4ef6b48eb2ee04babed17320d8d1b9ff9ad086e86f44ec",
+                "merkleized_continuation": "close"
+            }
+        ]
+    }"#;
+    let tx = parsing::deserialization::json::deserialize_transaction_input(merkleized).unwrap();
+    assert!(matches!(&tx.inputs[0],Input::MerkleizedInput(InputContent::IDeposit { .. },_,c) if **c == Contract::Close));
+    assert!(matches!(&tx.inputs[1],Input::MerkleizedInput(InputContent::INotify,hash,c) if **c == Contract::Close && hash.starts_with("923918e4")));
+    let reserialized = serde_json::to_string(&tx).unwrap();
+    assert_eq!(parsing::deserialization::json::deserialize_transaction_input(&reserialized).unwrap(),tx);
+}
+
+#[test]
+fn json_core_can_be_deserialized_back_into_the_same_contract() {
+    let serialized_contract = read_from_file("test_contracts/test_timeouts.marlowe");
+    let mut input = HashMap::new();
+    input.insert("TEST_PARAMETER_ONE".to_string(),666.into());
+    input.insert("TEST_PARAMETER_TWO".to_string(),4242.into());
+    input.insert("TEST_PARAMETER_THREE".to_string(),1658504132546i64.into());
+    let from_dsl = deserialize_with_input(&serialized_contract,input).unwrap();
+
+    let json_play = read_from_file("json_tests/test_timeouts_as_serialized_by_playground.json");
+    let from_json = parsing::deserialization::json::deserialize(&json_play).unwrap();
+    assert_eq!(serialize(from_json.clone()),serialize(from_dsl));
+
+    let json_core = parsing::serialization::json::serialize(from_json.clone()).unwrap();
+    assert_eq!(parsing::deserialization::json::deserialize(&json_core).unwrap(),from_json);
+}
+
+#[test]
+fn json_extended_keeps_uninitialized_params() {
+    let serialized_contract = read_from_file("test_contracts/test_uninitialized_timeout.marlowe");
+    let template = deserialize(&serialized_contract).unwrap();
+
+    let json_extended = parsing::serialization::json::serialize_extended(template.clone()).unwrap();
+    assert!(json_extended.contains("\"time_param\""));
+    assert_eq!(parsing::deserialization::json::deserialize(&json_extended).unwrap(),template);
+
+    // the core format must still refuse the template after having serialized an extended one
+    assert!(parsing::serialization::json::serialize(template.clone()).is_err());
+
+    // the format is chosen per value, so both can be written side by side
+    use parsing::serialization::json::Extended;
+    let both = serde_json::to_string(&(Extended(&template),&template));
+    assert!(both.is_err());
+    let compact = serde_json::to_string(&vec![Extended(&template),Extended(&template)]).unwrap();
+    assert_eq!(compact.matches("\"time_param\"").count(),2);
+}
+
+#[test]
+fn syntax_errors_are_reported_with_location_and_expected_rules() {
+    match deserialize("When [ ]\n (TimeParam \"test\") Clos") {
+        Err(parsing::error::ParseError::Syntax { line, column, expected, span, .. }) => {
+            assert_eq!((line,column),(2,21));
+            assert_eq!(span.start,29);
+            assert!(expected.contains(&Rule::Close));
+        },
+        other => panic!("Expected a syntax error, got: {other:?}")
+    }
+}
+
+#[test]
+fn unknown_input_keys_are_reported() {
+    let mut input = HashMap::new();
+    input.insert("test".to_string(),BigInt::from(1));
+    input.insert("not_a_param".to_string(),BigInt::from(2));
+    let source = "When [ ] (TimeParam \"test\") Close";
+    // extra keys are ignored unless asked for
+    assert_eq!(deserialize_with_input(source,input.clone()).unwrap(),deserialize("When [ ] 1 Close").unwrap());
+    match parsing::deserialization::deserialize_with_strict_input(source,input) {
+        Err(parsing::error::ParseError::UnknownInputKey { key }) => assert_eq!(key,"not_a_param"),
+        other => panic!("Expected an unknown key error, got: {other:?}")
+    }
+}
+
+#[test]
+fn spans_can_be_used_to_locate_nodes_in_the_source() {
+    let source = read_from_file("test_contracts/escrow.marlowe");
+    let (contract,spans) = parsing::deserialization::deserialize_with_spans(&source).unwrap();
+    assert_eq!(serialize(contract),serialize(deserialize(&source).unwrap()));
+
+    // the continuation of the first case of the outer When
+    let span = spans.get(&[0,0,1]).unwrap();
+    assert!(source[span.start..span.end].starts_with("When"));
+    assert_eq!(span.line_col(&source),(8,7));
+
+    // the timeout of the outer When
+    let span = spans.get(&[1]).unwrap();
+    assert_eq!(&source[span.start..span.end],"(TimeParam \"Payment deadline\")");
+    assert_eq!(spans.path_at(span.start + 1),Some(&vec![1]));
+}
+
+#[test]
+fn static_analysis_finds_runtime_warnings() {
+    use crate::analysis::*;
+    let contract = deserialize("When [ (Case (Deposit (Role \"A\") (Role \"B\") (Token \"\" \"\") (Constant 10)) (Let \"x\" (DivValue (Constant 1) (Constant 0)) (Let \"x\" (Constant 2) (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (Constant 20) (Assert FalseObs Close))))) ] 10 (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (NegValue (Constant 1)) Close)").unwrap();
+    let found : Vec<(Vec<usize>,WarningKind)> = warnings(&contract).into_iter().map(|x|(x.path,x.kind)).collect();
+    let token = Token::ADA;
+    assert_eq!(found,vec![
+        (vec![0,0,1,1],WarningKind::DivisionByZero),
+        (vec![0,0,1,2],WarningKind::Shadowing { value_id: "x".into() }),
+        (vec![0,0,1,2,2],WarningKind::PartialPay { account: Party::Role { role_token: "A".into() }, token, available: 10.into(), expected: 20.into() }),
+        (vec![0,0,1,2,2,4],WarningKind::AssertionAlwaysFails),
+        (vec![2],WarningKind::NonPositivePay { amount: (-1).into() }),
+    ]);
+}
+
+#[test]
+fn can_parse_all_observation_types() {
+    // the keyword is ValueEQ, "ValueE" used to be accepted instead
+    assert!(deserialize("Assert (ValueE (Constant 1) (Constant 2)) Close").is_err());
+    let contract = "Assert (AndObs (OrObs (ValueEQ (Constant 1) (Constant 2)) (ValueLE (Constant 1) (Constant 2))) (NotObs (ChoseSomething (ChoiceId \"c\" (Role \"A\"))))) Close";
+    let deserialized = deserialize(contract).unwrap();
+    assert_eq!(serialize(deserialized),contract);
+}
+
+#[test]
+fn timeout_checker_reports_violations_and_param_constraints() {
+    use crate::analysis::*;
+    let contract = deserialize(&read_from_file("test_contracts/escrow.marlowe")).unwrap();
+    let report = check_timeouts(&contract);
+    assert!(report.violations.is_empty());
+    let constraints : Vec<String> = report.constraints.iter().map(|x|x.to_string()).collect();
+    assert_eq!(constraints,vec![
+        "(TimeParam \"Payment deadline\") < (TimeParam \"Complaint response deadline\")",
+        "(TimeParam \"Complaint response deadline\") < (TimeParam \"Complaint deadline\")",
+        "(TimeParam \"Complaint deadline\") < (TimeParam \"Mediation deadline\")",
+    ]);
+
+    let contract = deserialize("When [ (Case (Notify TrueObs) (When [ ] 100 Close)) ] 200 (When [ ] 300 Close)").unwrap();
+    assert_eq!(check_timeouts(&contract).violations,vec![TimeoutViolation { path: vec![0,0,1], outer: 200.into(), inner: 100.into() }]);
+
+    // the outer bound still holds below a level with a time parameter
+    let contract = deserialize("When [ ] 200 (When [ ] (TimeParam \"later\") (When [ ] 150 Close))").unwrap();
+    let report = check_timeouts(&contract);
+    assert_eq!(report.violations,vec![TimeoutViolation { path: vec![2,2], outer: 200.into(), inner: 150.into() }]);
+    assert_eq!(report.constraints.len(),2);
+    let contract = deserialize("When [ ] 200 (When [ ] (TimeParam \"later\") (When [ ] 250 Close))").unwrap();
+    assert!(check_timeouts(&contract).violations.is_empty());
+}
+
+#[test]
+fn can_list_template_parameters() {
+    let contract = deserialize(&read_from_file("test_contracts/escrow.marlowe")).unwrap();
+    let params = crate::analysis::parameters(&contract);
+    let names = |x:&Vec<crate::analysis::Parameter>| x.iter().map(|p|(p.name.clone(),p.occurrences())).collect::<Vec<(String,usize)>>();
+    assert_eq!(names(&params.time_params),vec![
+        ("Mediation deadline".to_string(),1),
+        ("Complaint deadline".to_string(),1),
+        ("Complaint response deadline".to_string(),1),
+        ("Payment deadline".to_string(),1)
+    ]);
+    assert_eq!(names(&params.value_params),vec![("Price".to_string(),3)]);
+    assert_eq!(params.time_params[3].paths,vec![vec![1]]);
+}
+
+#[test]
+fn parsed_templates_can_be_instantiated() {
+    use crate::types::template::*;
+    let contract = deserialize(&read_from_file("test_contracts/escrow.marlowe")).unwrap();
+    let mut inputs = TemplateInputs { error_on_missing: true, error_on_unused: true, ..Default::default() };
+    for (i,name) in ["Payment deadline","Complaint response deadline","Complaint deadline","Mediation deadline"].iter().enumerate() {
+        inputs.time_params.insert(name.to_string(),(1000 * (i as i64 + 1)).into());
+    }
+    assert_eq!(contract.instantiate(&inputs),Err(InstantiationError::MissingValueParam("Price".to_string())));
+
+    inputs.value_params.insert("Price".to_string(),42.into());
+    let instantiated = contract.instantiate(&inputs).unwrap();
+    let mut old_style_input : HashMap<String,BigInt> = inputs.time_params.clone();
+    old_style_input.insert("Price".to_string(),42.into());
+    let parsed_with_input = deserialize_with_input(&read_from_file("test_contracts/escrow.marlowe"),old_style_input).unwrap();
+    assert_eq!(instantiated,parsed_with_input);
+
+    inputs.value_params.insert("Not used".to_string(),1.into());
+    assert_eq!(contract.instantiate(&inputs),Err(InstantiationError::UnusedValueParam("Not used".to_string())));
+    inputs.error_on_unused = false;
+    assert_eq!(contract.instantiate(&inputs).unwrap(),instantiated);
+}
+
+#[test]
+fn template_inputs_can_be_given_as_text() {
+    use crate::types::template::*;
+    let expected = BigInt::from(1646408491000i64);
+    for text in ["1646408491000","2022-03-04@15:41:31","2022-03-04T15:41:31Z","2022-03-04T16:41:31+01:00","2022-03-04T15:41:31"] {
+        assert_eq!(parse_time(text),Ok(expected.clone()),"{text}");
+    }
+    assert_eq!(parse_time_relative_to("+3d",1000),Ok(BigInt::from(1000 + 3 * 24 * 60 * 60 * 1000)));
+    assert_eq!(parse_time_relative_to("+30m",0),Ok(BigInt::from(30 * 60 * 1000)));
+    let beyond_i64 : BigInt = BigInt::from(i64::MAX) + 1;
+    assert_eq!(parse_time(&beyond_i64.to_string()),Ok(beyond_i64.clone()));
+    assert_eq!(parse_time_relative_to("+9223372036854775807ms",1),Ok(beyond_i64.clone()));
+    assert!(parse_time("2022-03-04@15:41").is_err());
+    assert!(parse_time("+3y").is_err());
+
+    assert_eq!(parse_amount("42"),Ok(BigInt::from(42)));
+    assert_eq!(parse_amount("42 lovelace"),Ok(BigInt::from(42)));
+    assert_eq!(parse_amount("5 ADA"),Ok(BigInt::from(5_000_000)));
+    assert_eq!(parse_amount("1.5ada"),Ok(BigInt::from(1_500_000)));
+    assert!(parse_amount("1.0000001 ADA").is_err());
+    assert!(parse_amount("5 BTC").is_err());
+
+    let contract = deserialize(&read_from_file("test_contracts/escrow.marlowe")).unwrap();
+    let inputs = TemplateInputs::from_text(&contract,[("Price","10 ADA"),("Payment deadline","2022-03-04@15:41:31")]).unwrap();
+    assert_eq!(inputs.value_params["Price"],BigInt::from(10_000_000));
+    assert_eq!(inputs.time_params["Payment deadline"],expected);
+    assert_eq!(TemplateInputs::from_text(&contract,[("Prize","10 ADA")]),Err(InstantiationError::UnknownParam("Prize".to_string())));
+    let inputs = TemplateInputs::from_text(&contract,[("Payment deadline","9223372036854775808")]).unwrap();
+    let instantiated = contract.instantiate(&inputs).unwrap();
+    assert!(matches!(instantiated,Contract::When { timeout: Slot::Filled(Timeout::TimeConstant(ref t)), .. } if *t == beyond_i64));
+}
+
+#[test]
+fn template_inputs_can_be_read_from_params_files() {
+    use crate::types::template::*;
+    let contract = read_from_file("test_contracts/escrow.marlowe");
+    let toml = r#"
+        [time_params]
+        "Payment deadline" = "2022-03-04T15:41:31Z"
+        "Complaint response deadline" = 1646408492000
+        "Complaint deadline" = "2022-03-04@15:41:33"
+        "Mediation deadline" = 1646408494000
+        [value_params]
+        Price = "5 ADA"
+    "#;
+    let inputs = TemplateInputs::from_toml(toml).unwrap();
+    let json = r#"{ 
+        "time_params": { 
+            "Payment deadline": 1646408491000, "Complaint response deadline": 1646408492000,
+            "Complaint deadline": 1646408493000, "Mediation deadline": 1646408494000 
+        },
+        "value_params": { "Price": 5000000 }
+    }"#;
+    assert_eq!(TemplateInputs::from_json(json),Ok(inputs.clone()));
+    let instantiated = crate::parsing::deserialization::deserialize_with_template_inputs(&contract,&inputs).unwrap();
+    assert!(crate::analysis::parameters(&instantiated) == Default::default());
+
+    let mut missing = inputs.clone();
+    missing.value_params.clear();
+    assert_eq!(
+        crate::parsing::deserialization::deserialize_with_template_inputs(&contract,&missing).unwrap_err().to_string(),
+        "No value was given for the constant parameter 'Price'.");
+    assert!(matches!(TemplateInputs::from_json(r#"{ "values": {} }"#),Err(InstantiationError::InvalidParamsFile(_))));
+
+    let beyond_i64 = r#"{ "time_params": { "Payment deadline": "9223372036854775808" } }"#;
+    assert_eq!(TemplateInputs::from_json(beyond_i64).unwrap().time_params["Payment deadline"],BigInt::from(i64::MAX) + 1);
+    assert!(matches!(TemplateInputs::from_json(r#"{ "time_params": { "Payment deadline": 1e30 } }"#),Err(InstantiationError::InvalidParamsFile(_))));
+}
+
+#[test]
+fn metadata_can_be_checked_against_a_contract() {
+    use crate::types::metadata::*;
+    let json = r#"{
+        "valueParameterInfo": [["Price",{"valueParameterFormat":{"contents":[6,"₳"],"tag":"DecimalFormat"},"valueParameterDescription":"Amount of lovelace to be paid"}]],
+        "timeParameterDescriptions": [["Payment deadline","The buyer must pay before this time"]],
+        "roleDescriptions": [["Buyer","The buyer"],["Seller","The seller"],["Auditor","Not in the contract"]],
+        "contractType": "Escrow",
+        "contractShortDescription": "Regulates a money exchange",
+        "contractName": "Simple escrow",
+        "contractLongDescription": "",
+        "choiceInfo": {
+            "Everything is alright": { "choiceFormat": {"tag":"DefaultFormat"}, "choiceDescription": "" },
+            "Dismiss claim": { "choiceFormat": {"tag":"TimeFormat"}, "choiceDescription": "When the claim was dismissed" }
+        }
+    }"#;
+    let metadata = MetaData::from_json(json).unwrap();
+    assert_eq!(metadata.value_parameter_info[0].1.value_parameter_format,NumberFormat::DecimalFormat(6,"₳".to_string()));
+    assert_eq!(metadata.choice_info.iter().find(|x|x.0 == "Dismiss claim").unwrap().1.choice_format,NumberFormat::TimeFormat);
+    let written : serde_json::Value = serde_json::from_str(&metadata.to_json().unwrap()).unwrap();
+    let dismiss = written["choiceInfo"].as_array().unwrap().iter().find(|x|x[0] == "Dismiss claim").unwrap();
+    assert_eq!(dismiss[1]["choiceFormat"],serde_json::json!({"tag":"TimeFormat"}));
+    assert_eq!(MetaData::from_json(&metadata.to_json().unwrap()).unwrap(),metadata);
+
+    let contract = deserialize(&read_from_file("test_contracts/escrow.marlowe")).unwrap();
+    let issues = metadata.check(&contract);
+    assert!(issues.contains(&MetaDataIssue::MissingRole("Mediator".to_string())));
+    assert!(issues.contains(&MetaDataIssue::StaleRole("Auditor".to_string())));
+    assert!(issues.contains(&MetaDataIssue::MissingChoice("Report problem".to_string())));
+    assert!(issues.contains(&MetaDataIssue::MissingTimeParameter("Mediation deadline".to_string())));
+    assert!(!issues.iter().any(|x|matches!(x,MetaDataIssue::MissingValueParameter(_)|MetaDataIssue::StaleValueParameter(_))));
+}
+
+#[test]
+fn playground_projects_can_be_read_and_written() {
+    use crate::types::project::Project;
+    let source = read_from_file("test_contracts/escrow.marlowe");
+    let json = serde_json::json!({
+        "projectName": "Escrow",
+        "version": "0.1.0",
+        "contract": source,
+        "metadata": {
+            "contractType": "Escrow", "contractName": "Simple escrow",
+            "contractShortDescription": "", "contractLongDescription": "",
+            "roleDescriptions": [["Buyer","The buyer"]], "choiceInfo": [],
+            "valueParameterInfo": [], "timeParameterDescriptions": []
+        }
+    }).to_string();
+    let mut project = Project::from_json(&json).unwrap();
+    assert_eq!(project.metadata.contract_name,"Simple escrow");
+    let contract = project.contract().unwrap();
+    assert_eq!(contract,deserialize(&source).unwrap());
+
+    project.set_contract(Contract::Close);
+    let written = Project::from_json(&project.to_json().unwrap()).unwrap();
+    assert_eq!(written.contract().unwrap(),Contract::Close);
+    assert_eq!(written.other["version"],"0.1.0");
+    assert_eq!(written.metadata,project.metadata);
+}
+
+#[test]
+fn contracts_can_be_converted_to_and_from_blockly() {
+    for file in ["test_contracts/escrow.marlowe","test_contracts/swap.marlowe","test_contracts/test_value_types.marlowe","test_contracts/Contract_For_Differences_with_Oracle.marlowe"] {
+        let contract = deserialize(&read_from_file(file)).unwrap();
+        let xml = crate::parsing::serialization::blockly::serialize(contract.clone()).unwrap();
+        assert_eq!(crate::parsing::deserialization::blockly::deserialize(&xml).unwrap(),contract,"{file}");
+    }
+    let with_holes = deserialize("When [ (Case (Deposit ?party (Role \"a & b\") ?token ?value) ?contract) ] ?timeout (If ?observation Close ?contract)").unwrap();
+    let xml = crate::parsing::serialization::blockly::serialize(with_holes.clone()).unwrap();
+    assert!(xml.contains("<field name=\"role\">a &amp; b</field>"));
+    assert_eq!(crate::parsing::deserialization::blockly::deserialize(&xml).unwrap(),with_holes);
+
+    let playground_xml = r#"<xml xmlns="https://developers.google.com/blockly/xml">
+        <block type="BaseContractType" x="13" y="187">
+          <statement name="BaseContractType">
+            <block type="PayContractType">
+              <value name="party"><block type="RolePartyType"><field name="role">Alice</field></block></value>
+              <value name="payee"><block type="PartyPayeeType"><value name="party"><block type="RolePartyType"><field name="role">Bob</field></block></value></block></value>
+              <value name="token"><block type="AdaTokenType"></block></value>
+              <value name="value"><block type="ConstantValueType"><field name="constant">5</field></block></value>
+              <statement name="contract"><block type="CloseContractType"></block></statement>
+            </block>
+          </statement>
+        </block>
+      </xml>"#;
+    assert_eq!(
+        crate::parsing::deserialization::blockly::deserialize(playground_xml).unwrap(),
+        Contract::Pay {
+            from_account: Slot::Filled(Party::Role { role_token: "Alice".to_string() }),
+            to: Slot::Filled(Payee::Party(Slot::Filled(Party::Role { role_token: "Bob".to_string() }))),
+            token: Slot::Filled(Token::ADA),
+            pay: Slot::Filled(Value::ConstantValue(5.into())),
+            then: Slot::Filled(Contract::Close.boxed())
+        }
+    );
+}
+
+#[test]
+fn blockly_names_holes_after_their_input() {
+    use crate::parsing::serialization::blockly::serialize;
+    use crate::parsing::deserialization::blockly::deserialize as from_blockly;
+    // blockly has no names for holes, so they are named after the kind of input they are in
+    let contract = deserialize("If (ValueGT ?a ?b) (Pay ?from (Account ?to) ?coin (NegValue ?amount) ?then) ?otherwise").unwrap();
+    let expected = deserialize("If (ValueGT ?value ?value) (Pay ?party (Account ?party) ?token (NegValue ?value) ?contract) ?contract").unwrap();
+    assert_eq!(from_blockly(&serialize(contract).unwrap()).unwrap(),expected);
+    assert_eq!(from_blockly(&serialize(expected.clone()).unwrap()).unwrap(),expected);
+}
+
+#[test]
+fn blockly_rejects_holes_it_can_not_represent() {
+    use crate::parsing::serialization::blockly::{serialize, BlocklyError};
+    use crate::parsing::deserialization::blockly::deserialize as from_blockly;
+    let hole = |name:&str,expected| Err(BlocklyError::Hole { name: name.to_string(), expected });
+    // a case is written as its action block, and lists of cases and bounds have no empty items
+    assert_eq!(serialize(deserialize(&read_from_file("test_contracts/test_holes.marlowe")).unwrap()),hole("action","action"));
+    assert_eq!(serialize(deserialize("When [ (Case ?action Close) ] 10 Close").unwrap()),hole("action","action"));
+    assert_eq!(serialize(deserialize("When [ ?case, (Case (Notify TrueObs) Close) ] 10 Close").unwrap()),hole("case","case"));
+    assert_eq!(serialize(deserialize("When [ (Case (Choice (ChoiceId \"c\" (Role \"A\")) [ (Bound 1 2), ?bound ]) Close) ] 10 Close").unwrap()),hole("bound","bound"));
+
+    // holes in the inputs of choice ids are left unconnected like all other holes
+    let contract = deserialize("When [ (Case (Choice (ChoiceId \"c\" ?party) [ (Bound 1 2) ]) (If (ValueGE (ChoiceValue (ChoiceId \"c\" ?party)) ?value) ?contract Close)) ] ?timeout ?contract").unwrap();
+    assert_eq!(from_blockly(&serialize(contract.clone()).unwrap()).unwrap(),contract);
+}
+
+#[test]
+fn contracts_can_be_encoded_as_plutus_data() {
+    use crate::plutus_data::*;
+    // Close is Constr 0 [], the same data as the unit datum `()`, so it must have the
+    // well known encoding and datum hash of the unit datum as reported by cardano-cli
+    let unit_datum_hex = "d87980";
+    assert_eq!(to_cbor_hex(&Contract::Close).unwrap(),unit_datum_hex);
+    assert_eq!(from_cbor_hex::<Contract>(unit_datum_hex).unwrap(),Contract::Close);
+    assert_eq!(crate::merkleization::contract_hash(&Contract::Close).unwrap(),"923918e403bf43c34b4ef6b48eb2ee04babed17320d8d1b9ff9ad086e86f44ec");
+    assert_eq!(to_cbor_hex(&Token::ADA).unwrap(),"d8799f4040ff");
+
+    let pay = Contract::Pay {
+        from_account: Slot::Filled(Party::Role { role_token: "Alice".to_string() }),
+        to: Slot::Filled(Payee::Party(Slot::Filled(Party::Role { role_token: "Bob".to_string() }))),
+        token: Slot::Filled(Token::ADA),
+        pay: Slot::Filled(Value::ConstantValue(5.into())),
+        then: Slot::Filled(Contract::Close.boxed())
+    };
+    let pay_hex = "d87a9fd87a9f45416c696365ffd87a9fd87a9f43426f62ffffd8799f4040ffd87a9f05ffd87980ff";
+    assert_eq!(to_cbor_hex(&pay).unwrap(),pay_hex);
+    assert_eq!(from_cbor_hex::<Contract>(pay_hex).unwrap(),pay);
+
+    // TrueObs has constructor index 9, which uses the extended tag range (1280 + 2)
+    let when = deserialize("When [ (Case (Notify TrueObs) Close) ] 1000 Close").unwrap();
+    let when_hex = "d87c9f9fd8799fd87b9fd9050280ffd87980ffff1903e8d87980ff";
+    assert_eq!(to_cbor_hex(&when).unwrap(),when_hex);
+    assert_eq!(from_cbor_hex::<Contract>(when_hex).unwrap(),when);
+
+    // the recorded swap uses a shortened currency symbol, which can not be encoded
+    let swap_source = read_from_file("test_contracts/swap.marlowe").replace("\"85bb65\"","\"8bb3b343d8e404472337966a722150048c768d0a92a9813596c5338d\"");
+    let swap = deserialize(&swap_source).unwrap();
+    let instantiated = swap.instantiate(&crate::types::template::TemplateInputs {
+        time_params: crate::analysis::parameters(&swap).time_params.iter().map(|x|(x.name.clone(),1000.into())).collect(),
+        value_params: crate::analysis::parameters(&swap).value_params.iter().map(|x|(x.name.clone(),5.into())).collect(),
+        ..Default::default()
+    }).unwrap();
+    // (Token "" "") is decoded as ADA, so compare the encoded bytes rather than the contracts
+    let encoded = to_cbor(&instantiated).unwrap();
+    assert_eq!(to_cbor(&from_cbor::<Contract>(&encoded).unwrap()).unwrap(),encoded);
+
+    assert_eq!(to_cbor(&deserialize("When [] ?timeout Close").unwrap()),Err(PlutusDataError::Hole("timeout".to_string())));
+    assert_eq!(to_cbor(&deserialize("When [] (TimeParam \"x\") Close").unwrap()),Err(PlutusDataError::UninitializedParam("x".to_string())));
+
+    let long_bytes = PlutusData::Bytes(vec![7;100]);
+    let encoded = long_bytes.to_cbor();
+    assert_eq!(&encoded[..3],&[0x5f,0x58,0x40]);
+    assert_eq!(PlutusData::from_cbor(&encoded).unwrap(),long_bytes);
+    let big = PlutusData::Integer(-(BigInt::from(1) << 70usize));
+    assert_eq!(PlutusData::from_cbor(&big.to_cbor()).unwrap(),big);
+}
+
+#[test]
+fn marlowe_data_can_be_decoded_from_datum_hex() {
+    use crate::plutus_data::*;
+    let datum = MarloweData::from_cbor_hex("d8799fd8799f42abcdffd8799fa0a0a000ffd87980ff").unwrap();
+    assert_eq!(datum.params.roles_currency,"abcd");
+    assert_eq!(datum.state,crate::semantics::State::empty_state(0));
+    assert_eq!(datum.contract,Contract::Close);
+
+    let alice = Party::Role { role_token: "Alice".to_string() };
+    let mut state = crate::semantics::State::empty_state(1000);
+    state.accounts.insert((alice.clone(),Token::ADA),5.into());
+    state.choices.insert(ChoiceId { choice_name: "c".to_string(), choice_owner: Slot::Filled(alice) },1.into());
+    state.bound_values.insert("v".to_string(),(-1).into());
+    let datum = MarloweData {
+        params: MarloweParams { roles_currency: "abcd".to_string() },
+        state,
+        contract: deserialize("When [ (Case (Notify TrueObs) Close) ] 1000 Close").unwrap()
+    };
+    let hex = to_cbor_hex(&datum).unwrap();
+    assert!(hex.contains("a1d8799fd87a9f45416c696365ffd8799f4040ffff05"));
+    assert_eq!(MarloweData::from_cbor_hex(&hex).unwrap(),datum);
+    assert!(MarloweData::from_cbor_hex("d87980").is_err());
+}
+
+#[test]
+fn case_continuations_can_be_merkleized() {
+    use crate::merkleization::*;
+    let close_hash = "923918e403bf43c34b4ef6b48eb2ee04babed17320d8d1b9ff9ad086e86f44ec";
+    assert_eq!(contract_hash(&Contract::Close).unwrap(),close_hash);
+
+    let contract = deserialize("When [ (Case (Notify TrueObs) (When [ (Case (Notify FalseObs) Close) ] 2000 Close)) ] 1000 Close").unwrap();
+    let (merkleized,store) = merkleize(&contract,None).unwrap();
+    assert_eq!(store.len(),2);
+    assert_eq!(store[close_hash],Contract::Close);
+    let Contract::When { when, .. } = &merkleized else { panic!("expected a when contract") };
+    let Slot::Filled(Case::MerkleizedCase { then, .. }) = &when[0] else { panic!("expected a merkleized case") };
+    assert!(matches!(&store[then],Contract::When { when, .. } if matches!(when[0],Slot::Filled(Case::MerkleizedCase { .. }))));
+    assert_eq!(rehydrate(&merkleized,&store).unwrap(),contract);
+    let continuation = store[then].clone();
+
+    // the DSL, json and Plutus Data formats all keep merkleized cases
+    let dsl = crate::parsing::serialization::marlowe::serialize(merkleized.clone());
+    assert!(dsl.contains(&format!("(MerkleizedCase (Notify TrueObs) \"{then}\")")));
+    assert_eq!(deserialize(&dsl).unwrap(),merkleized);
+    let json = crate::parsing::serialization::json::serialize(merkleized.clone()).unwrap();
+    assert_eq!(crate::parsing::deserialization::json::deserialize(&json).unwrap(),merkleized);
+    let cbor = crate::plutus_data::to_cbor(&merkleized).unwrap();
+    assert_eq!(crate::plutus_data::from_cbor::<Contract>(&cbor).unwrap(),merkleized);
+    // blockly can not edit them, so they have to be rehydrated first
+    assert_eq!(crate::parsing::serialization::blockly::serialize(merkleized.clone()),
+        Err(crate::parsing::serialization::blockly::BlocklyError::MerkleizedCase { hash: then.clone() }));
+
+    // only continuations that are large enough are replaced when using a threshold
+    let (partial,store) = merkleize(&contract,Some(10)).unwrap();
+    assert_eq!(store.len(),1);
+    assert!(!store.contains_key(close_hash));
+    assert_eq!(rehydrate(&partial,&store).unwrap(),contract);
+    assert_eq!(rehydrate(&merkleized,&Default::default()),Err(MerkleizationError::MissingContinuation(then.clone())));
+
+    // inputs for merkleized cases carry the continuation, normal inputs do not match them
+    let tx = |input| TransactionInput { interval: TimeInterval { start: 10, end: 20 }, inputs: vec![input] };
+    let state = crate::semantics::State::empty_state(0);
+    let output = crate::semantics::compute_transaction(&tx(Input::MerkleizedInput(InputContent::INotify,then.clone(),continuation.clone().boxed())),&state,&merkleized).unwrap();
+    assert_eq!(output.contract,continuation);
+    assert_eq!(crate::semantics::compute_transaction(&tx(Input::NormalInput(InputContent::INotify)),&state,&merkleized),Err(crate::semantics::TransactionError::HashMismatch));
+}
+
+#[test]
+fn address_parties_are_validated_and_checked_for_network_mixing() {
+    use crate::types::address::*;
+    // test vectors from CIP-19
+    let mainnet = "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x";
+    let testnet = "addr_test1qz2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgs68faae";
+    let pointer = "addr1gx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer5pnz75xxcrzqf96k";
+    let enterprise = "addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8";
+    assert_eq!(network(mainnet),Ok(Network::Mainnet));
+    assert_eq!(network(testnet),Ok(Network::Testnet));
+    let decoded = ShelleyAddress::from_bech32(pointer).unwrap();
+    assert_eq!(decoded.stake,StakeReference::Pointer { slot: 2498243, tx_index: 27, cert_index: 3 });
+    for address in [mainnet,testnet,pointer,enterprise] {
+        assert_eq!(ShelleyAddress::from_bech32(address).unwrap().to_bech32(),address);
+    }
+    assert!(matches!(network(&mainnet.replace("35a3x","35a3y")),Err(AddressError::InvalidBech32(_))));
+    assert!(matches!(network("stake1uyehkck0lajq8gr28t9uxnuvgcqrc6070x3k9r8048z8y5gh6ffgw"),Err(AddressError::UnknownPrefix(_))));
+
+    let dsl = format!("When [ (Case (Deposit (Address \"{mainnet}\") (Address \"{testnet}\") (Token \"abcd\" \"coin\") (Constant 5)) Close) ] 1000 (Pay (Address \"{mainnet}\") (Party (Role \"Bob\")) (Token \"abcd\" \"coin\") (Constant 5) Close)");
+    let contract = deserialize(&dsl).unwrap();
+    assert_eq!(deserialize(&crate::parsing::serialization::marlowe::serialize(contract.clone())).unwrap(),contract);
+    let json = crate::parsing::serialization::json::serialize(contract.clone()).unwrap();
+    assert!(json.contains(&format!("\"address\": \"{testnet}\"")));
+    assert_eq!(crate::parsing::deserialization::json::deserialize(&json).unwrap(),contract);
+    let blockly = crate::parsing::serialization::blockly::serialize(contract.clone()).unwrap();
+    assert_eq!(crate::parsing::deserialization::blockly::deserialize(&blockly).unwrap(),contract);
+    let party = Party::Address { address: pointer.to_string() };
+    let encoded = crate::plutus_data::to_cbor(&party).unwrap();
+    assert_eq!(crate::plutus_data::from_cbor::<Party>(&encoded).unwrap(),party);
+
+    let report = crate::analysis::check_networks(&contract);
+    assert!(report.is_mixed());
+    assert_eq!(report.mainnet,vec![vec![0,0,0,0],vec![2,0]]);
+    assert_eq!(report.testnet,vec![vec![0,0,0,1]]);
+    assert_eq!(report.network(),None);
+
+    match deserialize("When [ ] 10 (Pay (Address \"addr1qx2fxv\") (Party (Role \"Bob\")) (Token \"\" \"\") (Constant 5) Close)") {
+        Err(crate::parsing::error::ParseError::Validation { span: Some(_), error: crate::validation::ValidationErrorKind::InvalidAddress { address, .. } }) =>
+            assert_eq!(address,"addr1qx2fxv"),
+        other => panic!("Expected an invalid address error, got: {other:?}")
+    }
+}
+
+#[test]
+fn key_hashes_currency_symbols_and_token_names_are_validated() {
+    use crate::validation::*;
+    use crate::parsing::deserialization::deserialize_validated;
+    let symbol = "8bb3b343d8e404472337966a722150048c768d0a92a9813596c5338d";
+    let contract = Contract::Pay {
+        from_account: Slot::Filled(Party::PK { pk_hash: "abc".into() }),
+        to: Slot::Filled(Payee::Party(Slot::Filled(Party::PK { pk_hash: "0".repeat(56) }))),
+        token: Slot::Filled(Token::Custom { currency_symbol: "".into(), token_name: "coin".into() }),
+        pay: Slot::Filled(Value::AvailableMoney(
+            Slot::Filled(Party::Role { role_token: "A".into() }),
+            Slot::Filled(Token::Custom { currency_symbol: symbol.into(), token_name: "x".repeat(33) })
+        )),
+        then: Slot::Filled(Contract::Close.boxed())
+    };
+    let found : Vec<(Vec<usize>,ValidationErrorKind)> = validate(&contract).into_iter().map(|x|(x.path,x.kind)).collect();
+    assert_eq!(found,vec![
+        (vec![0],ValidationErrorKind::InvalidPubKeyHash("abc".into())),
+        (vec![2],ValidationErrorKind::MalformedAda { token_name: "coin".into() }),
+        (vec![3,1],ValidationErrorKind::TokenNameTooLong("x".repeat(33))),
+    ]);
+    // json is also used for contracts written for older tools, so it is only checked on chain
+    assert!(crate::parsing::serialization::json::serialize(contract.clone()).is_ok());
+    assert_eq!(crate::plutus_data::to_cbor(&contract),Err(crate::plutus_data::PlutusDataError::Validation(ValidationErrorKind::InvalidPubKeyHash("abc".into()))));
+
+    // token names are encoded as utf-8, so a hex string is measured by its characters, not the bytes it spells
+    let hex_name = Token::Custom { currency_symbol: symbol.into(), token_name: "ab".repeat(20) };
+    assert_eq!(validate_token(&hex_name),Err(ValidationErrorKind::TokenNameTooLong("ab".repeat(20))));
+    assert_eq!(crate::plutus_data::to_cbor(&hex_name),Err(crate::plutus_data::PlutusDataError::Validation(ValidationErrorKind::TokenNameTooLong("ab".repeat(20)))));
+    assert_eq!(validate_token(&Token::Custom { currency_symbol: symbol.into(), token_name: "ab".repeat(16) }),Ok(()));
+    assert_eq!(validate_token(&Token::Custom { currency_symbol: symbol.into(), token_name: "ä".repeat(17) }),Err(ValidationErrorKind::TokenNameTooLong("ä".repeat(17))));
+    assert_eq!(validate_token(&Token::Custom { currency_symbol: symbol[2..].into(), token_name: "".into() }),Err(ValidationErrorKind::InvalidCurrencySymbol(symbol[2..].into())));
+
+    // the parser reads (Token "" "") as ADA, anything else without a currency symbol is rejected on request
+    let pay = |party:&str,token:&str| format!("Pay {party} (Party (Role \"B\")) {token} (Constant 1) Close");
+    assert!(matches!(deserialize(&pay("(Role \"A\")","(Token \"\" \"\")")).unwrap(),Contract::Pay { token: Slot::Filled(Token::ADA), .. }));
+    let source = pay("(Role \"A\")","(Token \"\" \"aa\")");
+    assert!(deserialize(&source).is_ok());
+    match deserialize_validated(&source) {
+        Err(crate::parsing::error::ParseError::Validation { span: Some(span), error: ValidationErrorKind::MalformedAda { token_name } }) => {
+            assert_eq!(token_name,"aa");
+            assert_eq!(&source[span.start..span.end],"(Token \"\" \"aa\")");
+        },
+        other => panic!("Expected a malformed ADA error, got: {other:?}")
+    }
+    assert!(matches!(deserialize_validated(&pay("(PK \"0000\")","(Token \"\" \"\")")),
+        Err(crate::parsing::error::ParseError::Validation { error: ValidationErrorKind::InvalidPubKeyHash(_), .. })));
+    let valid = pay(&format!("(PK \"{}\")","0".repeat(56)),&format!("(Token \"{symbol}\" \"coin\")"));
+    assert_eq!(deserialize_validated(&valid).unwrap(),deserialize(&valid).unwrap());
+
+    // contracts recorded from the playground still parse, but use placeholder key hashes
+    let recorded = read_from_file("test_contracts/test_timeouts.marlowe");
+    assert!(deserialize(&recorded).is_ok());
+    assert!(matches!(deserialize_validated(&recorded),
+        Err(crate::parsing::error::ParseError::Validation { span: Some(_), error: ValidationErrorKind::InvalidPubKeyHash(_) })));
+}
+
+#[test]
+fn integers_larger_than_64_bits_are_supported() {
+    let big : BigInt = "123456789012345678901234567890".parse().unwrap();
+    let source = format!("When [Case (Choice (ChoiceId \"c\" (Role \"A\")) [Bound 0 {big}]) Close] {big} (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (Constant -{big}) Close)");
+    let contract = deserialize(&source).unwrap();
+    match &contract {
+        Contract::When { when, timeout: Slot::Filled(Timeout::TimeConstant(t)), timeout_continuation: Slot::Filled(c) } => {
+            assert_eq!(*t,big);
+            assert!(matches!(&when[0],Slot::Filled(Case::Case { case: Slot::Filled(Action::Choice { choose_between, .. }), .. }) if choose_between[0] == Slot::Filled(Bound(0.into(),big.clone()))));
+            assert!(matches!(&**c,Contract::Pay { pay: Slot::Filled(Value::ConstantValue(n)), .. } if *n == -big.clone()));
+        },
+        other => panic!("Unexpected contract: {other:?}")
+    }
+    assert_eq!(deserialize(&serialize(contract.clone())).unwrap(),contract);
+
+    // json numbers are written out in full, also beyond 2^53
+    let json = parsing::serialization::json::serialize(contract.clone()).unwrap();
+    assert!(json.contains(&format!("-{big}")));
+    assert_eq!(parsing::deserialization::json::deserialize(&json).unwrap(),contract);
+
+    assert_eq!(crate::plutus_data::from_cbor::<Contract>(&crate::plutus_data::to_cbor(&contract).unwrap()).unwrap(),contract);
+
+    // parameters given as input to the parser may also be larger than i64::MAX
+    let above_i64 : BigInt = BigInt::from(i64::MAX) + 1;
+    let input = HashMap::from([("Price".to_string(),above_i64.clone()),("Deadline".to_string(),big.clone())]);
+    let parameterized = "When [ ] (TimeParam \"Deadline\") (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (ConstantParam \"Price\") Close)";
+    assert_eq!(
+        parsing::deserialization::deserialize_with_input(parameterized,input).unwrap(),
+        deserialize(&format!("When [ ] {big} (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (Constant {above_i64}) Close)")).unwrap()
+    );
+
+    assert_eq!(crate::types::template::parse_amount(&format!("{big} ADA")),Ok(big * 1_000_000));
+}
+
+#[test]
+fn pretty_printer_reproduces_playground_layout() {
+    use crate::parsing::pretty::{format, FormatOptions};
+    for entry in std::fs::read_dir("test_contracts").unwrap() {
+        let path = entry.unwrap().path();
+        let source = read_from_file(path.to_str().unwrap());
+        let contract = deserialize(&source).unwrap();
+        assert_eq!(format(&contract,&FormatOptions::default()),source,"{path:?}");
+    }
+
+    // a NegValue is only broken when its argument is
+    let contract = deserialize("Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (AddValue (NegValue (UseValue \"x\")) (NegValue (NegValue (Constant 1)))) Close").unwrap();
+    assert_eq!(format(&contract,&FormatOptions::default()),"Pay\n  (Role \"A\")\n  (Party\n     (Role \"B\"))\n  (Token \"\" \"\")\n  (AddValue\n     (NegValue (UseValue \"x\"))\n     (NegValue\n        (NegValue (Constant 1)))) Close");
+
+    // lists of several items are kept on one line when they fit
+    let contract = deserialize("When [ (Case (Notify TrueObs) Close), (Case (Notify FalseObs) Close) ] 10 Close").unwrap();
+    let wide = format(&contract,&FormatOptions { indent: 4, width: 80 });
+    assert_eq!(wide,"When [ (Case (Notify TrueObs) Close), (Case (Notify FalseObs) Close)] 10 Close");
+    let narrow = format(&contract,&FormatOptions { indent: 4, width: 40 });
+    assert_eq!(narrow,"When [\n     (Case\n          (Notify TrueObs) Close)\n     ,\n     (Case\n          (Notify FalseObs) Close)] 10 Close");
+    assert_eq!(deserialize(&narrow).unwrap(),contract);
+
+    // the width is counted in characters, not bytes
+    let flat = "When [ (Case (Notify TrueObs) Close), (Case (Notify (ChoseSomething (ChoiceId \"c\" (Role \"Åsa Öberg\")))) Close)] 10 Close";
+    let contract = deserialize(flat).unwrap();
+    assert_eq!(format(&contract,&FormatOptions { indent: 2, width: flat.chars().count() }),flat);
+    assert_ne!(format(&contract,&FormatOptions { indent: 2, width: flat.chars().count() - 1 }),flat);
+}
+
+#[test]
+fn comments_survive_a_round_trip() {
+    use crate::parsing::comments::{Comment, Placement};
+    use crate::parsing::deserialization::deserialize_with_comments;
+    use crate::parsing::serialization::marlowe::serialize_with_comments;
+    let source = "-- pays out when notified
+When [ {- the only {- nested -} case -}
+    (Case (Notify TrueObs) -- no condition
+        (Pay (Role \"A\") (Party (Role \"B\")) (Token \"\" \"\") (Constant 5) Close))
+] 10 Close -- the end";
+    let (contract,comments) = deserialize_with_comments(source).unwrap();
+    assert_eq!(contract,deserialize(source).unwrap());
+    let comment = |text:&str,placement| Comment { text: text.to_string(), placement };
+    assert_eq!(comments.get(&[]),&[comment("-- pays out when notified",Placement::Before),comment("-- the end",Placement::After)]);
+    assert_eq!(comments.get(&[0,0]),&[comment("{- the only {- nested -} case -}",Placement::Before)]);
+    assert_eq!(comments.get(&[0,0,0]),&[comment("-- no condition",Placement::After)]);
+
+    let serialized = serialize_with_comments(contract.clone(),&comments);
+    assert_eq!(serialized,"-- pays out when notified
+When [
+  {- the only {- nested -} case -}
+  (Case
+     (Notify TrueObs) -- no condition
+     (Pay
+        (Role \"A\")
+        (Party
+           (Role \"B\"))
+        (Token \"\" \"\")
+        (Constant 5) Close))] 10 Close -- the end");
+    assert_eq!(deserialize_with_comments(&serialized).unwrap(),(contract,comments));
+
+    // comment markers in strings are not comments
+    let (_,comments) = deserialize_with_comments("Let \"--{-\" (Constant 1) Close").unwrap();
+    assert!(comments.is_empty());
+}
+
+#[test]
+fn syntax_trees_are_lossless_and_can_be_edited_in_place() {
+    use crate::parsing::cst::*;
+    use crate::parsing::error::Span;
+    let source = "-- escrow
+When [
+  (Case (Deposit ?seller   ?buyer (Token \"\" \"\") (Constant 10))
+      (Pay ?seller (Party ?buyer) (Token \"\" \"\") (Constant 10) Close))
+] 100 {- refund -} Close
+";
+    let mut tree = SyntaxTree::parse(source).unwrap();
+
+    fn texts(node:&SyntaxNode,out:&mut Vec<Span>) {
+        if node.children.is_empty() {
+            return out.push(node.span)
+        }
+        for x in &node.children {
+            match x {
+                SyntaxElement::Node(n) => texts(n,out),
+                SyntaxElement::Token(t) => out.push(t.span)
+            }
+        }
+    }
+    let mut spans : Vec<Span> = tree.leading_trivia().iter().map(|x|x.span).collect();
+    texts(tree.root(),&mut spans);
+    spans.extend(tree.trailing_trivia().iter().map(|x|x.span));
+    assert_eq!(spans.iter().map(|x|tree.text(*x)).collect::<String>(),source);
+    assert_eq!(tree.node(&[0,0,1]).map(|x|x.rule),Some(Rule::Pay));
+
+    let holes : Vec<&str> = tree.holes().into_iter().filter_map(|x|tree.hole_name(x)).collect();
+    assert_eq!(holes,vec!["seller","buyer","seller","buyer"]);
+
+    let alice = Party::Role { role_token: "Alice".into() };
+    let edits = tree.apply(&Edit::FillHole { name: "seller".into(), with: Replacement::Party(alice.clone()) }).unwrap();
+    assert_eq!(edits,vec![
+        TextEdit { span: Span { start: 34, end: 41 }, text: "(Role \"Alice\")".into() },
+        TextEdit { span: Span { start: 91, end: 98 }, text: "(Role \"Alice\")".into() }
+    ]);
+    assert_eq!(tree.source(),source.replace("?seller","(Role \"Alice\")"));
+
+    // nested contracts are written in the layout of the playground, at the column they start at
+    let pay = Contract::Pay {
+        from_account: Slot::Filled(alice),
+        to: Slot::Filled(Payee::Party(Slot::Filled(Party::Role { role_token: "Bob".into() }))),
+        token: Slot::Filled(Token::ADA),
+        pay: Slot::Filled(Value::ConstantValue(10.into())),
+        then: Slot::Filled(Contract::Close.boxed())
+    };
+    let edits = tree.apply(&Edit::Replace { path: vec![0,0,1], with: Replacement::Contract(pay.clone()) }).unwrap();
+    assert_eq!(edits.len(),1);
+    assert_eq!(tree.source(),"-- escrow
+When [
+  (Case (Deposit (Role \"Alice\")   ?buyer (Token \"\" \"\") (Constant 10))
+      (Pay
+         (Role \"Alice\")
+         (Party
+            (Role \"Bob\"))
+         (Token \"\" \"\")
+         (Constant 10) Close))
+] 100 {- refund -} Close
+");
+    match tree.contract().unwrap() {
+        Contract::When { when, .. } => assert!(matches!(&when[0],Slot::Filled(Case::Case { then: Slot::Filled(x), .. }) if **x == pay)),
+        other => panic!("{other:?}")
+    }
+
+    // the parentheses around a nested contract go away along with it
+    let close = Replacement::Contract(Contract::Close);
+    tree.apply(&Edit::Replace { path: vec![0,0,1], with: close.clone() }).unwrap();
+    assert!(tree.source().contains("(Constant 10))\n      Close)\n] 100 {- refund -} Close\n"));
+    assert_eq!(tree.apply(&Edit::Replace { path: vec![2], with: close.clone() }).unwrap(),vec![]);
+
+    assert_eq!(
+        tree.apply(&Edit::Replace { path: vec![1], with: close.clone() }),
+        Err(EditError::TypeMismatch { path: vec![1], expected: NodeKind::Timeout, found: NodeKind::Contract })
+    );
+    assert_eq!(tree.apply(&Edit::FillHole { name: "seller".into(), with: close.clone() }),Err(EditError::NoSuchHole("seller".into())));
+    assert_eq!(tree.apply(&Edit::Replace { path: vec![3], with: close }),Err(EditError::NoSuchNode(vec![3])));
+}
+
+#[test]
+fn hole_names_survive_parsing_and_serialization() {
+    use crate::parsing::pretty::{format, FormatOptions};
+    use crate::plutus_data::*;
+    let source = "When [ (Case (Deposit ?seller (Role \"Bob\") (Token \"\" \"\") ?seller-deposit) ?after_deposit) ] ?deadline Close";
+    let contract = deserialize(source).unwrap();
+    match &contract {
+        Contract::When { when, timeout, .. } => {
+            assert_eq!(timeout.hole_name(),Some("deadline"));
+            match &when[0] {
+                Slot::Filled(Case::Case { case: Slot::Filled(Action::Deposit { into_account, deposits, .. }), then }) => {
+                    assert_eq!(*into_account,Slot::hole("seller"));
+                    assert_eq!(*deposits,Slot::hole("seller-deposit"));
+                    assert_eq!(then.hole_name(),Some("after_deposit"));
+                },
+                other => panic!("{other:?}")
+            }
+        },
+        other => panic!("{other:?}")
+    }
+
+    let serialized = serialize(contract.clone());
+    for name in ["?seller","?seller-deposit","?after_deposit","?deadline"] {
+        assert!(serialized.contains(name),"{name}");
+    }
+    assert_eq!(deserialize(&serialized).unwrap(),contract);
+    assert_eq!(deserialize(&format(&contract,&FormatOptions::default())).unwrap(),contract);
+
+    assert!(crate::parsing::serialization::json::serialize(contract.clone()).is_err());
+    assert_eq!(to_cbor(&contract),Err(PlutusDataError::Hole("seller".to_string())));
+}
@@ -0,0 +1,200 @@
+// Shelley era payment addresses, as used by Address parties.
+// The binary format is described in CIP-19: the header byte holds the address
+// type in the high nibble and the network id in the low nibble, followed by the
+// payment credential and, depending on the type, a stake reference.
+
+use bech32::{FromBase32, ToBase32, Variant};
+
+const MAINNET_PREFIX : &str = "addr";
+const TESTNET_PREFIX : &str = "addr_test";
+const HASH_LENGTH : usize = 28;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub enum Network {
+    Mainnet,
+    /// Any of the test networks (preview, preprod, ...), they all use network id 0
+    Testnet
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Network::Mainnet => write!(f, "mainnet"),
+            Network::Testnet => write!(f, "testnet")
+        }
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum AddressError {
+    /// The text is not valid bech32 (bad characters, mixed case or checksum)
+    InvalidBech32(String),
+    /// The human readable part is neither "addr" nor "addr_test"
+    UnknownPrefix(String),
+    /// The header describes something other than a Shelley payment address
+    UnsupportedType(u8),
+    /// The network id in the header is not 0 (testnet) or 1 (mainnet)
+    UnknownNetworkId(u8),
+    /// The prefix says one network and the header says another
+    NetworkMismatch { prefix: String, network: Network },
+    /// The payload does not have the length required by the address type
+    InvalidLength(usize)
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::InvalidBech32(e) => write!(f, "The address is not valid bech32: {e}"),
+            AddressError::UnknownPrefix(p) => write!(f, "Expected the address to start with 'addr' or 'addr_test', found '{p}'"),
+            AddressError::UnsupportedType(t) => write!(f, "Address type {t} is not a Shelley payment address"),
+            AddressError::UnknownNetworkId(n) => write!(f, "Unknown network id {n}"),
+            AddressError::NetworkMismatch { prefix, network } => write!(f, "The prefix '{prefix}' does not match the {network} network id in the address"),
+            AddressError::InvalidLength(n) => write!(f, "The address payload has an invalid length ({n} bytes)")
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Hash of a verification key or of a script
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum Credential {
+    Key(Vec<u8>),
+    Script(Vec<u8>)
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum StakeReference {
+    /// Enterprise addresses have no stake rights
+    None,
+    Credential(Credential),
+    /// Points at the certificate that registered the stake key
+    Pointer { slot: u64, tx_index: u64, cert_index: u64 }
+}
+
+/// A decoded Shelley payment address
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ShelleyAddress {
+    pub network: Network,
+    pub payment: Credential,
+    pub stake: StakeReference
+}
+
+fn credential(script:bool,hash:&[u8]) -> Credential {
+    if script { Credential::Script(hash.to_vec()) } else { Credential::Key(hash.to_vec()) }
+}
+
+/// Reads a natural number in the variable length encoding used by pointers
+fn read_natural(bytes:&mut &[u8]) -> Option<u64> {
+    let mut n : u64 = 0;
+    loop {
+        let (b,rest) = bytes.split_first()?;
+        *bytes = rest;
+        n = n.checked_mul(128)?.checked_add((b & 0x7f) as u64)?;
+        if b & 0x80 == 0 { return Some(n) }
+    }
+}
+
+fn write_natural(n:u64,out:&mut Vec<u8>) {
+    let mut groups = vec![(n & 0x7f) as u8];
+    let mut n = n >> 7;
+    while n > 0 {
+        groups.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+impl ShelleyAddress {
+
+    /// Decodes and validates a bech32 address such as "addr_test1..."
+    pub fn from_bech32(address:&str) -> Result<ShelleyAddress,AddressError> {
+        let (prefix,data,variant) = bech32::decode(address).map_err(|e|AddressError::InvalidBech32(e.to_string()))?;
+        if variant != Variant::Bech32 {
+            return Err(AddressError::InvalidBech32("expected bech32, found bech32m".to_string()))
+        }
+        let bytes = Vec::<u8>::from_base32(&data).map_err(|e|AddressError::InvalidBech32(e.to_string()))?;
+        let expected_network = match prefix.as_str() {
+            MAINNET_PREFIX => Network::Mainnet,
+            TESTNET_PREFIX => Network::Testnet,
+            _ => return Err(AddressError::UnknownPrefix(prefix))
+        };
+        let address = ShelleyAddress::from_bytes(&bytes)?;
+        if address.network != expected_network {
+            return Err(AddressError::NetworkMismatch { prefix, network: address.network })
+        }
+        Ok(address)
+    }
+
+    /// Decodes the binary form of an address (header byte followed by the payload)
+    pub fn from_bytes(bytes:&[u8]) -> Result<ShelleyAddress,AddressError> {
+        let Some((header,payload)) = bytes.split_first() else {
+            return Err(AddressError::InvalidLength(0))
+        };
+        let kind = header >> 4;
+        let network = match header & 0x0f {
+            0 => Network::Testnet,
+            1 => Network::Mainnet,
+            n => return Err(AddressError::UnknownNetworkId(n))
+        };
+        if kind > 7 {
+            return Err(AddressError::UnsupportedType(kind))
+        }
+        if payload.len() < HASH_LENGTH {
+            return Err(AddressError::InvalidLength(payload.len()))
+        }
+        let (payment,rest) = payload.split_at(HASH_LENGTH);
+        let payment = credential(kind & 1 == 1,payment);
+        let stake = match kind {
+            0..=3 if rest.len() == HASH_LENGTH => StakeReference::Credential(credential(kind & 2 == 2,rest)),
+            4 | 5 => {
+                let mut rest = rest;
+                let pointer = (read_natural(&mut rest),read_natural(&mut rest),read_natural(&mut rest));
+                match pointer {
+                    (Some(slot),Some(tx_index),Some(cert_index)) if rest.is_empty() =>
+                        StakeReference::Pointer { slot, tx_index, cert_index },
+                    _ => return Err(AddressError::InvalidLength(payload.len()))
+                }
+            },
+            6 | 7 if rest.is_empty() => StakeReference::None,
+            _ => return Err(AddressError::InvalidLength(payload.len()))
+        };
+        Ok(ShelleyAddress { network, payment, stake })
+    }
+
+    /// The binary form of the address
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let script = |c:&Credential| matches!(c,Credential::Script(_)) as u8;
+        let hash = |c:&Credential| match c { Credential::Key(h) | Credential::Script(h) => h.clone() };
+        let kind = match &self.stake {
+            StakeReference::Credential(s) => script(&self.payment) | script(s) << 1,
+            StakeReference::Pointer { .. } => 4 | script(&self.payment),
+            StakeReference::None => 6 | script(&self.payment)
+        };
+        let network_id = match self.network { Network::Mainnet => 1, Network::Testnet => 0 };
+        let mut out = vec![kind << 4 | network_id];
+        out.extend(hash(&self.payment));
+        match &self.stake {
+            StakeReference::Credential(s) => out.extend(hash(s)),
+            StakeReference::Pointer { slot, tx_index, cert_index } => {
+                write_natural(*slot,&mut out);
+                write_natural(*tx_index,&mut out);
+                write_natural(*cert_index,&mut out);
+            },
+            StakeReference::None => {}
+        }
+        out
+    }
+
+    /// The bech32 form of the address, using the prefix of its network
+    pub fn to_bech32(&self) -> String {
+        let prefix = match self.network { Network::Mainnet => MAINNET_PREFIX, Network::Testnet => TESTNET_PREFIX };
+        bech32::encode(prefix,self.to_bytes().to_base32(),Variant::Bech32)
+            .expect("the address prefixes are valid bech32")
+    }
+}
+
+/// Validates a bech32 address and finds out which network it belongs to
+pub fn network(address:&str) -> Result<Network,AddressError> {
+    Ok(ShelleyAddress::from_bech32(address)?.network)
+}
@@ -1,5 +1,8 @@
 use serde::{Serialize };
 
+/// Marlowe integers are unbounded, so amounts, bounds and timeouts use arbitrary precision integers
+pub use num_bigint::BigInt;
+
 use crate::{
     Impl_From_For_Vec, 
     Impl_From_For
@@ -21,82 +24,181 @@ pub(crate) enum AstNode {
     MarloweObservation(crate::types::marlowe::Observation),
     MarlowePayee(crate::types::marlowe::Payee),
     MarloweChoiceId(crate::types::marlowe::ChoiceId),
-    MarloweNumber(i64),
-    Null
+    MarloweNumber(BigInt),
+    /// The name of a hole, without the question mark
+    Hole(String)
+}
+
+/// A node of a contract that may not have been written yet.
+/// Holes keep their name, so that `?seller_deposit` and `?buyer_deposit`
+/// can be told apart and survive a round trip through the DSL.
+#[derive(Debug,Clone,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum Slot<T> {
+    Filled(T),
+    /// The name of the hole, without the question mark
+    Hole(String)
+}
+
+impl<T> Slot<T> {
+
+    pub fn hole(name:&str) -> Slot<T> {
+        Slot::Hole(name.to_string())
+    }
+
+    pub fn is_hole(&self) -> bool {
+        matches!(self,Slot::Hole(_))
+    }
+
+    /// The name of the hole, None if the slot is filled
+    pub fn hole_name(&self) -> Option<&str> {
+        match self {
+            Slot::Filled(_) => None,
+            Slot::Hole(name) => Some(name)
+        }
+    }
+
+    /// The node, None if it is a hole
+    pub fn as_ref(&self) -> Option<&T> {
+        match self {
+            Slot::Filled(x) => Some(x),
+            Slot::Hole(_) => None
+        }
+    }
+
+    /// The node, None if it is a hole
+    pub fn filled(self) -> Option<T> {
+        match self {
+            Slot::Filled(x) => Some(x),
+            Slot::Hole(_) => None
+        }
+    }
+
+    /// The node, panics if it is a hole
+    pub fn unwrap(self) -> T {
+        match self {
+            Slot::Filled(x) => x,
+            Slot::Hole(name) => panic!("called `Slot::unwrap()` on the hole '?{name}'")
+        }
+    }
+
+    pub fn as_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Slot::Filled(x) => Some(x),
+            Slot::Hole(_) => None
+        }
+    }
+
+    pub fn map<U>(self,f:impl FnOnce(T) -> U) -> Slot<U> {
+        match self {
+            Slot::Filled(x) => Slot::Filled(f(x)),
+            Slot::Hole(name) => Slot::Hole(name)
+        }
+    }
+
+    /// Like [`Slot::map`], but borrows the node. Holes keep their name.
+    pub fn map_ref<U>(&self,f:impl FnOnce(&T) -> U) -> Slot<U> {
+        match self {
+            Slot::Filled(x) => Slot::Filled(f(x)),
+            Slot::Hole(name) => Slot::Hole(name.clone())
+        }
+    }
+}
+
+impl<T,E> Slot<Result<T,E>> {
+    /// Turns a filled slot with an error into the error, like [`Option::transpose`]
+    pub fn transpose(self) -> Result<Slot<T>,E> {
+        match self {
+            Slot::Filled(x) => x.map(Slot::Filled),
+            Slot::Hole(name) => Ok(Slot::Hole(name))
+        }
+    }
+}
+
+impl<T:std::ops::Deref> Slot<T> {
+    /// The node behind the box, None if it is a hole
+    pub fn as_deref(&self) -> Option<&T::Target> {
+        self.as_ref().map(|x|x.deref())
+    }
+}
+
+impl<T> From<T> for Slot<T> {
+    fn from(x: T) -> Self {
+        Slot::Filled(x)
+    }
 }
 
 #[derive(Debug,Serialize)]
 #[serde(rename_all = "lowercase")]
 pub struct ValueId(pub String);
 
-#[derive(Debug)]
-pub struct Bound(pub i64,pub i64);
+#[derive(Debug,Clone,PartialEq)]
+pub struct Bound(pub BigInt,pub BigInt);
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq,Eq,PartialOrd,Ord)]
 pub struct ChoiceId { 
-    pub choice_owner : Option<Party>,
+    pub choice_owner : Slot<Party>,
     pub choice_name : String
 }
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum Payee {
-    Party(Option<Party>),
-    Account(Option<Party>)
+    Party(Slot<Party>),
+    Account(Slot<Party>)
 }
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum Observation { 
     ValueGT {
-        value: Option<Box<Value>>,
-        gt_than: Option<Box<Value>>
+        value: Slot<Box<Value>>,
+        gt_than: Slot<Box<Value>>
     },
     ValueGE {
-        value: Option<Box<Value>>,
-        ge_than: Option<Box<Value>>
+        value: Slot<Box<Value>>,
+        ge_than: Slot<Box<Value>>
     },
     ValueLT {
-        value: Option<Box<Value>>,
-        lt_than: Option<Box<Value>>
+        value: Slot<Box<Value>>,
+        lt_than: Slot<Box<Value>>
     },
     ValueLE {
-        value: Option<Box<Value>>,
-        le_than: Option<Box<Value>>
+        value: Slot<Box<Value>>,
+        le_than: Slot<Box<Value>>
     },
     ValueEQ {
-        value: Option<Box<Value>>,
-        equal_to: Option<Box<Value>>
+        value: Slot<Box<Value>>,
+        equal_to: Slot<Box<Value>>
     },
     True,
     False,
-    ChoseSomething(Option<ChoiceId>),        
+    ChoseSomething(Slot<ChoiceId>),        
     OrObs {
-        either: Option<Box<Observation>>,
-        or: Option<Box<Observation>>
+        either: Slot<Box<Observation>>,
+        or: Slot<Box<Observation>>
     },
     AndObs {
-        both: Option<Box<Observation>>,
-        and: Option<Box<Observation>>
+        both: Slot<Box<Observation>>,
+        and: Slot<Box<Observation>>
     },
     NotObs {
-        not: Option<Box<Observation>>
+        not: Slot<Box<Observation>>
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum Value {
     TimeIntervalStart,
     TimeIntervalEnd,
-    AvailableMoney(Option<Party>,Option<Token>), 
-    ConstantValue(i64), 
+    AvailableMoney(Slot<Party>,Slot<Token>), 
+    ConstantValue(BigInt), 
     ConstantParam(String), 
     UseValue(String), 
-    MulValue(Option<Box<Value>>,Option<Box<Value>>), 
-    DivValue(Option<Box<Value>>,Option<Box<Value>>), 
-    SubValue(Option<Box<Value>>,Option<Box<Value>>), 
-    AddValue(Option<Box<Value>>,Option<Box<Value>>), 
-    NegValue(Option<Box<Value>>), 
-    ChoiceValue(Option<ChoiceId>), 
-    Cond(Option<Observation>,Option<Box<Value>>,Option<Box<Value>>)
+    MulValue(Slot<Box<Value>>,Slot<Box<Value>>), 
+    DivValue(Slot<Box<Value>>,Slot<Box<Value>>), 
+    SubValue(Slot<Box<Value>>,Slot<Box<Value>>), 
+    AddValue(Slot<Box<Value>>,Slot<Box<Value>>), 
+    NegValue(Slot<Box<Value>>), 
+    ChoiceValue(Slot<ChoiceId>), 
+    Cond(Slot<Observation>,Slot<Box<Value>>,Slot<Box<Value>>)
 }
 
 #[derive(Serialize)]
@@ -105,68 +207,186 @@ pub enum BoolObs{
     False
 }
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq,Eq)]
 pub enum Token {
     ADA,
     Custom { token_name: String, currency_symbol: String }
 }
 
-#[derive(Debug)]
+impl Token {
+    /// The currency symbol and token name, ADA being `Token "" ""`
+    fn key(&self) -> (&str,&str) {
+        match self {
+            Token::ADA => ("",""),
+            Token::Custom { token_name, currency_symbol } => (currency_symbol,token_name)
+        }
+    }
+}
+
+/// Tokens are ordered like in the Haskell semantics: by currency symbol, then by token name.
+/// The order decides in which order accounts are refunded when a contract closes.
+impl Ord for Token {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key()).then_with(||matches!(self,Token::Custom{..}).cmp(&matches!(other,Token::Custom{..})))
+    }
+}
+
+impl PartialOrd for Token {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug,Clone,PartialEq,Eq)]
 pub enum Party {
     Role { role_token: String },
-    PK { pk_hash: String }
+    PK { pk_hash: String },
+    /// A bech32 encoded Shelley address, see [`crate::types::address`]
+    Address { address: String }
 }
 
-#[derive(Debug)]
+impl Party {
+    /// The position of the constructor in the Haskell semantics, where `PK` was later replaced by `Address`
+    fn rank(&self) -> u8 {
+        match self {
+            Party::PK { .. } => 0,
+            Party::Address { .. } => 1,
+            Party::Role { .. } => 2
+        }
+    }
+}
+
+/// Parties are ordered like in the Haskell semantics: addresses (and public keys) before roles.
+/// The order decides in which order accounts are refunded when a contract closes.
+impl Ord for Party {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (self,other) {
+            (Party::Role { role_token: a },Party::Role { role_token: b }) => a.cmp(b),
+            (Party::PK { pk_hash: a },Party::PK { pk_hash: b }) => a.cmp(b),
+            (Party::Address { address: a },Party::Address { address: b }) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank())
+        }
+    }
+}
+
+impl PartialOrd for Party {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
 pub enum Action {
-    Deposit { party: Option<Party>, of_token: Option<Token>, into_account: Option<Party>, deposits: Option<Value> },
-    Notify { notify_if: Option<Observation> },
-    Choice { for_choice: Option<ChoiceId>, choose_between: Vec<Option<Bound>> }
+    Deposit { party: Slot<Party>, of_token: Slot<Token>, into_account: Slot<Party>, deposits: Slot<Value> },
+    Notify { notify_if: Slot<Observation> },
+    Choice { for_choice: Slot<ChoiceId>, choose_between: Vec<Slot<Bound>> }
 }
 
-#[derive(Debug)]
-pub struct Case { 
-    pub then: Option<Box<Contract>>,
-    pub case: Option<Action>
+#[derive(Debug,Clone,PartialEq)]
+pub enum Case { 
+    Case {
+        case: Slot<Action>,
+        then: Slot<Box<Contract>>
+    },
+    /// A case whose continuation has been replaced by its hash (hex), see [`crate::merkleization`]
+    MerkleizedCase {
+        case: Slot<Action>,
+        then: String
+    }
+}
+
+impl Case {
+    /// The action of the case, regardless of whether it is merkleized
+    pub fn action(&self) -> Option<&Action> {
+        match self {
+            Case::Case { case, .. } | Case::MerkleizedCase { case, .. } => case.as_ref()
+        }
+    }
+    /// The continuation contract, None if it is a hole or if the case is merkleized
+    pub fn continuation(&self) -> Option<&Contract> {
+        match self {
+            Case::Case { then, .. } => then.as_deref(),
+            Case::MerkleizedCase { .. } => None
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum Timeout {
-    TimeConstant(i64),
+    TimeConstant(BigInt),
     TimeParam(String)
 }
 
-#[derive(Debug)]
+#[derive(Debug,Clone,PartialEq)]
 pub enum Contract {
     Close,
     When  { 
-        when: Vec<Option<Case>>, 
-        timeout_continuation: Option<Box<Contract>>,
-        timeout: Option<Timeout>
+        when: Vec<Slot<Case>>, 
+        timeout_continuation: Slot<Box<Contract>>,
+        timeout: Slot<Timeout>
     },
     If  { 
-        r#if: Option<Observation>, 
-        then: Option<Box<Contract>>, 
-        r#else: Option<Box<Contract>> 
+        r#if: Slot<Observation>, 
+        then: Slot<Box<Contract>>, 
+        r#else: Slot<Box<Contract>> 
     },
     Assert { 
-        assert: Option<Observation>, 
-        then: Option<Box<Contract>> 
+        assert: Slot<Observation>, 
+        then: Slot<Box<Contract>> 
     },
     Let { 
         r#let: String, 
-        be: Option<Box<Value>>, 
-        then: Option<Box<Contract>> 
+        be: Slot<Box<Value>>, 
+        then: Slot<Box<Contract>> 
     },
     Pay { 
-        from_account: Option<Party>, 
-        to: Option<Payee>, 
-        token: Option<Token>, 
-        pay: Option<Value>, 
-        then: Option<Box<Contract>>
+        from_account: Slot<Party>, 
+        to: Slot<Payee>, 
+        token: Slot<Token>, 
+        pay: Slot<Value>, 
+        then: Slot<Box<Contract>>
+    }
+}
+
+/// Start and end of the time interval in which a transaction is valid (POSIX milliseconds, inclusive)
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct TimeInterval {
+    pub start: i64,
+    pub end: i64
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum InputContent {
+    IDeposit { into_account: Party, input_from_party: Party, of_token: Token, that_deposits: BigInt },
+    IChoice { for_choice_id: ChoiceId, input_that_chooses_num: BigInt },
+    INotify
+}
+
+/// An input to a contract
+#[derive(Debug,Clone,PartialEq)]
+pub enum Input {
+    NormalInput(InputContent),
+    /// An input for a merkleized case, along with the hash of its continuation and the continuation itself
+    MerkleizedInput(InputContent,String,Box<Contract>)
+}
+
+impl Input {
+    /// The action being taken, regardless of how the input is wrapped
+    pub fn content(&self) -> &InputContent {
+        match self {
+            Input::NormalInput(content) |
+            Input::MerkleizedInput(content,_,_) => content
+        }
     }
 }
 
+/// A transaction is a time interval and a (possibly empty) list of inputs
+#[derive(Debug,Clone,PartialEq)]
+pub struct TransactionInput {
+    pub interval: TimeInterval,
+    pub inputs: Vec<Input>
+}
+
 Impl_From_For_Vec!(@MarloweCaseList,@MarloweCase,@Case);
 Impl_From_For_Vec!(@MarloweBoundList,@MarloweBound,@Bound);
 Impl_From_For!(@Payee,MarlowePayee);
@@ -180,7 +400,7 @@ Impl_From_For!(@Token,MarloweToken);
 Impl_From_For!(@Party,MarloweParty);
 Impl_From_For!(@Timeout,MarloweTimeout);
 Impl_From_For!(@Contract,MarloweContract);
-Impl_From_For!(@i64,MarloweNumber);
+Impl_From_For!(@BigInt,MarloweNumber);
 Impl_From_For!(@Observation,MarloweObservation);
 
 
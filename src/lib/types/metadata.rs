@@ -0,0 +1,248 @@
+// Metadata as stored by the Marlowe playground next to a contract.
+// Maps are encoded by the playground as lists of [key,value] pairs, but plain
+// json objects are accepted as well when deserializing.
+
+use std::collections::BTreeSet;
+
+use serde::{Serialize, Deserialize, Deserializer};
+
+use crate::types::marlowe::*;
+
+/// How a number should be presented to the user
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+#[serde(tag = "tag", content = "contents")]
+pub enum NumberFormat {
+    #[default]
+    DefaultFormat,
+    /// Number of decimals and a label, for example `[6,"₳"]` for lovelace presented as ADA
+    DecimalFormat(u32,String),
+    /// The number is a POSIX time in milliseconds
+    TimeFormat
+}
+
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoiceInfo {
+    pub choice_format: NumberFormat,
+    pub choice_description: String
+}
+
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueParameterInfo {
+    pub value_parameter_format: NumberFormat,
+    pub value_parameter_description: String
+}
+
+fn pairs<'de,D,T>(deserializer:D) -> Result<Vec<(String,T)>,D::Error>
+where D: Deserializer<'de>, T: Deserialize<'de> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Pairs<T> {
+        List(Vec<(String,T)>),
+        Object(serde_json::Map<String,serde_json::Value>)
+    }
+    match Pairs::<T>::deserialize(deserializer)? {
+        Pairs::List(x) => Ok(x),
+        Pairs::Object(o) => o.into_iter()
+            .map(|(k,v)| T::deserialize(v).map(|v|(k,v)).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+/// Contract metadata in the format used by the Marlowe playground
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaData {
+    pub contract_type: String,
+    pub contract_name: String,
+    pub contract_short_description: String,
+    pub contract_long_description: String,
+    #[serde(default,deserialize_with = "pairs")]
+    pub role_descriptions: Vec<(String,String)>,
+    #[serde(default,deserialize_with = "pairs")]
+    pub choice_info: Vec<(String,ChoiceInfo)>,
+    #[serde(default,deserialize_with = "pairs")]
+    pub value_parameter_info: Vec<(String,ValueParameterInfo)>,
+    #[serde(default,deserialize_with = "pairs",alias = "slotParameterDescriptions")]
+    pub time_parameter_descriptions: Vec<(String,String)>
+}
+
+/// A difference between the metadata and the contract it describes
+#[derive(Debug,Clone,PartialEq,Eq,PartialOrd,Ord)]
+pub enum MetaDataIssue {
+    /// A role is used in the contract but has no description
+    MissingRole(String),
+    /// A role is described but is not used in the contract
+    StaleRole(String),
+    MissingChoice(String),
+    StaleChoice(String),
+    MissingValueParameter(String),
+    StaleValueParameter(String),
+    MissingTimeParameter(String),
+    StaleTimeParameter(String)
+}
+
+impl std::fmt::Display for MetaDataIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetaDataIssue::MissingRole(x) => write!(f, "The role \"{x}\" has no description"),
+            MetaDataIssue::StaleRole(x) => write!(f, "The role \"{x}\" is described but not used in the contract"),
+            MetaDataIssue::MissingChoice(x) => write!(f, "The choice \"{x}\" has no description"),
+            MetaDataIssue::StaleChoice(x) => write!(f, "The choice \"{x}\" is described but not used in the contract"),
+            MetaDataIssue::MissingValueParameter(x) => write!(f, "The value parameter \"{x}\" has no description"),
+            MetaDataIssue::StaleValueParameter(x) => write!(f, "The value parameter \"{x}\" is described but not used in the contract"),
+            MetaDataIssue::MissingTimeParameter(x) => write!(f, "The time parameter \"{x}\" has no description"),
+            MetaDataIssue::StaleTimeParameter(x) => write!(f, "The time parameter \"{x}\" is described but not used in the contract"),
+        }
+    }
+}
+
+/// Roles and choice names used in a contract
+#[derive(Default)]
+struct Names {
+    roles: BTreeSet<String>,
+    choices: BTreeSet<String>
+}
+
+impl Names {
+    fn party(&mut self,party:&Slot<Party>) {
+        if let Slot::Filled(Party::Role { role_token }) = party {
+            self.roles.insert(role_token.clone());
+        }
+    }
+    fn choice(&mut self,choice:&Slot<ChoiceId>) {
+        if let Slot::Filled(c) = choice {
+            self.choices.insert(c.choice_name.clone());
+            self.party(&c.choice_owner)
+        }
+    }
+    fn payee(&mut self,payee:&Slot<Payee>) {
+        match payee {
+            Slot::Filled(Payee::Party(p)) | Slot::Filled(Payee::Account(p)) => self.party(p),
+            Slot::Hole(_) => {}
+        }
+    }
+    fn value(&mut self,value:Option<&Value>) {
+        match value {
+            Some(Value::AvailableMoney(p,_)) => self.party(p),
+            Some(Value::ChoiceValue(c)) => self.choice(c),
+            Some(Value::MulValue(a,b)) | Some(Value::DivValue(a,b)) |
+            Some(Value::SubValue(a,b)) | Some(Value::AddValue(a,b)) => {
+                self.value(a.as_deref());
+                self.value(b.as_deref())
+            },
+            Some(Value::NegValue(a)) => self.value(a.as_deref()),
+            Some(Value::Cond(o,a,b)) => {
+                if let Slot::Filled(o) = o { self.observation(o) }
+                self.value(a.as_deref());
+                self.value(b.as_deref())
+            },
+            _ => {}
+        }
+    }
+    fn observations(&mut self,observation:&Slot<Box<Observation>>) {
+        if let Slot::Filled(o) = observation { self.observation(o) }
+    }
+    fn observation(&mut self,observation:&Observation) {
+        match observation {
+            Observation::AndObs { both: a, and: b } | Observation::OrObs { either: a, or: b } => {
+                self.observations(a);
+                self.observations(b)
+            },
+            Observation::NotObs { not } => self.observations(not),
+            Observation::ChoseSomething(c) => self.choice(c),
+            Observation::ValueGE { value: a, ge_than: b } |
+            Observation::ValueGT { value: a, gt_than: b } |
+            Observation::ValueLT { value: a, lt_than: b } |
+            Observation::ValueLE { value: a, le_than: b } |
+            Observation::ValueEQ { value: a, equal_to: b } => {
+                self.value(a.as_deref());
+                self.value(b.as_deref())
+            },
+            Observation::True | Observation::False => {}
+        }
+    }
+    fn contracts(&mut self,contract:&Slot<Box<Contract>>) {
+        if let Slot::Filled(c) = contract { self.contract(c) }
+    }
+    fn contract(&mut self,contract:&Contract) {
+        match contract {
+            Contract::Close => {},
+            Contract::When { when, timeout_continuation, .. } => {
+                for case in when.iter().filter_map(|x|x.as_ref()) {
+                    match case.action() {
+                        Some(Action::Deposit { party, into_account, deposits, .. }) => {
+                            self.party(party);
+                            self.party(into_account);
+                            self.value(deposits.as_ref())
+                        },
+                        Some(Action::Notify { notify_if: Slot::Filled(o) }) => self.observation(o),
+                        Some(Action::Choice { for_choice, .. }) => self.choice(for_choice),
+                        _ => {}
+                    }
+                    if let Some(c) = case.continuation() { self.contract(c) }
+                }
+                if let Slot::Filled(c) = timeout_continuation { self.contract(c) }
+            },
+            Contract::If { r#if, then, r#else } => {
+                if let Slot::Filled(o) = r#if { self.observation(o) }
+                self.contracts(then);
+                self.contracts(r#else)
+            },
+            Contract::Assert { assert, then } => {
+                if let Slot::Filled(o) = assert { self.observation(o) }
+                self.contracts(then)
+            },
+            Contract::Let { be, then, .. } => {
+                self.value(be.as_deref());
+                self.contracts(then)
+            },
+            Contract::Pay { from_account, to, pay, then, .. } => {
+                self.party(from_account);
+                self.payee(to);
+                self.value(pay.as_ref());
+                self.contracts(then)
+            }
+        }
+    }
+}
+
+fn compare<T>(
+    used:&BTreeSet<String>,described:&[(String,T)],
+    missing:fn(String) -> MetaDataIssue,stale:fn(String) -> MetaDataIssue,
+    out:&mut Vec<MetaDataIssue>
+) {
+    out.extend(used.iter().filter(|x|!described.iter().any(|(k,_)|k == *x)).cloned().map(missing));
+    out.extend(described.iter().filter(|(k,_)|!used.contains(k)).map(|(k,_)|stale(k.clone())));
+}
+
+impl MetaData {
+
+    /// Parses metadata from the json format used by the Marlowe playground
+    pub fn from_json(json:&str) -> Result<MetaData,serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Serializes the metadata to the json format used by the Marlowe playground
+    pub fn to_json(&self) -> Result<String,serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Compares the metadata with a contract, finding roles, choices and parameters
+    /// that are used in the contract but lack a description, as well as descriptions
+    /// of things that are not used in the contract.
+    pub fn check(&self,contract:&Contract) -> Vec<MetaDataIssue> {
+        let mut names = Names::default();
+        names.contract(contract);
+        let params = crate::analysis::parameters(contract);
+        let time_params = params.time_params.into_iter().map(|x|x.name).collect();
+        let value_params = params.value_params.into_iter().map(|x|x.name).collect();
+        let mut out = vec![];
+        compare(&names.roles,&self.role_descriptions,MetaDataIssue::MissingRole,MetaDataIssue::StaleRole,&mut out);
+        compare(&names.choices,&self.choice_info,MetaDataIssue::MissingChoice,MetaDataIssue::StaleChoice,&mut out);
+        compare(&value_params,&self.value_parameter_info,MetaDataIssue::MissingValueParameter,MetaDataIssue::StaleValueParameter,&mut out);
+        compare(&time_params,&self.time_parameter_descriptions,MetaDataIssue::MissingTimeParameter,MetaDataIssue::StaleTimeParameter,&mut out);
+        out
+    }
+}
@@ -1,2 +1,10 @@
 /// All types required for representing a Marlowe contract
 pub mod marlowe;
+/// Bech32 payment addresses used by Address parties
+pub mod address;
+/// Instantiating contract templates with values for their parameters
+pub mod template;
+/// Contract metadata as used by the Marlowe playground
+pub mod metadata;
+/// Reading and writing projects saved from the Marlowe playground
+pub mod project;
@@ -0,0 +1,54 @@
+// Projects saved from the Marlowe playground: the contract source together with its metadata.
+// Fields that are not used by this library (editor state, version info etc.) are kept
+// as they are so that a project can be written back without losing anything.
+
+use serde::{Serialize, Deserialize};
+
+use crate::parsing::error::ParseError;
+use crate::types::marlowe::Contract;
+use crate::types::metadata::MetaData;
+
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    #[serde(default)]
+    pub project_name: String,
+    /// The contract in the Marlowe DSL
+    #[serde(alias = "marlowe")]
+    pub contract: String,
+    #[serde(default)]
+    pub metadata: MetaData,
+    /// Everything else that was found in the project file
+    #[serde(flatten)]
+    pub other: serde_json::Map<String,serde_json::Value>
+}
+
+impl Project {
+
+    /// Creates a project containing the given contract
+    pub fn new(project_name:&str,contract:Contract,metadata:MetaData) -> Project {
+        let mut project = Project { project_name: project_name.to_string(), metadata, ..Default::default() };
+        project.set_contract(contract);
+        project
+    }
+
+    /// Reads a project saved from the Marlowe playground
+    pub fn from_json(json:&str) -> Result<Project,serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Writes the project in the format used by the Marlowe playground
+    pub fn to_json(&self) -> Result<String,serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Parses the contract of the project
+    pub fn contract(&self) -> Result<Contract,ParseError> {
+        crate::parsing::deserialization::deserialize(&self.contract)
+    }
+
+    /// Replaces the source of the project with the given contract
+    pub fn set_contract(&mut self,contract:Contract) {
+        self.contract = crate::parsing::serialization::marlowe::serialize(contract)
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use crate::types::marlowe::*;
+
+/// Values for the parameters of a contract template
+#[derive(Debug,Clone,PartialEq,Default)]
+pub struct TemplateInputs {
+    /// Values for Timeout::TimeParam (POSIX milliseconds)
+    pub time_params: HashMap<String,BigInt>,
+    /// Values for Value::ConstantParam
+    pub value_params: HashMap<String,BigInt>,
+    /// Fail if the contract uses a parameter that has no value,
+    /// otherwise such parameters are left as they are
+    pub error_on_missing: bool,
+    /// Fail if a value is given for a parameter that the contract does not use
+    pub error_on_unused: bool
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum InstantiationError {
+    MissingTimeParam(String),
+    MissingValueParam(String),
+    UnusedTimeParam(String),
+    UnusedValueParam(String),
+    /// A value was given for a name that is not a parameter of the contract
+    UnknownParam(String),
+    /// The text could not be understood as a point in time
+    InvalidTime(String),
+    /// The text could not be understood as an integer or an amount of ADA/lovelace
+    InvalidAmount(String),
+    /// A parameters file is not valid JSON/TOML or does not have the expected layout
+    InvalidParamsFile(String)
+}
+
+impl std::fmt::Display for InstantiationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstantiationError::MissingTimeParam(name) =>
+                write!(f, "No value was given for the time parameter '{name}'."),
+            InstantiationError::MissingValueParam(name) =>
+                write!(f, "No value was given for the constant parameter '{name}'."),
+            InstantiationError::UnusedTimeParam(name) =>
+                write!(f, "The contract has no time parameter named '{name}'."),
+            InstantiationError::UnusedValueParam(name) =>
+                write!(f, "The contract has no constant parameter named '{name}'."),
+            InstantiationError::UnknownParam(name) =>
+                write!(f, "The contract has no parameter named '{name}'."),
+            InstantiationError::InvalidTime(text) =>
+                write!(f, "'{text}' is not a valid time. Use POSIX milliseconds, RFC-3339 (2022-03-04T15:41:31Z), 2022-03-04@15:41:31 or a relative offset such as +3d."),
+            InstantiationError::InvalidAmount(text) =>
+                write!(f, "'{text}' is not a valid amount. Use an integer, optionally followed by ADA or lovelace."),
+            InstantiationError::InvalidParamsFile(message) =>
+                write!(f, "Invalid parameters file: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InstantiationError {}
+
+fn value(v:&Value,inputs:&TemplateInputs) -> Value {
+    let sub = |x:&Slot<Box<Value>>| x.map_ref(|x|Box::new(value(x,inputs)));
+    match v {
+        Value::ConstantParam(name) => match inputs.value_params.get(name) {
+            Some(n) => Value::ConstantValue(n.clone()),
+            None => v.clone()
+        },
+        Value::MulValue(a,b) => Value::MulValue(sub(a),sub(b)),
+        Value::DivValue(a,b) => Value::DivValue(sub(a),sub(b)),
+        Value::SubValue(a,b) => Value::SubValue(sub(a),sub(b)),
+        Value::AddValue(a,b) => Value::AddValue(sub(a),sub(b)),
+        Value::NegValue(a) => Value::NegValue(sub(a)),
+        Value::Cond(o,a,b) => Value::Cond(o.map_ref(|o|observation(o,inputs)),sub(a),sub(b)),
+        _ => v.clone()
+    }
+}
+
+fn observation(o:&Observation,inputs:&TemplateInputs) -> Observation {
+    let sub = |x:&Slot<Box<Observation>>| x.map_ref(|x|Box::new(observation(x,inputs)));
+    let val = |x:&Slot<Box<Value>>| x.map_ref(|x|Box::new(value(x,inputs)));
+    match o {
+        Observation::AndObs { both, and } => Observation::AndObs { both: sub(both), and: sub(and) },
+        Observation::OrObs { either, or } => Observation::OrObs { either: sub(either), or: sub(or) },
+        Observation::NotObs { not } => Observation::NotObs { not: sub(not) },
+        Observation::ValueGE { value, ge_than } => Observation::ValueGE { value: val(value), ge_than: val(ge_than) },
+        Observation::ValueGT { value, gt_than } => Observation::ValueGT { value: val(value), gt_than: val(gt_than) },
+        Observation::ValueLT { value, lt_than } => Observation::ValueLT { value: val(value), lt_than: val(lt_than) },
+        Observation::ValueLE { value, le_than } => Observation::ValueLE { value: val(value), le_than: val(le_than) },
+        Observation::ValueEQ { value, equal_to } => Observation::ValueEQ { value: val(value), equal_to: val(equal_to) },
+        _ => o.clone()
+    }
+}
+
+fn action(a:&Action,inputs:&TemplateInputs) -> Action {
+    match a {
+        Action::Deposit { party, of_token, into_account, deposits } => Action::Deposit {
+            party: party.clone(),
+            of_token: of_token.clone(),
+            into_account: into_account.clone(),
+            deposits: deposits.map_ref(|v|value(v,inputs))
+        },
+        Action::Notify { notify_if } => Action::Notify { notify_if: notify_if.map_ref(|o|observation(o,inputs)) },
+        Action::Choice { .. } => a.clone()
+    }
+}
+
+fn contract(c:&Contract,inputs:&TemplateInputs) -> Contract {
+    let sub = |x:&Slot<Box<Contract>>| x.map_ref(|x|Box::new(contract(x,inputs)));
+    match c {
+        Contract::Close => Contract::Close,
+        Contract::When { when, timeout_continuation, timeout } => Contract::When {
+            when: when.iter().map(|case|case.map_ref(|case|match case {
+                Case::Case { case, then } => Case::Case {
+                    case: case.map_ref(|a|action(a,inputs)),
+                    then: sub(then)
+                },
+                Case::MerkleizedCase { case, then } => Case::MerkleizedCase {
+                    case: case.map_ref(|a|action(a,inputs)),
+                    then: then.clone()
+                }
+            })).collect(),
+            timeout_continuation: sub(timeout_continuation),
+            timeout: match timeout {
+                Slot::Filled(Timeout::TimeParam(name)) if inputs.time_params.contains_key(name) =>
+                    Slot::Filled(Timeout::TimeConstant(inputs.time_params[name].clone())),
+                _ => timeout.clone()
+            }
+        },
+        Contract::If { r#if, then, r#else } => Contract::If {
+            r#if: r#if.map_ref(|o|observation(o,inputs)),
+            then: sub(then),
+            r#else: sub(r#else)
+        },
+        Contract::Assert { assert, then } => Contract::Assert {
+            assert: assert.map_ref(|o|observation(o,inputs)),
+            then: sub(then)
+        },
+        Contract::Let { r#let, be, then } => Contract::Let {
+            r#let: r#let.clone(),
+            be: be.map_ref(|v|Box::new(value(v,inputs))),
+            then: sub(then)
+        },
+        Contract::Pay { from_account, to, token, pay, then } => Contract::Pay {
+            from_account: from_account.clone(),
+            to: to.clone(),
+            token: token.clone(),
+            pay: pay.map_ref(|v|value(v,inputs)),
+            then: sub(then)
+        }
+    }
+}
+
+/// Parses a time parameter value into POSIX milliseconds. Accepted formats are
+/// POSIX milliseconds (`1646408491000`), RFC-3339 (`2022-03-04T15:41:31Z`),
+/// ISO-8601 dates and times without an offset which are taken to be UTC (`2022-03-04T15:41:31`, `2022-03-04`),
+/// `2022-03-04@15:41:31` (UTC) and offsets relative to the current time (`+3d`, `+12h`, `+30m`, `+45s`, `+1w`, `+500ms`).
+/// Times given in milliseconds or as an offset are not limited to the 64 bit range.
+pub fn parse_time(text:&str) -> Result<BigInt,InstantiationError> {
+    parse_time_relative_to(text,chrono::Utc::now().timestamp_millis())
+}
+
+/// Same as [`parse_time`], but relative offsets are added to the given POSIX time (milliseconds) instead of the current time
+pub fn parse_time_relative_to(text:&str,now:i64) -> Result<BigInt,InstantiationError> {
+    let invalid = || InstantiationError::InvalidTime(text.to_string());
+    let text = text.trim();
+    if let Ok(n) = text.parse::<BigInt>() {
+        return Ok(n)
+    }
+    if let Some(offset) = text.strip_prefix('+') {
+        let split = offset.find(|c:char|!c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (amount,unit) = offset.split_at(split);
+        let amount = amount.parse::<BigInt>().map_err(|_|invalid())?;
+        let unit_ms : i64 = match unit {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            "w" => 7 * 24 * 60 * 60 * 1000,
+            _ => return Err(invalid())
+        };
+        return Ok(amount * unit_ms + now)
+    }
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Ok(t.timestamp_millis().into())
+    }
+    for format in ["%Y-%m-%d@%H:%M:%S","%Y-%m-%dT%H:%M:%S%.f","%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(t) = chrono::NaiveDateTime::parse_from_str(text,format) {
+            return Ok(t.and_utc().timestamp_millis().into())
+        }
+    }
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(text,"%Y-%m-%d") {
+        return Ok(d.and_hms_opt(0,0,0).ok_or_else(invalid)?.and_utc().timestamp_millis().into())
+    }
+    Err(invalid())
+}
+
+/// Parses a value parameter. Accepts integers, optionally followed by a unit:
+/// `lovelace` or `ADA` (`1.5 ADA` is 1500000 lovelace).
+pub fn parse_amount(text:&str) -> Result<BigInt,InstantiationError> {
+    let invalid = || InstantiationError::InvalidAmount(text.to_string());
+    let trimmed = text.trim();
+    let split = trimmed.find(|c:char|c.is_alphabetic()).unwrap_or(trimmed.len());
+    let (number,unit) = trimmed.split_at(split);
+    let number = number.trim();
+    match unit.to_lowercase().as_str() {
+        "" | "lovelace" => number.parse::<BigInt>().map_err(|_|invalid()),
+        "ada" => {
+            let (whole,fraction) = number.split_once('.').unwrap_or((number,""));
+            if fraction.len() > 6 || !fraction.chars().all(|c|c.is_ascii_digit()) {
+                return Err(invalid())
+            }
+            let negative = whole.starts_with('-');
+            let whole = whole.parse::<BigInt>().map_err(|_|invalid())?;
+            let fraction = format!("{fraction:0<6}").parse::<BigInt>().map_err(|_|invalid())?;
+            let whole = whole * 1_000_000;
+            Ok(if negative { whole - fraction } else { whole + fraction })
+        },
+        _ => Err(invalid())
+    }
+}
+
+impl TemplateInputs {
+    /// Creates the inputs for a contract from textual values, using the parameters
+    /// of the contract to decide whether each value is a time (see [`parse_time`])
+    /// or an amount (see [`parse_amount`]).
+    pub fn from_text<'a>(contract:&Contract,values:impl IntoIterator<Item=(&'a str,&'a str)>) -> Result<TemplateInputs,InstantiationError> {
+        let params = crate::analysis::parameters(contract);
+        let mut inputs = TemplateInputs::default();
+        for (name,text) in values {
+            if params.time_params.iter().any(|p|p.name == name) {
+                inputs.time_params.insert(name.to_string(),parse_time(text)?);
+            } else if params.value_params.iter().any(|p|p.name == name) {
+                inputs.value_params.insert(name.to_string(),parse_amount(text)?);
+            } else {
+                return Err(InstantiationError::UnknownParam(name.to_string()))
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+fn params_section(section:Option<&serde_json::Value>,name:&str) -> Result<Vec<(String,String)>,InstantiationError> {
+    let section = match section {
+        None => return Ok(vec![]),
+        Some(serde_json::Value::Object(o)) => o,
+        Some(_) => return Err(InstantiationError::InvalidParamsFile(format!("'{name}' must be a table of parameter names and values.")))
+    };
+    section.iter().map(|(key,value)| match value {
+        serde_json::Value::String(s) => Ok((key.clone(),s.clone())),
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => Ok((key.clone(),n.to_string())),
+        serde_json::Value::Number(_) => Err(InstantiationError::InvalidParamsFile(format!("the value of '{key}' must be an integer, numbers outside the 64 bit range must be given as strings."))),
+        _ => Err(InstantiationError::InvalidParamsFile(format!("the value of '{key}' must be a number or a string.")))
+    }).collect()
+}
+
+impl TemplateInputs {
+
+    /// Reads inputs from a parsed parameters file, see [`TemplateInputs::from_json`]
+    fn from_params_value(value:serde_json::Value) -> Result<TemplateInputs,InstantiationError> {
+        let o = match &value {
+            serde_json::Value::Object(o) => o,
+            _ => return Err(InstantiationError::InvalidParamsFile("expected a table with the sections 'time_params' and 'value_params'.".into()))
+        };
+        if let Some(key) = o.keys().find(|x|*x != "time_params" && *x != "value_params") {
+            return Err(InstantiationError::InvalidParamsFile(format!("unknown section '{key}', expected 'time_params' or 'value_params'.")))
+        }
+        let mut inputs = TemplateInputs { error_on_missing: true, error_on_unused: true, ..Default::default() };
+        for (name,text) in params_section(o.get("time_params"),"time_params")? {
+            inputs.time_params.insert(name,parse_time(&text)?);
+        }
+        for (name,text) in params_section(o.get("value_params"),"value_params")? {
+            inputs.value_params.insert(name,parse_amount(&text)?);
+        }
+        Ok(inputs)
+    }
+
+    /// Reads inputs from a JSON parameters file with the sections `time_params` and `value_params`,
+    /// each mapping parameter names to values in any of the formats accepted by [`parse_time`] and [`parse_amount`]:
+    /// ```json
+    /// { "time_params": { "Payment deadline": "2022-03-04T15:41:31Z" }, "value_params": { "Price": "5 ADA" } }
+    /// ```
+    /// Since a parameters file is expected to contain the complete input for a contract, 
+    /// both `error_on_missing` and `error_on_unused` are set.
+    pub fn from_json(text:&str) -> Result<TemplateInputs,InstantiationError> {
+        let value = serde_json::from_str(text).map_err(|e|InstantiationError::InvalidParamsFile(e.to_string()))?;
+        TemplateInputs::from_params_value(value)
+    }
+
+    /// Same as [`TemplateInputs::from_json`], but for TOML:
+    /// ```toml
+    /// [time_params]
+    /// "Payment deadline" = "2022-03-04T15:41:31Z"
+    /// [value_params]
+    /// Price = "5 ADA"
+    /// ```
+    pub fn from_toml(text:&str) -> Result<TemplateInputs,InstantiationError> {
+        let value = toml::from_str(text).map_err(|e|InstantiationError::InvalidParamsFile(e.to_string()))?;
+        TemplateInputs::from_params_value(value)
+    }
+
+    /// Reads a parameters file, as TOML if the file name ends with .toml and otherwise as JSON
+    pub fn from_file(path:&std::path::Path) -> Result<TemplateInputs,InstantiationError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e|InstantiationError::InvalidParamsFile(format!("{}: {e}",path.display())))?;
+        match path.extension().and_then(|x|x.to_str()) {
+            Some("toml") => TemplateInputs::from_toml(&text),
+            _ => TemplateInputs::from_json(&text)
+        }
+    }
+}
+
+impl Contract {
+    /// Creates a copy of the contract where the time and constant parameters
+    /// have been replaced with the values given in the inputs.
+    pub fn instantiate(&self,inputs:&TemplateInputs) -> Result<Contract,InstantiationError> {
+        let params = crate::analysis::parameters(self);
+        let has = |params:&Vec<crate::analysis::Parameter>,name:&String| params.iter().any(|p|&p.name == name);
+        if inputs.error_on_missing {
+            if let Some(p) = params.time_params.iter().find(|p|!inputs.time_params.contains_key(&p.name)) {
+                return Err(InstantiationError::MissingTimeParam(p.name.clone()))
+            }
+            if let Some(p) = params.value_params.iter().find(|p|!inputs.value_params.contains_key(&p.name)) {
+                return Err(InstantiationError::MissingValueParam(p.name.clone()))
+            }
+        }
+        if inputs.error_on_unused {
+            if let Some(name) = inputs.time_params.keys().find(|x|!has(&params.time_params,x)) {
+                return Err(InstantiationError::UnusedTimeParam(name.clone()))
+            }
+            if let Some(name) = inputs.value_params.keys().find(|x|!has(&params.value_params,x)) {
+                return Err(InstantiationError::UnusedValueParam(name.clone()))
+            }
+        }
+        Ok(contract(self,inputs))
+    }
+}
@@ -0,0 +1,121 @@
+// Checks that the identifiers in a contract can actually exist on chain:
+// key hashes and currency symbols are 28 byte blake2b-224 hashes (hex encoded)
+// and token names are at most 32 bytes.
+// The checks are opt-in for the DSL and json, which also carry contracts written for
+// older tools (the playground emits 32 byte placeholder key hashes for example):
+// use `validate` directly or parse with `deserialize_validated`. The parser only checks
+// addresses, and the Plutus Data encoder runs all checks as it produces on chain data.
+
+use crate::analysis::{visit_leaves, Leaf};
+use crate::parsing::spans::NodePath;
+use crate::types::address::AddressError;
+use crate::types::marlowe::*;
+
+/// Byte length of public key hashes and currency symbols (minting policy hashes)
+pub const HASH_LENGTH : usize = 28;
+
+/// Maximum byte length of token names
+pub const MAX_TOKEN_NAME_LENGTH : usize = 32;
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum ValidationErrorKind {
+    /// A PK party whose hash is not 28 bytes of hex
+    InvalidPubKeyHash(String),
+    /// An Address party that is not a valid bech32 Shelley address
+    InvalidAddress { address: String, error: AddressError },
+    /// A currency symbol that is not 28 bytes of hex
+    InvalidCurrencySymbol(String),
+    /// A token name longer than 32 bytes once encoded as utf-8
+    TokenNameTooLong(String),
+    /// A custom token with an empty currency symbol, which is what ADA uses.
+    /// Use `Token::ADA` for ADA (the token name of which must also be empty).
+    MalformedAda { token_name: String }
+}
+
+impl std::fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationErrorKind::InvalidPubKeyHash(x) =>
+                write!(f, "'{x}' is not a valid public key hash, expected {} hex encoded bytes",HASH_LENGTH),
+            ValidationErrorKind::InvalidAddress { address, error } =>
+                write!(f, "'{address}' is not a valid address: {error}"),
+            ValidationErrorKind::InvalidCurrencySymbol(x) =>
+                write!(f, "'{x}' is not a valid currency symbol, expected {} hex encoded bytes",HASH_LENGTH),
+            ValidationErrorKind::TokenNameTooLong(x) =>
+                write!(f, "The token name '{x}' is longer than {} bytes",MAX_TOKEN_NAME_LENGTH),
+            ValidationErrorKind::MalformedAda { token_name } =>
+                write!(f, "The token (Token \"\" \"{token_name}\") has no currency symbol, but is not ADA"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationErrorKind {}
+
+/// An invalid identifier in a contract
+#[derive(Debug,Clone,PartialEq)]
+pub struct ValidationError {
+    /// Location of the offending party or token, see [`NodePath`]
+    pub path: NodePath,
+    pub kind: ValidationErrorKind
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at {:?})",self.kind,self.path)
+    }
+}
+
+fn is_hash(text:&str) -> bool {
+    matches!(hex::decode(text),Ok(bytes) if bytes.len() == HASH_LENGTH)
+}
+
+/// Token names are text and go on chain as their utf-8 bytes, see `ToPlutusData for Token`
+fn token_name_fits(name:&str) -> bool {
+    name.len() <= MAX_TOKEN_NAME_LENGTH
+}
+
+/// Checks the key hash of a PK party, or the address of an Address party
+pub fn validate_party(party:&Party) -> Result<(),ValidationErrorKind> {
+    match party {
+        Party::Role { .. } => Ok(()),
+        Party::PK { pk_hash } if is_hash(pk_hash) => Ok(()),
+        Party::PK { pk_hash } => Err(ValidationErrorKind::InvalidPubKeyHash(pk_hash.clone())),
+        Party::Address { address } => match crate::types::address::network(address) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(ValidationErrorKind::InvalidAddress { address: address.clone(), error })
+        }
+    }
+}
+
+/// Checks the currency symbol and token name of a custom token
+pub fn validate_token(token:&Token) -> Result<(),ValidationErrorKind> {
+    match token {
+        Token::ADA => Ok(()),
+        Token::Custom { currency_symbol, token_name } => {
+            if currency_symbol.is_empty() {
+                Err(ValidationErrorKind::MalformedAda { token_name: token_name.clone() })
+            } else if !is_hash(currency_symbol) {
+                Err(ValidationErrorKind::InvalidCurrencySymbol(currency_symbol.clone()))
+            } else if !token_name_fits(token_name) {
+                Err(ValidationErrorKind::TokenNameTooLong(token_name.clone()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Finds all invalid parties and tokens in a contract, in the order they appear in the contract
+pub fn validate(contract:&Contract) -> Vec<ValidationError> {
+    let mut out = vec![];
+    visit_leaves(contract,&mut |leaf,path| {
+        let result = match leaf {
+            Leaf::Party(p) => validate_party(p),
+            Leaf::Token(t) => validate_token(t)
+        };
+        if let Err(kind) = result {
+            out.push(ValidationError { path: path.to_vec(), kind })
+        }
+    });
+    out
+}